@@ -35,4 +35,7 @@ pub enum AdminCommand {
     
     #[command(description = "🏢 My groups")]
     Group,
+
+    #[command(description = "📢 Broadcast now")]
+    Broadcast,
 }
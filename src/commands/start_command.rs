@@ -1,4 +1,5 @@
 use crate::commands::AdminCommand;
+use crate::my_handler::broadcast::init_broadcast;
 use crate::service::{group, user, Db};
 use crate::{HandlerResult, MainDialogue, State};
 use log::info;
@@ -44,6 +45,9 @@ pub async fn enter(
         AdminCommand::Group => {
             group_menu(bot, msg.clone(), dialogue, db).await?;
         }
+        AdminCommand::Broadcast => {
+            init_broadcast(bot, msg, dialogue, db).await?;
+        }
     }
     Ok(())
 }
@@ -89,6 +93,7 @@ pub fn poll_msg_menu() -> InlineKeyboardMarkup {
     let admin_button = vec![
         ("➕ Add", "add_poll_message"),
         ("📝 List", "list_poll_message"),
+        ("🔀 Pools", "pool_list"),
     ];
     let admin_button: Vec<InlineKeyboardButton> = admin_button
         .into_iter()
@@ -120,7 +125,7 @@ pub async fn group_menu(bot: Bot, msg: Message, dialogue: MainDialogue, db: Db)
 pub async fn group_buttons(db: Db) -> Option<InlineKeyboardMarkup> {
     let my_groups = group::new(db).all().await;
 
-    if my_groups.len() <= 0 {
+    if my_groups.is_empty() {
         return None;
     }
 
@@ -133,5 +138,6 @@ pub async fn group_buttons(db: Db) -> Option<InlineKeyboardMarkup> {
             format!("group_{}_{}", i.id, i.group_name),
         )]);
     }
+    group_but.push(vec![InlineKeyboardButton::callback("🏷 Tags", "tag_list")]);
     Some(InlineKeyboardMarkup::new(group_but))
 }
@@ -1,18 +1,17 @@
-use crate::service::{polling_msg, Db};
-use chrono::Local;
+use crate::service::msg::MsgContent;
+use crate::service::send_queue::SendQueue;
 use log::info;
 use serde::{Deserialize, Serialize};
 use std::panic;
 use teloxide::dispatching::dialogue::serializer::Json;
 use teloxide::dispatching::dialogue::{ErasedStorage, SqliteStorage, Storage};
 use teloxide::dispatching::Dispatcher;
-use teloxide::payloads::SendMessageSetters;
-use teloxide::prelude::{Dialogue, Requester};
-use teloxide::types::{ChatId,ParseMode};
+use teloxide::prelude::Dialogue;
 use teloxide::{dptree, Bot};
 
 pub mod commands;
 pub mod my_handler;
+mod scheduler;
 
 pub mod service;
 
@@ -29,13 +28,42 @@ pub enum State {
 
     // Message module
     AddPollingMsg,           // add the message for poll push.
-    AddPollingTitle(String), // add the title for the message.
+    AddPollingAlbum{media_group_id: String, content: MsgContent}, // collect the album parts, then the title.
+    AddPollingTitle(String), // add the title for the text message, the dialogues stored before the media messages.
+    AddPollingContentTitle(MsgContent), // add the title for the message.
+    AddPollingButtons{content: MsgContent, title: String}, // add the buttons, then preview.
+    AddPollingConfirm{content: MsgContent, title: String}, // confirm the preview, then save.
+    EditPollingText{msg_db_id: i64},  // edit the text (caption) of the message.
+    EditPollingTextConfirm{msg_db_id: i64, text: String}, // confirm the preview of the new text, then save.
+    EditPollingTitle{msg_db_id: i64}, // edit the title of the message.
     SetWelcomeMsg,           // Set the group message when a new user joins and send this.
+    SetWelcomeButtons{group_db_id: i64, group_name: String, content: MsgContent}, // 0 is the global one
 
     // Group module
     Group,
     GroupChoose{group_db_id: i64, group_name: String},
-    GroupPushMsg{group_db_id: i64, group_name: String, msg_db_id: i64},
+    // pool_id: the push sends the messages of the pool, msg_db_id is 0 then.
+    GroupPushMsg{group_db_id: i64, group_name: String, msg_db_id: i64, #[serde(default)] pool_id: i64},
+    GroupPushWindow{group_db_id: i64, group_name: String, msg_db_id: i64, send_time: String, #[serde(default)] pool_id: i64},
+    GroupTimeZone{group_db_id: i64, group_name: String},
+    GroupQuietHours{group_db_id: i64, group_name: String},
+    GroupWelcomeMsg{group_db_id: i64, group_name: String},
+    GroupTags{group_db_id: i64, group_name: String},
+    TagChoose{tag: String},
+    TagPushMsg{tag: String, msg_db_id: i64, #[serde(default)] pool_id: i64},
+    TagPushWindow{tag: String, msg_db_id: i64, send_time: String, #[serde(default)] pool_id: i64},
+    GroupDripDelay{group_db_id: i64, group_name: String, msg_db_id: i64}, // the delay of the new drip step
+
+    // Push options
+    PushCleanup{push_id: i64}, // auto-delete of the sent messages
+    PushPin{push_id: i64}, // pin and unpin of the sent messages
+
+    // Message pool
+    PoolName, // the name of the new pool
+
+    // Broadcast module
+    BroadcastCompose, // compose the message to broadcast.
+    BroadcastGroups{content: MsgContent, title: String, selected: Vec<i64>}, // check the groups, then send.
 
     // 这个作废
     GroupPush {
@@ -72,15 +100,18 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let db_main = db.clone();
     let db_poll = db.clone();
 
+    // Shared by the scheduled pushes and the broadcasts, the rate limits are per bot.
+    let queue = std::sync::Arc::new(SendQueue::start(bot.clone()));
+    let queue_main = queue.clone();
+
+    // The minutes missed (downtime, slow sends) within the window are caught up.
+    let grace_minutes = std::env::var("POLL_GRACE_MINUTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(scheduler::DEFAULT_GRACE_MINUTES);
+
     let poll_handle = tokio::spawn(async move {
-        loop {
-            match poll_task(&bot_poll, db_poll.clone()).await {
-                Ok(_) => log::info!("Poll task completed successfully"),
-                Err(e) => log::error!("Poll task error: {:?}", e),
-            }
-            // Waiting 1 minute
-            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
-        }
+        scheduler::run(bot_poll, db_poll, queue, grace_minutes).await;
     });
 
     let main_handle = tokio::spawn(async move {
@@ -91,7 +122,7 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         
         info!("Message handler created...");
         Dispatcher::builder(bot_clone, my_handler::create())
-            .dependencies(dptree::deps![storage, db_main])
+            .dependencies(dptree::deps![storage, db_main, queue_main])
             .enable_ctrlc_handler()
             .build()
             .dispatch()
@@ -113,34 +144,3 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         }
     }
 }
-
-/// Polling thread enter
-async fn poll_task(bot: &Bot, db: Db) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-
-    // Get Datetime HH:mm
-    let current_time = Local::now().format("%H:%M").to_string();
-    info!("Executing poll task at {}...", current_time);
-
-    let push_data = polling_msg::new(db)
-        .get_polling_msgs_by_time(&current_time)
-        .await;
-
-    if let Err(_) = push_data {
-        return Ok(());
-    }
-
-    let push_data = push_data?;
-    for push_msg in push_data {
-        let group_id: i64 = push_msg.group_id.parse()?;
-        info!("Push group_id is: {:?}", push_msg);
-        bot.send_message(ChatId(group_id), push_msg.msg_text)
-            .parse_mode(ParseMode::Html)
-            .await?;
-
-        info!(
-            "Successfully sent message to group {} at {}",
-            group_id, current_time
-        );
-    }
-    Ok(())
-}
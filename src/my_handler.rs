@@ -4,10 +4,18 @@ mod group_event;
 mod poll_message;
 mod welcome_message;
 mod group_set;
+mod preview;
+pub(crate) mod broadcast;
+pub(crate) mod alert;
+mod group_tag;
+mod push_option;
+mod msg_pool;
+mod drip;
 
 use crate::my_handler::admin::{add_admin_submit, rename_admin_submit};
-use crate::my_handler::group_event::{handle_my_chat_member, handle_new_members};
+use crate::my_handler::group_event::{handle_left_member, handle_my_chat_member, handle_new_members};
 
+use crate::service::msg::media;
 use crate::{commands, HandlerResult, State};
 use log::info;
 
@@ -18,9 +26,20 @@ use teloxide::{
     dptree,
     prelude::*,
 };
-use crate::my_handler::group_set::handle_group_push_datetime;
-use crate::my_handler::poll_message::{add_poll_message, add_poll_message_title};
-use crate::my_handler::welcome_message::handle_set_welcome_msg;
+use crate::my_handler::group_set::{
+    handle_group_push_datetime, handle_group_push_window, handle_group_quiet_hours, handle_group_time_zone,
+    handle_group_welcome_msg,
+};
+use crate::my_handler::poll_message::{
+    add_poll_album_item, add_poll_message, add_poll_message_buttons, add_poll_message_title,
+    handle_edit_poll_text, handle_edit_poll_title,
+};
+use crate::my_handler::welcome_message::{handle_set_welcome_buttons, handle_set_welcome_msg};
+use crate::my_handler::broadcast::handle_broadcast_compose;
+use crate::my_handler::group_tag::{handle_group_tags, handle_tag_push_datetime, handle_tag_push_window};
+use crate::my_handler::push_option::{handle_push_cleanup, handle_push_pin};
+use crate::my_handler::msg_pool::handle_pool_name;
+use crate::my_handler::drip::handle_group_drip_delay;
 
 /// Create handler
 pub fn create() -> UpdateHandler<Box<dyn std::error::Error + Send + Sync + 'static>> {
@@ -37,6 +56,10 @@ pub fn create() -> UpdateHandler<Box<dyn std::error::Error + Send + Sync + 'stat
                     dptree::filter(|msg: Message| {msg.new_chat_members().is_some()})
                         .endpoint(handle_new_members)
                 )
+                .branch(
+                    dptree::filter(|msg: Message| {msg.left_chat_member().is_some()})
+                        .endpoint(handle_left_member)
+                )
                 .enter_dialogue::<Message, ErasedStorage<State>, State>()
                 .branch(command_handler())
                 .branch(admin_command_handler())
@@ -69,6 +92,15 @@ fn admin_command_handler() -> UpdateHandler<Box<dyn std::error::Error + Send + S
  fn dialogue_handler() -> UpdateHandler<Box<dyn std::error::Error + Send + Sync + 'static>> {
     info!("Dialogue handler created.");
     Update::filter_message()
+        // Media (photo, video, document, animation) for the message contents
+        .branch(
+            dptree::filter(|msg: Message| msg.text().is_none() && media::content_of(&msg).is_some())
+                .branch(case![State::SetWelcomeMsg].endpoint(handle_set_welcome_msg))
+                .branch(case![State::AddPollingMsg].endpoint(add_poll_message))
+                .branch(case![State::AddPollingAlbum{media_group_id, content}].endpoint(add_poll_album_item))
+                .branch(case![State::GroupWelcomeMsg{group_db_id, group_name}].endpoint(handle_group_welcome_msg))
+                .branch(case![State::BroadcastCompose].endpoint(handle_broadcast_compose)),
+        )
         .filter_async(|msg: Message| async move { msg.text().is_some() }) //
         .branch(
             dptree::entry()
@@ -78,12 +110,31 @@ fn admin_command_handler() -> UpdateHandler<Box<dyn std::error::Error + Send + S
                 // Add poll message
                 .branch(case![State::AddPollingMsg].endpoint(add_poll_message))
                 .branch( case![State::AddPollingTitle(title)].endpoint(add_poll_message_title))
+                .branch(case![State::AddPollingContentTitle(content)].endpoint(add_poll_message_title))
+                .branch(case![State::AddPollingAlbum{media_group_id, content}].endpoint(add_poll_message_title))
+                .branch(case![State::AddPollingButtons{content, title}].endpoint(add_poll_message_buttons))
+                .branch(case![State::EditPollingText{msg_db_id}].endpoint(handle_edit_poll_text))
+                .branch(case![State::EditPollingTitle{msg_db_id}].endpoint(handle_edit_poll_title))
+                .branch(case![State::SetWelcomeButtons{group_db_id, group_name, content}].endpoint(handle_set_welcome_buttons))
 
                 // Update admin user name
                 .branch(case![State::AdminRename(user_id)].endpoint(rename_admin_submit))
                 .branch(case![State::AdminAdd].endpoint(add_admin_submit))
                 // Group
-                .branch(case![State::GroupPushMsg{group_db_id, group_name, msg_db_id}].endpoint(handle_group_push_datetime))
+                .branch(case![State::GroupPushMsg{group_db_id, group_name, msg_db_id, pool_id}].endpoint(handle_group_push_datetime))
+                .branch(case![State::GroupPushWindow{group_db_id, group_name, msg_db_id, send_time, pool_id}].endpoint(handle_group_push_window))
+                .branch(case![State::GroupTimeZone{group_db_id, group_name}].endpoint(handle_group_time_zone))
+                .branch(case![State::GroupQuietHours{group_db_id, group_name}].endpoint(handle_group_quiet_hours))
+                .branch(case![State::GroupWelcomeMsg{group_db_id, group_name}].endpoint(handle_group_welcome_msg))
+                .branch(case![State::GroupTags{group_db_id, group_name}].endpoint(handle_group_tags))
+                .branch(case![State::TagPushMsg{tag, msg_db_id, pool_id}].endpoint(handle_tag_push_datetime))
+                .branch(case![State::TagPushWindow{tag, msg_db_id, send_time, pool_id}].endpoint(handle_tag_push_window))
+                .branch(case![State::GroupDripDelay{group_db_id, group_name, msg_db_id}].endpoint(handle_group_drip_delay))
+                .branch(case![State::PushCleanup{push_id}].endpoint(handle_push_cleanup))
+                .branch(case![State::PushPin{push_id}].endpoint(handle_push_pin))
+                .branch(case![State::PoolName].endpoint(handle_pool_name))
+                // Broadcast
+                .branch(case![State::BroadcastCompose].endpoint(handle_broadcast_compose))
                 // other
                 .branch(case![State::Menu].endpoint(handle_invalid_command)),
         )
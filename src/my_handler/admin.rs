@@ -37,6 +37,7 @@ pub async fn admin_chose_menu(bot: &Bot, q: &CallbackQuery) -> HandlerResult {
     let button = InlineKeyboardMarkup::new(vec![vec![
         InlineKeyboardButton::callback("Delete", "admin_delete"),
         InlineKeyboardButton::callback("Rename", "admin_rename"),
+        InlineKeyboardButton::callback("🔔 Alerts", "admin_alerts"),
     ], vec![InlineKeyboardButton::callback("⬅️ Back", "back_admin", )]
     ]);
 
@@ -169,3 +170,27 @@ pub async fn add_admin_submit(
     dialogue.update(State::Admin).await?;
     Ok(())
 }
+
+/// Turn the push failure alerts of the admin on or off
+pub async fn admin_toggle_alerts(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    db: Db,
+) -> HandlerResult {
+    let Some(State::AdminChoose(user_id)) = dialogue.get().await? else {
+        let message = q.message.as_ref().unwrap();
+        bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+            .await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+
+    let text = match user::new(db).toggle_notify_failures(&user_id).await {
+        Some(true) => "🔔 Push failure alerts on",
+        Some(false) => "🔕 Push failure alerts off",
+        None => "Admin not found",
+    };
+    bot.answer_callback_query(q.id).text(text).await?;
+    Ok(())
+}
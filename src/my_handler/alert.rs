@@ -0,0 +1,119 @@
+//! # Failure alerts
+//! A push failing for good (bot removed, no rights, bad HTML...) fails again on every slot,
+//! the admins opted in get a private message to fix or disable it.
+use crate::my_handler::group_set::group_view_push;
+use crate::service::polling_msg::PollingMsg;
+use crate::service::{group, polling_msg, user, Db};
+use crate::{HandlerResult, MainDialogue, State};
+use chrono::{DateTime, Utc};
+use teloxide::payloads::{AnswerCallbackQuerySetters, SendMessageSetters};
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, ParseMode};
+use teloxide::utils::html;
+use teloxide::Bot;
+
+/// DM the admins about the push that failed permanently, the send errors are only logged.
+pub async fn notify_push_failure(
+    bot: &Bot,
+    db: &Db,
+    push: &PollingMsg,
+    scheduled_at: &DateTime<Utc>,
+    error: &str,
+) {
+    let recipients = user::new(db.clone()).failure_recipients().await;
+    if recipients.is_empty() {
+        return;
+    }
+
+    let schedule = if push.tag.is_empty() {
+        push.send_time.clone()
+    } else {
+        format!("{} (🏷 {})", push.send_time, push.tag)
+    };
+    let text = format!(
+        "⚠️ <b>Push failed</b>\nGroup: {}\nMessage: {}\nSchedule: {}\nScheduled at: {}\nError: {}",
+        html::escape(&push.group_name),
+        html::escape(&push.msg_title),
+        html::escape(&schedule),
+        polling_msg::wall_clock(scheduled_at, push.tz().as_ref()).format("%Y-%m-%d %H:%M"),
+        html::escape(&error.chars().take(300).collect::<String>()),
+    );
+    // A one-shot push is deleted after the slot, nothing to disable.
+    let one_shot = push.schedule().is_ok_and(|s| s.is_one_shot());
+    let keyboard = alert_keyboard(push, !one_shot, false);
+
+    for admin in recipients {
+        let Ok(user_id) = admin.user_id.parse::<i64>() else {
+            log::warn!("Admin {} has an invalid user id", admin.user_id);
+            continue;
+        };
+        if let Err(e) = bot
+            .send_message(ChatId(user_id), &text)
+            .parse_mode(ParseMode::Html)
+            .reply_markup(keyboard.clone())
+            .await
+        {
+            log::error!("Failed to notify admin {} of push {}: {}", admin.user_id, push.id, e);
+        }
+    }
+}
+
+fn alert_keyboard(push: &PollingMsg, can_disable: bool, disabled: bool) -> InlineKeyboardMarkup {
+    let pushes = if push.tag.is_empty() {
+        format!("alert_pushes_{}", push.group_id)
+    } else {
+        format!("tag_view_{}", push.tag)
+    };
+    let mut row = vec![InlineKeyboardButton::callback("👀 Pushes", pushes)];
+    if can_disable {
+        row.push(if disabled {
+            InlineKeyboardButton::callback("▶️ Enable", format!("alert_enable_{}", push.id))
+        } else {
+            InlineKeyboardButton::callback("⏸ Disable", format!("alert_disable_{}", push.id))
+        });
+    }
+    InlineKeyboardMarkup::new(vec![row])
+}
+
+/// Alert: open the push list of the group
+pub async fn alert_pushes(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    db: Db,
+    group_id: &str,
+) -> HandlerResult {
+    let Some(info) = group::new(db.clone()).get_by_group_id(group_id).await else {
+        bot.answer_callback_query(q.id)
+            .text("The group is no longer managed")
+            .await?;
+        return Ok(());
+    };
+
+    dialogue
+        .update(State::GroupChoose {
+            group_db_id: info.id,
+            group_name: info.group_name,
+        })
+        .await?;
+    group_view_push(bot, q, dialogue, db).await
+}
+
+/// Alert: disable the push, or enable it again
+pub async fn alert_set_disabled(bot: Bot, q: CallbackQuery, db: Db, push_id: i64, disabled: bool) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let polling_ser = polling_msg::new(db);
+    let Some(push) = polling_ser.get_by_id(push_id).await? else {
+        bot.answer_callback_query(q.id).text("The push is gone").await?;
+        return Ok(());
+    };
+
+    polling_ser.set_disabled(push_id, disabled).await?;
+    bot.edit_message_reply_markup(message.chat().id, message.id())
+        .reply_markup(alert_keyboard(&push, true, disabled))
+        .await?;
+    bot.answer_callback_query(q.id)
+        .text(if disabled { "Push disabled" } else { "Push enabled" })
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,386 @@
+//! # Broadcast
+//! Send a message to the chosen groups right now, without a schedule.
+//!
+//! /broadcast -> stored message or compose one -> check the groups (or tags) -> confirm -> report per group
+use crate::my_handler::preview::send_preview;
+use crate::service::group::{self, GroupInfo};
+use crate::service::msg::template::TemplateContext;
+use crate::service::msg::{media, template, MediaKind, MsgContent, MsgType};
+use crate::service::send_queue::{SendQueue, Sending};
+use crate::service::{msg, polling_msg, Db};
+use crate::{HandlerResult, MainDialogue, State};
+use chrono::Utc;
+use std::sync::Arc;
+use teloxide::payloads::{AnswerCallbackQuerySetters, EditMessageTextSetters, SendMessageSetters};
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use teloxide::Bot;
+
+/// Title of the message composed for the broadcast
+const COMPOSED_TITLE: &str = "Composed message";
+
+/// /broadcast: choose the stored message, or compose a new one.
+pub async fn init_broadcast(bot: Bot, msg: Message, dialogue: MainDialogue, db: Db) -> HandlerResult {
+    dialogue.update(State::Menu).await?;
+
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = msg::new(db)
+        .all()
+        .await
+        .iter()
+        .map(|msg_item| {
+            vec![InlineKeyboardButton::callback(
+                format!("{} {}", msg_item.media_kind.icon(), msg_item.msg_title),
+                format!("broadcast_msg_{}", msg_item.id),
+            )]
+        })
+        .collect();
+    keyboard.push(vec![
+        InlineKeyboardButton::callback("✏️ Compose", "broadcast_compose"),
+        InlineKeyboardButton::callback("Cancel", "cancel"),
+    ]);
+
+    bot.send_message(msg.chat.id, "📢 Broadcast now\n\nChoose the message to send, or compose a new one:")
+        .reply_markup(InlineKeyboardMarkup::new(keyboard))
+        .await?;
+    Ok(())
+}
+
+/// The stored message is chosen, go to choose the groups.
+pub async fn broadcast_stored_message(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    db: Db,
+    msg_db_id: i64,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let msg_ser = msg::new(db.clone());
+    let (Some(msg_item), Some(content)) = (
+        msg_ser.get_msg(msg_db_id).await?,
+        msg_ser.get_content(msg_db_id).await?,
+    ) else {
+        bot.answer_callback_query(q.id).text("The message was deleted").await?;
+        return Ok(());
+    };
+
+    let group_ser = group::new(db);
+    let groups = group_ser.all().await;
+    if groups.is_empty() {
+        bot.edit_message_text(message.chat().id, message.id(), "The robot has not joined any groups yet!")
+            .await?;
+        return Ok(());
+    }
+
+    dialogue
+        .update(State::BroadcastGroups {
+            content,
+            title: msg_item.msg_title.clone(),
+            selected: Vec::new(),
+        })
+        .await?;
+    bot.edit_message_text(message.chat().id, message.id(), groups_text(&msg_item.msg_title, 0))
+        .reply_markup(groups_keyboard(&groups, &group_ser.all_tags().await?, &[]))
+        .await?;
+    Ok(())
+}
+
+pub async fn init_broadcast_compose(bot: Bot, q: CallbackQuery, dialogue: MainDialogue) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    dialogue.update(State::BroadcastCompose).await?;
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        "Send the message to broadcast, text or a photo/video/document/animation with caption.\nThe formatting is kept, forwarding a message works too.",
+    )
+    .await?;
+    Ok(())
+}
+
+/// The composed message, previewed then go to choose the groups.
+pub async fn handle_broadcast_compose(
+    bot: Bot,
+    message: Message,
+    dialogue: MainDialogue,
+    db: Db,
+) -> HandlerResult {
+    let content = match media::content_of(&message) {
+        Some(content) if content.media_kind != MediaKind::Text || !content.text.is_empty() => content,
+        _ => {
+            bot.send_message(message.chat.id, "Please enter the message:").await?;
+            return Ok(());
+        }
+    };
+    if message.media_group_id().is_some() {
+        bot.send_message(
+            message.chat.id,
+            "Albums can not be composed here, add it in /pollmsg then broadcast it.\nPlease enter the message:",
+        )
+        .await?;
+        return Ok(());
+    }
+    if let Err(e) = template::validate(&content.text, &MsgType::Polling) {
+        bot.send_message(message.chat.id, format!("{e}\nPlease enter the message again:"))
+            .await?;
+        return Ok(());
+    }
+    if let Err(e) = send_preview(&bot, message.chat.id, message.from.as_ref(), &content, None).await {
+        bot.send_message(
+            message.chat.id,
+            format!("Telegram can not send the message: {e}\nPlease enter the message again:"),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let group_ser = group::new(db);
+    let groups = group_ser.all().await;
+    if groups.is_empty() {
+        dialogue.update(State::Menu).await?;
+        bot.send_message(message.chat.id, "The robot has not joined any groups yet!")
+            .await?;
+        return Ok(());
+    }
+
+    dialogue
+        .update(State::BroadcastGroups {
+            content,
+            title: COMPOSED_TITLE.to_string(),
+            selected: Vec::new(),
+        })
+        .await?;
+    bot.send_message(message.chat.id, groups_text(COMPOSED_TITLE, 0))
+        .reply_markup(groups_keyboard(&groups, &group_ser.all_tags().await?, &[]))
+        .await?;
+    Ok(())
+}
+
+/// What to check or uncheck in the group list
+pub enum Toggle<'a> {
+    Group(i64),
+    Tag(&'a str), // the groups having the tag
+    All,
+}
+
+/// Check or uncheck the group, the tag or all the groups, they are unchecked when all are checked.
+pub async fn toggle_broadcast_group(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    db: Db,
+    toggle: Toggle<'_>,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(State::BroadcastGroups { content, title, mut selected }) = dialogue.get().await? else {
+        bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+            .await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+
+    let group_ser = group::new(db);
+    let groups = group_ser.all().await;
+    let ids: Vec<i64> = match toggle {
+        Toggle::Group(id) => vec![id],
+        Toggle::Tag(tag) => group_ser
+            .groups_by_tag(tag)
+            .await?
+            .iter()
+            .map(|info| info.id)
+            .collect(),
+        Toggle::All => groups.iter().map(|info| info.id).collect(),
+    };
+    if ids.iter().all(|id| selected.contains(id)) {
+        selected.retain(|id| !ids.contains(id));
+    } else {
+        selected.extend(ids.iter().filter(|id| !selected.contains(id)).collect::<Vec<_>>());
+    }
+
+    bot.edit_message_text(message.chat().id, message.id(), groups_text(&title, selected.len()))
+        .reply_markup(groups_keyboard(&groups, &group_ser.all_tags().await?, &selected))
+        .await?;
+    dialogue
+        .update(State::BroadcastGroups { content, title, selected })
+        .await?;
+    Ok(())
+}
+
+/// Back to the groups from the confirmation
+pub async fn show_broadcast_groups(bot: Bot, q: CallbackQuery, dialogue: MainDialogue, db: Db) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(State::BroadcastGroups { title, selected, .. }) = dialogue.get().await? else {
+        bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+            .await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+
+    let group_ser = group::new(db);
+    let groups = group_ser.all().await;
+    bot.edit_message_text(message.chat().id, message.id(), groups_text(&title, selected.len()))
+        .reply_markup(groups_keyboard(&groups, &group_ser.all_tags().await?, &selected))
+        .await?;
+    Ok(())
+}
+
+/// Ask to confirm before sending
+pub async fn confirm_broadcast(bot: Bot, q: CallbackQuery, dialogue: MainDialogue, db: Db) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(State::BroadcastGroups { title, selected, .. }) = dialogue.get().await? else {
+        bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+            .await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+    if selected.is_empty() {
+        bot.answer_callback_query(q.id).text("Please check the groups first").await?;
+        return Ok(());
+    }
+
+    let names = group::new(db)
+        .all()
+        .await
+        .into_iter()
+        .filter(|info| selected.contains(&info.id))
+        .map(|info| format!("- {}", info.group_name))
+        .collect::<Vec<String>>()
+        .join("\n");
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        format!("Send [{title}] now to {} groups?\n{names}", selected.len()),
+    )
+    .reply_markup(InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback("✅ Send now", "broadcast_confirm"),
+        InlineKeyboardButton::callback("⬅️ Back", "broadcast_groups"),
+        InlineKeyboardButton::callback("Cancel", "cancel"),
+    ]]))
+    .await?;
+    Ok(())
+}
+
+/// Queue the message to the checked groups and report the results, a failed group does not stop the others.
+pub async fn send_broadcast(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    db: Db,
+    queue: Arc<SendQueue>,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(State::BroadcastGroups { content, title, selected }) = dialogue.get().await? else {
+        bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+            .await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+    // Leave the state first, a second click does not send it again.
+    dialogue.update(State::Menu).await?;
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        format!("Sending [{title}] to {} groups...", selected.len()),
+    )
+    .await?;
+
+    let groups: Vec<GroupInfo> = group::new(db)
+        .all()
+        .await
+        .into_iter()
+        .filter(|info| selected.contains(&info.id))
+        .collect();
+    // All queued first, the queue sends to the groups side by side.
+    let mut sending = Vec::with_capacity(groups.len());
+    for info in &groups {
+        sending.push(send_to_group(&bot, &queue, info, &content).await);
+    }
+    let mut report = Vec::with_capacity(groups.len());
+    let mut sent = 0;
+    for (info, sending) in groups.iter().zip(sending) {
+        let result = match sending {
+            Ok(sending) => sending.result().await.map_err(anyhow::Error::from),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(_) => {
+                sent += 1;
+                report.push(format!("✅ {}", info.group_name));
+            }
+            Err(e) => {
+                log::error!("Failed to broadcast to group {}: {}", info.group_id, e);
+                report.push(format!("❌ {}: {}", info.group_name, e));
+            }
+        }
+    }
+
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        format!(
+            "📢 [{title}] sent to {sent}/{} groups\n\n{}",
+            groups.len(),
+            report.join("\n")
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn send_to_group(
+    bot: &Bot,
+    queue: &SendQueue,
+    info: &GroupInfo,
+    content: &MsgContent,
+) -> anyhow::Result<Sending> {
+    let chat_id = ChatId(info.group_id.parse()?);
+    let member_count = if content.uses("member_count") {
+        bot.get_chat_member_count(chat_id).await.ok()
+    } else {
+        None
+    };
+    let tz = group::parse_time_zone(&info.time_zone).ok().flatten();
+    let ctx = TemplateContext {
+        group_name: info.group_name.clone(),
+        member_count,
+        now: Some(polling_msg::wall_clock(&Utc::now(), tz.as_ref())),
+        ..Default::default()
+    };
+    Ok(queue.enqueue(chat_id, content.clone(), ctx))
+}
+
+fn groups_text(title: &str, selected: usize) -> String {
+    format!("📢 [{title}]\n\nCheck the groups to send to ({selected} checked):")
+}
+
+/// Checkbox buttons of the groups, the tag buttons check all the groups of the tag.
+fn groups_keyboard(groups: &[GroupInfo], tags: &[(String, i64)], selected: &[i64]) -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = groups
+        .iter()
+        .map(|info| {
+            let check = if selected.contains(&info.id) { "☑️" } else { "⬜" };
+            vec![InlineKeyboardButton::callback(
+                format!("{check} {}", info.group_name),
+                format!("broadcast_toggle_{}", info.id),
+            )]
+        })
+        .collect();
+    for row in tags.chunks(3) {
+        keyboard.push(
+            row.iter()
+                .map(|(tag, count)| {
+                    InlineKeyboardButton::callback(format!("🏷 {tag} ({count})"), format!("broadcast_tag_{tag}"))
+                })
+                .collect(),
+        );
+    }
+    let all = if groups.iter().all(|info| selected.contains(&info.id)) {
+        "⬜ None"
+    } else {
+        "☑️ All"
+    };
+    keyboard.push(vec![
+        InlineKeyboardButton::callback(all, "broadcast_all"),
+        InlineKeyboardButton::callback(format!("📢 Send ({})", selected.len()), "broadcast_send"),
+    ]);
+    keyboard.push(vec![InlineKeyboardButton::callback("Cancel", "cancel")]);
+    InlineKeyboardMarkup::new(keyboard)
+}
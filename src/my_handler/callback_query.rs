@@ -1,22 +1,50 @@
-use crate::commands::start_command::admin_menu;
-use crate::my_handler::admin::{admin_chose_menu, all_admin, delete_admin, rename_admin};
+use crate::commands::start_command::{admin_menu, poll_msg_menu};
+use crate::my_handler::admin::{admin_chose_menu, admin_toggle_alerts, all_admin, delete_admin, rename_admin};
+use crate::my_handler::alert::{alert_pushes, alert_set_disabled};
+use crate::my_handler::broadcast::{
+    broadcast_stored_message, confirm_broadcast, init_broadcast_compose, send_broadcast,
+    show_broadcast_groups, toggle_broadcast_group, Toggle,
+};
+use crate::my_handler::drip::{group_drip, group_drip_add, group_drip_delete, group_drip_msg};
 use crate::my_handler::group_set::{
-    group_add_push, group_delete_push, group_msg_choose, group_view_push, show_group_buttons,
+    group_add_push, group_deliveries, group_delete_push, group_msg_choose, group_quiet_hours, group_time_zone,
+    group_toggle_mute,
+    group_view_push, group_welcome, group_welcome_reset, group_welcome_set, show_group_buttons,
     show_group_menu,
 };
-use crate::my_handler::poll_message::{init_add_poll_message, list_poll_message};
+use crate::my_handler::group_tag::{
+    group_tags, tag_add_push, tag_delete_push, tag_list, tag_msg_choose, tag_view,
+};
+use crate::my_handler::msg_pool::{
+    confirm_delete_pool, delete_pool, pool_list, pool_new, pool_toggle_item, pool_toggle_order, pool_view,
+};
+use crate::my_handler::poll_message::{
+    confirm_delete_poll_message, confirm_edit_poll_text, confirm_poll_message, delete_poll_message,
+    init_add_poll_message, init_edit_poll_text, init_edit_poll_title, list_poll_message,
+    poll_message_history, preview_poll_message, restore_poll_message_revision, view_poll_message,
+    view_poll_message_revision,
+};
+use crate::my_handler::push_option::{push_cleanup, push_menu, push_pin};
 use crate::my_handler::welcome_message::{current_welcome_message, setting_welcome_message};
+use crate::service::send_queue::SendQueue;
 use crate::service::Db;
 use crate::{HandlerResult, MainDialogue, State};
 use log::info;
 use std::str::FromStr;
+use std::sync::Arc;
 use teloxide::payloads::{AnswerCallbackQuerySetters, EditMessageTextSetters};
 use teloxide::prelude::Requester;
 use teloxide::types::CallbackQuery;
 use teloxide::Bot;
 
 /// Query enter
-pub async fn enter(bot: Bot, q: CallbackQuery, dialogue: MainDialogue, db: Db) -> HandlerResult {
+pub async fn enter(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    db: Db,
+    queue: Arc<SendQueue>,
+) -> HandlerResult {
     info!("Into callback query handle");
     if q.data.is_none() {
         bot.answer_callback_query(q.id)
@@ -41,7 +69,10 @@ pub async fn enter(bot: Bot, q: CallbackQuery, dialogue: MainDialogue, db: Db) -
             group_add_push(bot, q.clone(), dialogue, db).await?;
         }
         ["group", "msg", msg_db_id] => {
-            group_msg_choose(bot, q.clone(), dialogue, i64::from_str(msg_db_id).unwrap()).await?;
+            group_msg_choose(bot, q.clone(), dialogue, i64::from_str(msg_db_id).unwrap(), 0).await?;
+        }
+        ["group", "pool", pool_id] => {
+            group_msg_choose(bot, q.clone(), dialogue, 0, pool_id.parse().unwrap()).await?;
         }
         ["group", "view", "push"] => {
             group_view_push(bot, q.clone(), dialogue, db).await?;
@@ -49,9 +80,76 @@ pub async fn enter(bot: Bot, q: CallbackQuery, dialogue: MainDialogue, db: Db) -
         ["group", "delete", "push", push_id] => {
             group_delete_push(bot, q.clone(), dialogue, db, push_id.parse().unwrap()).await?;
         }
+        ["group", "quiet"] => {
+            group_quiet_hours(bot, q.clone(), dialogue, db).await?;
+        }
+        ["group", "timezone"] => {
+            group_time_zone(bot, q.clone(), dialogue, db).await?;
+        }
+        ["group", "welcome"] => {
+            group_welcome(bot, q.clone(), dialogue, db).await?;
+        }
+        ["group", "welcome", "set"] => {
+            group_welcome_set(bot, q.clone(), dialogue).await?;
+        }
+        ["group", "welcome", "reset"] => {
+            group_welcome_reset(bot, q.clone(), dialogue, db).await?;
+        }
+        ["group", "deliveries"] => {
+            group_deliveries(bot, q.clone(), dialogue, db).await?;
+        }
+        ["group", "tags"] => {
+            group_tags(bot, q, dialogue, db).await?;
+        }
+        ["group", "toggle", target @ ("polling" | "welcome")] => {
+            group_toggle_mute(bot, q.clone(), dialogue, db, target).await?;
+        }
+        ["group", "drip"] => {
+            group_drip(bot, q.clone(), dialogue, db).await?;
+        }
+        ["group", "drip", "add"] => {
+            group_drip_add(bot, q.clone(), dialogue, db).await?;
+        }
+        ["group", "drip", "msg", msg_db_id] => {
+            group_drip_msg(bot, q.clone(), dialogue, msg_db_id.parse().unwrap()).await?;
+        }
+        ["group", "drip", "delete", step_id] => {
+            group_drip_delete(bot, q.clone(), dialogue, db, step_id.parse().unwrap()).await?;
+        }
         ["group", group_id, res @ ..] => {
             let group_name = res.join("_");
-            show_group_menu(bot, q.clone(), dialogue, group_id, &group_name).await?;
+            show_group_menu(bot, q.clone(), dialogue, db, group_id, &group_name).await?;
+        }
+
+        // Tag
+        ["tag", "list"] => {
+            tag_list(bot, q, dialogue, db).await?;
+        }
+        ["tag", "view", tag] => {
+            tag_view(bot, q.clone(), dialogue, db, tag).await?;
+        }
+        ["tag", "add", "push"] => {
+            tag_add_push(bot, q, dialogue, db).await?;
+        }
+        ["tag", "msg", msg_db_id] => {
+            tag_msg_choose(bot, q.clone(), dialogue, msg_db_id.parse().unwrap(), 0).await?;
+        }
+        ["tag", "pool", pool_id] => {
+            tag_msg_choose(bot, q.clone(), dialogue, 0, pool_id.parse().unwrap()).await?;
+        }
+        ["tag", "delete", "push", push_id] => {
+            tag_delete_push(bot, q.clone(), dialogue, db, push_id.parse().unwrap()).await?;
+        }
+
+        // Push options
+        ["push", "view", push_id] => {
+            push_menu(bot, q.clone(), db, push_id.parse().unwrap()).await?;
+        }
+        ["push", "cleanup", push_id] => {
+            push_cleanup(bot, q.clone(), dialogue, push_id.parse().unwrap()).await?;
+        }
+        ["push", "pin", push_id] => {
+            push_pin(bot, q.clone(), dialogue, push_id.parse().unwrap()).await?;
         }
 
         // Admin list
@@ -85,6 +183,9 @@ pub async fn enter(bot: Bot, q: CallbackQuery, dialogue: MainDialogue, db: Db) -
         ["admin", "rename"] => {
             rename_admin(bot, q, dialogue).await?;
         }
+        ["admin", "alerts"] => {
+            admin_toggle_alerts(bot, q, dialogue, db).await?;
+        }
         ["setting", "welcome", "message"] => {
             setting_welcome_message(bot, q, dialogue).await?;
         }
@@ -92,13 +193,110 @@ pub async fn enter(bot: Bot, q: CallbackQuery, dialogue: MainDialogue, db: Db) -
             current_welcome_message(bot, q, db).await?;
         }
 
-        ["add", "poll", "message"] => {
+        ["add", "poll", "message"] | ["pollmsg", "edit"] => {
             init_add_poll_message(bot, q, dialogue).await?;
         }
+        ["pollmsg", "confirm"] => {
+            confirm_poll_message(bot, q, dialogue, db).await?;
+        }
 
         ["list", "poll", "message"] => {
+            dialogue.update(State::Menu).await?;
             list_poll_message(bot, q, db).await?;
         }
+        ["poll", "msg", "menu"] => {
+            let mess = q.message.as_ref().unwrap();
+            bot.edit_message_text(mess.chat().id, mess.id(), "Poll msg")
+                .reply_markup(poll_msg_menu())
+                .await?;
+        }
+        ["pollmsg", "view", msg_db_id] => {
+            view_poll_message(bot, q.clone(), dialogue, db, msg_db_id.parse().unwrap()).await?;
+        }
+        ["pollmsg", "preview", msg_db_id] => {
+            preview_poll_message(bot, q.clone(), db, msg_db_id.parse().unwrap()).await?;
+        }
+        ["pollmsg", "savetext"] => {
+            confirm_edit_poll_text(bot, q, dialogue, db).await?;
+        }
+        ["pollmsg", "text", msg_db_id] => {
+            init_edit_poll_text(bot, q.clone(), dialogue, db, msg_db_id.parse().unwrap()).await?;
+        }
+        ["pollmsg", "title", msg_db_id] => {
+            init_edit_poll_title(bot, q.clone(), dialogue, msg_db_id.parse().unwrap()).await?;
+        }
+        ["pollmsg", "delete", msg_db_id] => {
+            confirm_delete_poll_message(bot, q.clone(), db, msg_db_id.parse().unwrap()).await?;
+        }
+        ["pollmsg", "remove", msg_db_id] => {
+            delete_poll_message(bot, q.clone(), db, msg_db_id.parse().unwrap()).await?;
+        }
+        ["pollmsg", "history", msg_db_id] => {
+            poll_message_history(bot, q.clone(), db, msg_db_id.parse().unwrap()).await?;
+        }
+        ["pollmsg", "revision", revision_id] => {
+            view_poll_message_revision(bot, q.clone(), db, revision_id.parse().unwrap()).await?;
+        }
+        ["pollmsg", "restore", revision_id] => {
+            restore_poll_message_revision(bot, q.clone(), db, revision_id.parse().unwrap()).await?;
+        }
+
+        // Message pool
+        ["pool", "list"] => {
+            pool_list(bot, q, dialogue, db).await?;
+        }
+        ["pool", "new"] => {
+            pool_new(bot, q, dialogue).await?;
+        }
+        ["pool", "view", pool_id] => {
+            pool_view(bot, q.clone(), db, pool_id.parse().unwrap()).await?;
+        }
+        ["pool", "item", pool_id, msg_db_id] => {
+            pool_toggle_item(bot, q.clone(), db, pool_id.parse().unwrap(), msg_db_id.parse().unwrap()).await?;
+        }
+        ["pool", "order", pool_id] => {
+            pool_toggle_order(bot, q.clone(), db, pool_id.parse().unwrap()).await?;
+        }
+        ["pool", "delete", pool_id] => {
+            confirm_delete_pool(bot, q.clone(), db, pool_id.parse().unwrap()).await?;
+        }
+        ["pool", "remove", pool_id] => {
+            delete_pool(bot, q.clone(), dialogue, db, pool_id.parse().unwrap()).await?;
+        }
+
+        // Broadcast
+        ["broadcast", "msg", msg_db_id] => {
+            broadcast_stored_message(bot, q.clone(), dialogue, db, msg_db_id.parse().unwrap()).await?;
+        }
+        ["broadcast", "compose"] => {
+            init_broadcast_compose(bot, q, dialogue).await?;
+        }
+        ["broadcast", "toggle", group_db_id] => {
+            toggle_broadcast_group(bot, q.clone(), dialogue, db, Toggle::Group(group_db_id.parse().unwrap())).await?;
+        }
+        ["broadcast", "tag", tag] => {
+            toggle_broadcast_group(bot, q.clone(), dialogue, db, Toggle::Tag(tag)).await?;
+        }
+        ["broadcast", "all"] => {
+            toggle_broadcast_group(bot, q, dialogue, db, Toggle::All).await?;
+        }
+        ["broadcast", "groups"] => {
+            show_broadcast_groups(bot, q, dialogue, db).await?;
+        }
+        ["broadcast", "send"] => {
+            confirm_broadcast(bot, q, dialogue, db).await?;
+        }
+        ["broadcast", "confirm"] => {
+            send_broadcast(bot, q, dialogue, db, queue).await?;
+        }
+
+        // Failure alert
+        ["alert", "pushes", group_id] => {
+            alert_pushes(bot, q.clone(), dialogue, db, group_id).await?;
+        }
+        ["alert", action @ ("disable" | "enable"), push_id] => {
+            alert_set_disabled(bot, q.clone(), db, push_id.parse().unwrap(), *action == "disable").await?;
+        }
 
         ["cancel"] => {
             let mess = q.message.as_ref().unwrap();
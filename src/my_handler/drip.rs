@@ -0,0 +1,157 @@
+//! # Drip sequence
+//! The steps the new members of the group get after the welcome, e.g. the rules after 1h, the FAQ after 1d.
+use crate::service::drip::delay_str;
+use crate::service::{drip, group, msg, Db};
+use crate::{HandlerResult, MainDialogue, State};
+use teloxide::payloads::EditMessageTextSetters;
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use teloxide::Bot;
+
+const DELAY_TIPS: &str = "Send the delay after the join:
+now - with the welcome
+30m / 1h / 1d - after the time (up to 30d)";
+
+/// Group: the drip sequence
+pub async fn group_drip(bot: Bot, q: CallbackQuery, dialogue: MainDialogue, db: Db) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(State::GroupChoose { group_db_id, group_name }) = dialogue.get().await? else {
+        bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+            .await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+
+    let (text, keyboard) = drip_content(&db, group_db_id, &group_name).await?;
+    bot.edit_message_text(message.chat().id, message.id(), text)
+        .reply_markup(keyboard)
+        .await?;
+    Ok(())
+}
+
+async fn drip_content(db: &Db, group_db_id: i64, group_name: &str) -> anyhow::Result<(String, InlineKeyboardMarkup)> {
+    let drip_ser = drip::new(db.clone());
+    let steps = drip_ser.steps(group_db_id).await?;
+    let pending = match group::new(db.clone()).get_by_id(group_db_id).await {
+        Some(info) => drip_ser.pending_count(&info.group_id).await?,
+        None => 0,
+    };
+
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| {
+            vec![InlineKeyboardButton::callback(
+                format!("🗑 {}. {} - {}", i + 1, delay_str(step.delay_minutes), step.msg_title),
+                format!("group_drip_delete_{}", step.id),
+            )]
+        })
+        .collect();
+    keyboard.push(vec![
+        InlineKeyboardButton::callback("➕ Add step", "group_drip_add"),
+        InlineKeyboardButton::callback("⬅️ Back", format!("group_{}_{}", group_db_id, group_name)),
+    ]);
+
+    let text = if steps.is_empty() {
+        format!("{group_name}\nNo drip steps, the new members only get the welcome message.\n\nAdd the steps, e.g. the rules after 1h and the FAQ after 1d.")
+    } else {
+        let list = steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| format!("{}. {} - {}", i + 1, delay_str(step.delay_minutes), step.msg_title))
+            .collect::<Vec<String>>()
+            .join("\n");
+        format!("{group_name}\nAfter the welcome, the new members get:\n{list}\n\nWaiting to be sent: {pending}\nLeaving the group cancels the rest. Click a step to delete it.")
+    };
+    Ok((text, InlineKeyboardMarkup::new(keyboard)))
+}
+
+/// Group drip: choose the message of the new step
+pub async fn group_drip_add(bot: Bot, q: CallbackQuery, dialogue: MainDialogue, db: Db) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(State::GroupChoose { .. }) = dialogue.get().await? else {
+        bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+            .await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![vec![InlineKeyboardButton::callback(
+        "⬅️ Back",
+        "group_drip",
+    )]];
+    for msg_info in msg::new(db).all().await {
+        keyboard.push(vec![InlineKeyboardButton::callback(
+            msg_info.msg_title,
+            format!("group_drip_msg_{}", msg_info.id),
+        )]);
+    }
+    bot.edit_message_text(message.chat().id, message.id(), "Please specify the message of the step:")
+        .reply_markup(InlineKeyboardMarkup::new(keyboard))
+        .await?;
+    Ok(())
+}
+
+/// Group drip: ask the delay of the step
+pub async fn group_drip_msg(bot: Bot, q: CallbackQuery, dialogue: MainDialogue, msg_db_id: i64) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(State::GroupChoose { group_db_id, group_name }) = dialogue.get().await? else {
+        bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+            .await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+
+    dialogue
+        .update(State::GroupDripDelay {
+            group_db_id,
+            group_name,
+            msg_db_id,
+        })
+        .await?;
+    bot.edit_message_text(message.chat().id, message.id(), DELAY_TIPS)
+        .await?;
+    Ok(())
+}
+
+/// Group drip: submit the delay, add the step
+pub async fn handle_group_drip_delay(bot: Bot, msg: Message, dialogue: MainDialogue, db: Db) -> HandlerResult {
+    let Some(State::GroupDripDelay { group_db_id, group_name, msg_db_id }) = dialogue.get().await? else {
+        bot.send_message(msg.chat.id, "Abnormal status, exited!").await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+    let delay = match drip::parse_delay(msg.text().unwrap_or_default()) {
+        Ok(delay) => delay,
+        Err(e) => {
+            bot.send_message(msg.chat.id, format!("Wrong format: {e}\n\n{DELAY_TIPS}"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    drip::new(db.clone()).add_step(group_db_id, delay, msg_db_id).await?;
+    dialogue
+        .update(State::GroupChoose {
+            group_db_id,
+            group_name: group_name.clone(),
+        })
+        .await?;
+    let (text, keyboard) = drip_content(&db, group_db_id, &group_name).await?;
+    bot.send_message(msg.chat.id, format!("Success\n\n{text}"))
+        .reply_markup(keyboard)
+        .await?;
+    Ok(())
+}
+
+/// Group drip: delete the step, the members waiting for it do not get it
+pub async fn group_drip_delete(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    db: Db,
+    step_id: i64,
+) -> HandlerResult {
+    drip::new(db.clone()).delete_step(step_id).await?;
+    group_drip(bot, q, dialogue, db).await
+}
@@ -1,33 +1,112 @@
-use crate::service::{group, msg, Db};
+use crate::service::msg::template::TemplateContext;
+use crate::service::drip::Member;
+use crate::service::send_queue::SendQueue;
+use crate::service::{drip, group, msg, polling_msg, quiet, Db};
 use crate::HandlerResult;
+use chrono::Utc;
 use log::{error, info};
+use std::sync::Arc;
 use teloxide::prelude::*;
-use teloxide::types::{ChatMemberStatus, Me, ParseMode};
+use teloxide::types::{ChatMemberStatus, Me};
 use teloxide::Bot;
 
 pub async fn handle_new_members(
     bot: Bot,
     message: Message,
     db: Db,
+    queue: Arc<SendQueue>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let chat_id = message.chat.id.to_string();
+    let group_info = group::new(db.clone()).get_by_group_id(&chat_id).await;
+    if group_info.as_ref().is_some_and(|info| info.mute_welcome) {
+        info!("Welcome message of {} is paused", chat_id);
+        return Ok(());
+    }
+    let group_db_id = group_info.as_ref().map(|info| info.id);
+    let tz = group_info
+        .as_ref()
+        .and_then(|info| group::parse_time_zone(&info.time_zone).unwrap_or(None));
+    // Within the quiet hours the welcomes wait for the end, when batched.
+    let now = Utc::now();
+    let hold_until = group_info
+        .as_ref()
+        .and_then(|info| info.quiet())
+        .filter(|quiet| quiet.batch_welcome && quiet.is_quiet(&now, tz.as_ref()))
+        .map(|quiet| quiet.end_after(&now, tz.as_ref()));
+
     if let Some(new_members) = message.new_chat_members() {
+        let welcome_msg = msg::new(db.clone()).welcome_msg_for(group_db_id).await;
+        let member_count = if welcome_msg.uses("member_count") {
+            bot.get_chat_member_count(message.chat.id).await.ok()
+        } else {
+            None
+        };
+
         for member in new_members {
             if member.is_bot {
                 continue;
             }
 
-            let welcome_msg = format!("Welcome {} to the group!", member.first_name);
+            let joined = Member {
+                user_id: member.id.0 as i64,
+                first_name: &member.first_name,
+                username: member.username.as_deref(),
+            };
+            // A failed welcome does not stop the others, nor the drip sequence of the member.
+            match &hold_until {
+                Some(send_at) => {
+                    if let Err(e) = quiet::new(db.clone()).hold_welcome(&chat_id, &joined, send_at).await {
+                        error!("Failed to hold the welcome of {} in {}: {}", joined.user_id, chat_id, e);
+                    }
+                }
+                None => {
+                    let ctx = TemplateContext {
+                        user_id: Some(member.id.0),
+                        first_name: member.first_name.clone(),
+                        username: member.username.clone(),
+                        group_name: message.chat.title().unwrap_or_default().to_string(),
+                        member_count,
+                        now: Some(polling_msg::wall_clock(&now, tz.as_ref())),
+                    };
+                    // Within the rate limits of the group, like the pushes.
+                    let sending = queue.enqueue(message.chat.id, welcome_msg.clone(), ctx);
+                    let (user_id, chat_id) = (joined.user_id, chat_id.clone());
+                    tokio::spawn(async move {
+                        if let Err(e) = sending.result().await {
+                            error!("Failed to welcome {} in {}: {}", user_id, chat_id, e);
+                        }
+                    });
+                }
+            }
 
-            bot.send_message(message.chat.id, welcome_msg).await?;
-            let welcome_msg = msg::new(db.clone()).welcome_msg().await;
-            bot.send_message(message.chat.id, welcome_msg)
-                .parse_mode(ParseMode::Html)
-                .await?;
+            // The drip sequence follows the welcome, sent by the poll task.
+            if let Some(group_db_id) = group_db_id {
+                if let Err(e) = drip::new(db.clone())
+                    .enqueue(group_db_id, &chat_id, &joined, &now)
+                    .await
+                {
+                    error!("Failed to queue the drip sequence of {} in {}: {}", joined.user_id, chat_id, e);
+                }
+            }
         }
     }
     Ok(())
 }
 
+/// The member left (or was removed), the rest of the drip sequence and the held welcome are not sent.
+pub async fn handle_left_member(message: Message, db: Db) -> HandlerResult {
+    let Some(member) = message.left_chat_member() else {
+        return Ok(());
+    };
+    let chat_id = message.chat.id.to_string();
+    let cancelled = drip::new(db.clone()).cancel(&chat_id, member.id.0 as i64).await?;
+    quiet::new(db).cancel(&chat_id, member.id.0 as i64).await?;
+    if cancelled > 0 {
+        info!("{} drip steps of {} in {} cancelled", cancelled, member.id, chat_id);
+    }
+    Ok(())
+}
+
 pub async fn handle_my_chat_member(
     bot: Bot,
     chat_member: ChatMemberUpdated,
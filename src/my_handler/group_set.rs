@@ -2,28 +2,36 @@
 //! All operations related to groups are here
 
 use crate::commands::start_command::group_buttons;
-use crate::service::{msg, polling_msg, Db};
+use crate::my_handler::poll_message::BUTTON_TIPS;
+use crate::my_handler::welcome_message::welcome_tips;
+use crate::service::msg::{media, template, MediaKind, MsgType};
+use crate::service::polling_msg::Schedule;
+use crate::service::delivery::DeliveryStatus;
+use crate::service::{delivery, group, msg, polling_msg, pool, Db};
 use crate::{HandlerResult, MainDialogue, State};
-use chrono::NaiveTime;
+use chrono::{NaiveDate, Utc};
 use std::str::FromStr;
 use teloxide::payloads::EditMessageTextSetters;
 use teloxide::prelude::*;
-use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, ParseMode};
+use teloxide::utils::html;
 use teloxide::Bot;
 
 pub async fn show_group_menu(
     bot: Bot,
     q: CallbackQuery,
     dialogue: MainDialogue,
+    db: Db,
     group_id: &str,
     group_name: &str,
 ) -> HandlerResult {
     let message = q.message.as_ref().unwrap();
     let message_id = message.id();
+    let group_db_id = i64::from_str(group_id).unwrap();
 
     dialogue
         .update(State::GroupChoose {
-            group_db_id: i64::from_str(group_id).unwrap(),
+            group_db_id,
             group_name: group_name.to_string(),
         })
         .await?;
@@ -31,19 +39,57 @@ pub async fn show_group_menu(
     bot.edit_message_text(
         message.chat().id,
         message_id,
-        format!("{}\nPlease choose an operation:", group_name),
+        group_menu_text(db, group_db_id, group_name).await,
     )
     .reply_markup(group_menu())
     .await?;
     Ok(())
 }
 
+/// The group name and its settings
+async fn group_menu_text(db: Db, group_db_id: i64, group_name: &str) -> String {
+    let group_ser = group::new(db);
+    let Some(info) = group_ser.get_by_id(group_db_id).await else {
+        return format!("{}\nPlease choose an operation:", group_name);
+    };
+    let tags = group_ser.tags(group_db_id).await.unwrap_or_default();
+
+    let on_off = |mute: bool| if mute { "⏸ paused" } else { "▶️ on" };
+    format!(
+        "{}\nPushes: {}\nWelcome: {}\nTime zone: {}\nQuiet hours: {}\nTags: {}\n\nPlease choose an operation:",
+        group_name,
+        on_off(info.mute_polling),
+        on_off(info.mute_welcome),
+        if info.time_zone.is_empty() { "server" } else { &info.time_zone },
+        if info.quiet_hours.is_empty() { "off" } else { &info.quiet_hours },
+        if tags.is_empty() { "none".to_string() } else { tags.join(", ") },
+    )
+}
+
 pub fn group_menu() -> InlineKeyboardMarkup {
-    InlineKeyboardMarkup::new(vec![vec![
-        InlineKeyboardButton::callback("📲 Add Push", "group_add_push"),
-        InlineKeyboardButton::callback("👀 View Push", "group_view_push"),
-        InlineKeyboardButton::callback("Cancel", "cancel_group"),
-    ]])
+    InlineKeyboardMarkup::new(vec![
+        vec![
+            InlineKeyboardButton::callback("📲 Add Push", "group_add_push"),
+            InlineKeyboardButton::callback("👀 View Push", "group_view_push"),
+        ],
+        vec![
+            InlineKeyboardButton::callback("⏯ Pushes", "group_toggle_polling"),
+            InlineKeyboardButton::callback("⏯ Welcome", "group_toggle_welcome"),
+        ],
+        vec![
+            InlineKeyboardButton::callback("👋 Welcome Msg", "group_welcome"),
+            InlineKeyboardButton::callback("🌐 Time Zone", "group_timezone"),
+        ],
+        vec![
+            InlineKeyboardButton::callback("🏷 Tags", "group_tags"),
+            InlineKeyboardButton::callback("📜 Deliveries", "group_deliveries"),
+        ],
+        vec![
+            InlineKeyboardButton::callback("💧 Drip", "group_drip"),
+            InlineKeyboardButton::callback("🌙 Quiet Hours", "group_quiet"),
+        ],
+        vec![InlineKeyboardButton::callback("Cancel", "cancel_group")],
+    ])
 }
 
 /// Show group buttons
@@ -101,7 +147,7 @@ pub async fn group_add_push(
         }
     };
 
-    let message_list = msg::new(db).all().await;
+    let message_list = msg::new(db.clone()).all().await;
     let mut keyboard_buttons: Vec<Vec<InlineKeyboardButton>> =
         vec![vec![InlineKeyboardButton::callback(
             "⬅️ Back",
@@ -113,6 +159,12 @@ pub async fn group_add_push(
             format!("group_msg_{}", msg_info.id,),
         )]);
     }
+    for pool_info in pool::new(db).all().await? {
+        keyboard_buttons.push(vec![InlineKeyboardButton::callback(
+            format!("🔀 {}", pool_info.pool_name),
+            format!("group_pool_{}", pool_info.id),
+        )]);
+    }
 
     let keyboard = InlineKeyboardMarkup::new(keyboard_buttons);
     bot.edit_message_text(
@@ -126,12 +178,13 @@ pub async fn group_add_push(
     Ok(())
 }
 
-/// Group add push: choose the message
+/// Group add push: choose the message, or the pool (`msg_db_id` 0)
 pub async fn group_msg_choose(
     bot: Bot,
     q: CallbackQuery,
     dialogue: MainDialogue,
     msg_db_id: i64,
+    pool_id: i64,
 ) -> HandlerResult {
     let message = q.message.as_ref().unwrap();
     let (group_db_id, group_name) = match dialogue.get().await?.unwrap() {
@@ -152,17 +205,23 @@ pub async fn group_msg_choose(
             group_db_id,
             group_name,
             msg_db_id,
+            pool_id,
         })
         .await?;
-    bot.edit_message_text(
-        message.chat().id,
-        message.id(),
-        "Time (HH:MM): e.g. 08:20\n",
-    )
-    .await?;
+    bot.edit_message_text(message.chat().id, message.id(), SCHEDULE_TIPS)
+        .await?;
     Ok(())
 }
 
+pub const SCHEDULE_TIPS: &str = "Schedule, daily time (HH:MM), one-shot (YYYY-MM-DD HH:MM) or cron (minute hour day month weekday):
+08:20 - every day at 08:20
+2026-11-01 09:00 - only once
+30 9 * * 1-5 - weekdays at 09:30
+0 8 * * MON - every Monday at 08:00
+0 10 1 * * - 1st of month at 10:00
+0 */3 * * * - every 3 hours
+";
+
 /// Group add push: set push datetime
 pub async fn handle_group_push_datetime(
     bot: Bot,
@@ -172,45 +231,63 @@ pub async fn handle_group_push_datetime(
 ) -> HandlerResult {
     let time_str = msg.text();
     if time_str.is_none() {
-        bot.send_message(msg.chat.id, "Time (HH:MM): e.g. 08:20\n")
-            .await?;
+        bot.send_message(msg.chat.id, SCHEDULE_TIPS).await?;
         return Ok(());
     }
-    let time_str = time_str.unwrap();
-    let time_ok = match NaiveTime::parse_from_str(&time_str, "%H:%M") {
-        Ok(_) => true,
-        Err(_) => false,
+    let schedule = match Schedule::parse(time_str.unwrap()) {
+        Ok(schedule) => schedule,
+        Err(e) => {
+            bot.send_message(msg.chat.id, format!("Wrong format: {e}\n\n{SCHEDULE_TIPS}"))
+                .await?;
+            return Ok(());
+        }
     };
 
-    if !time_ok {
-        bot.send_message(msg.chat.id, "Wrong format. Use HH:MM (e.g. 08:20)\n")
-            .await?;
-        return Ok(());
-    }
-
     let state = dialogue.get().await?.unwrap();
     match state {
         State::GroupPushMsg {
             group_db_id,
             group_name,
             msg_db_id,
+            pool_id,
         } => {
-            let polling_ser = polling_msg::new(db);
+            if let Some(once) = schedule.one_shot_at() {
+                let tz = match group::new(db.clone()).get_by_id(group_db_id).await {
+                    Some(info) => group::parse_time_zone(&info.time_zone).unwrap_or(None),
+                    None => None,
+                };
+                if once <= polling_msg::wall_clock(&Utc::now(), tz.as_ref()) {
+                    bot.send_message(msg.chat.id, "The time has passed, please enter a future time:")
+                        .await?;
+                    return Ok(());
+                }
 
-            let insert_id = polling_ser
-                .add_polling_msg(msg_db_id, group_db_id, time_str)
-                .await?;
-            let return_str = if insert_id > 0 { "Success" } else { "Failed" };
+                // One-shot push has no validity window.
+                let return_str =
+                    save_group_push(&db, msg_db_id, pool_id, group_db_id, schedule.as_str(), None, None)
+                        .await?;
+                dialogue
+                    .update(State::GroupChoose {
+                        group_db_id,
+                        group_name,
+                    })
+                    .await?;
+                bot.send_message(msg.chat.id, return_str)
+                    .reply_markup(group_menu())
+                    .await?;
+                return Ok(());
+            }
 
             dialogue
-                .update(State::GroupChoose {
+                .update(State::GroupPushWindow {
                     group_db_id,
                     group_name,
+                    msg_db_id,
+                    send_time: schedule.to_string(),
+                    pool_id,
                 })
                 .await?;
-            bot.send_message(msg.chat.id, return_str)
-                .reply_markup(group_menu())
-                .await?;
+            bot.send_message(msg.chat.id, WINDOW_TIPS).await?;
 
             return Ok(());
         }
@@ -224,6 +301,90 @@ pub async fn handle_group_push_datetime(
     Ok(())
 }
 
+pub const WINDOW_TIPS: &str = "Validity window (YYYY-MM-DD ~ YYYY-MM-DD), either side can be empty:
+2026-11-01 ~ 2026-11-30 - only in November
+2026-11-01 ~ - from November 1st
+skip - no limit
+";
+
+/// Group add push: set the validity window and save the push
+pub async fn handle_group_push_window(
+    bot: Bot,
+    msg: Message,
+    dialogue: MainDialogue,
+    db: Db,
+) -> HandlerResult {
+    let (group_db_id, group_name, msg_db_id, send_time, pool_id) = match dialogue.get().await?.unwrap() {
+        State::GroupPushWindow {
+            group_db_id,
+            group_name,
+            msg_db_id,
+            send_time,
+            pool_id,
+        } => (group_db_id, group_name, msg_db_id, send_time, pool_id),
+        _ => {
+            bot.send_message(msg.chat.id, "Abnormal status, exited!")
+                .await?;
+            dialogue.update(State::Menu).await?;
+            return Ok(());
+        }
+    };
+
+    let input = msg.text().unwrap_or_default().trim();
+    let (start_date, end_date) = if input.eq_ignore_ascii_case("skip") {
+        (None, None)
+    } else {
+        match polling_msg::parse_date_window(input) {
+            Ok(window) => window,
+            Err(e) => {
+                bot.send_message(msg.chat.id, format!("Wrong format: {e}\n\n{WINDOW_TIPS}"))
+                    .await?;
+                return Ok(());
+            }
+        }
+    };
+
+    let return_str =
+        save_group_push(&db, msg_db_id, pool_id, group_db_id, &send_time, start_date, end_date).await?;
+    dialogue
+        .update(State::GroupChoose {
+            group_db_id,
+            group_name,
+        })
+        .await?;
+    bot.send_message(msg.chat.id, return_str)
+        .reply_markup(group_menu())
+        .await?;
+    Ok(())
+}
+
+async fn save_group_push(
+    db: &Db,
+    msg_db_id: i64,
+    pool_id: i64,
+    group_db_id: i64,
+    send_time: &str,
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+) -> anyhow::Result<&'static str> {
+    let polling_ser = polling_msg::new(db.clone());
+    let insert_id = polling_ser
+        .add_polling_msg(msg_db_id, group_db_id, send_time)
+        .await?;
+    if insert_id <= 0 {
+        return Ok("Failed");
+    }
+    if pool_id > 0 {
+        polling_ser.set_pool(insert_id, pool_id).await?;
+    }
+    if start_date.is_some() || end_date.is_some() {
+        polling_ser
+            .set_date_window(insert_id, start_date, end_date)
+            .await?;
+    }
+    Ok("Success")
+}
+
 /// Group: view the push list
 pub async fn group_view_push(
     bot: Bot,
@@ -246,7 +407,11 @@ pub async fn group_view_push(
         }
     };
 
-    let all_push = polling_msg::new(db).get_group_msgs(group_db_id).await?;
+    let (one_shot, recurring): (Vec<_>, Vec<_>) = polling_msg::new(db)
+        .get_group_msgs(group_db_id)
+        .await?
+        .into_iter()
+        .partition(|push_info| push_info.schedule().is_ok_and(|s| s.is_one_shot()));
 
     let mut keyboard_buttons: Vec<Vec<InlineKeyboardButton>> = vec![
         // back front other button.
@@ -256,17 +421,45 @@ pub async fn group_view_push(
         )],
     ];
 
-    for push_info in all_push {
+    for push_info in &recurring {
         keyboard_buttons.push(vec![InlineKeyboardButton::callback(
-            format!("{}-{}", push_info.send_time, push_info.msg_title),
-            format!("group_delete_push_{}", push_info.id,),
+            format!(
+                "{}🔁 {} - {}{}",
+                if push_info.disabled { "⏸ " } else { "" },
+                push_info.send_time,
+                push_info.msg_title,
+                match push_info.window_str().as_str() {
+                    "" => String::new(),
+                    window => format!(" ({window})"),
+                }
+            ),
+            format!("push_view_{}", push_info.id),
+        )]);
+    }
+    for push_info in &one_shot {
+        keyboard_buttons.push(vec![InlineKeyboardButton::callback(
+            format!(
+                "{}⏱ {} - {}",
+                if push_info.disabled { "⏸ " } else { "" },
+                push_info.send_time,
+                push_info.msg_title
+            ),
+            format!("push_view_{}", push_info.id),
         )]);
     }
 
     let keyboard = InlineKeyboardMarkup::new(keyboard_buttons);
-    bot.edit_message_text(message.chat().id, message.id(), "Click to delete:")
-        .reply_markup(keyboard)
-        .await?;
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        format!(
+            "🔁 Recurring: {}\n⏱ One-shot: {} (cancel it before it goes out)\n⏸ Disabled after a failed send\n\nClick the push for its options:",
+            recurring.len(),
+            one_shot.len()
+        ),
+    )
+    .reply_markup(keyboard)
+    .await?;
     Ok(())
 }
 
@@ -304,3 +497,439 @@ pub async fn group_delete_push(
 
     Ok(())
 }
+
+/// How many deliveries the group report shows
+const DELIVERY_LIMIT: i64 = 20;
+
+/// Group: the last deliveries of the scheduled pushes, failures with the Telegram error.
+pub async fn group_deliveries(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    db: Db,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(State::GroupChoose { group_db_id, group_name }) = dialogue.get().await? else {
+        bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+            .await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+    let Some(info) = group::new(db.clone()).get_by_id(group_db_id).await else {
+        bot.answer_callback_query(q.id.clone()).text("Group not found").await?;
+        return Ok(());
+    };
+
+    let tz = group::parse_time_zone(&info.time_zone).unwrap_or(None);
+    let deliveries = delivery::new(db)
+        .recent_by_group(&info.group_id, DELIVERY_LIMIT)
+        .await?;
+    let failures = deliveries
+        .iter()
+        .filter(|d| d.status == DeliveryStatus::Failed)
+        .count();
+
+    let lines = deliveries
+        .iter()
+        .map(|d| {
+            let line = format!(
+                "{} {} {}",
+                d.status.icon(),
+                polling_msg::wall_clock(&d.scheduled_at, tz.as_ref()).format("%m-%d %H:%M"),
+                html::escape(d.msg_title.as_deref().unwrap_or("(deleted push)")),
+            );
+            match (d.status, d.defer_until) {
+                (DeliveryStatus::Failed, _) => format!(
+                    "<b>{line}</b>\n    ↳ {}",
+                    html::escape(&d.error.chars().take(200).collect::<String>())
+                ),
+                (DeliveryStatus::Skipped, _) => format!("{line}\n    ↳ {}", html::escape(&d.error)),
+                (DeliveryStatus::Pending, Some(until)) => format!(
+                    "{line}\n    ↳ quiet hours, deferred to {}",
+                    polling_msg::wall_clock(&until, tz.as_ref()).format("%m-%d %H:%M")
+                ),
+                _ => line,
+            }
+        })
+        .collect::<Vec<String>>();
+
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        format!(
+            "{}\nLast {} deliveries, {} failed:\n\n{}",
+            html::escape(&group_name),
+            deliveries.len(),
+            failures,
+            if lines.is_empty() { "Nothing sent yet.".to_string() } else { lines.join("\n") }
+        ),
+    )
+    .parse_mode(ParseMode::Html)
+    .reply_markup(InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
+        "⬅️ Back",
+        format!("group_{}_{}", group_db_id, group_name),
+    )]]))
+    .await?;
+    Ok(())
+}
+
+/// Group: set the time zone, the schedules of the group use its wall clock.
+pub async fn group_time_zone(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    db: Db,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let (group_db_id, group_name) = match dialogue.get().await?.unwrap() {
+        State::GroupChoose {
+            group_db_id,
+            group_name,
+        } => (group_db_id, group_name),
+        _ => {
+            bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+                .await?;
+            dialogue.update(State::Menu).await?;
+            return Ok(());
+        }
+    };
+
+    let current = match group::new(db).get_by_id(group_db_id).await {
+        Some(info) if !info.time_zone.is_empty() => info.time_zone,
+        _ => "server time zone".to_string(),
+    };
+
+    dialogue
+        .update(State::GroupTimeZone {
+            group_db_id,
+            group_name: group_name.clone(),
+        })
+        .await?;
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        format!(
+            "{group_name}\nCurrent time zone: {current}\n\n\
+            Send the IANA name, e.g. Asia/Shanghai, Europe/Berlin, America/Sao_Paulo, UTC\n\
+            Send \"local\" to use the server time zone."
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Group: submit the time zone
+pub async fn handle_group_time_zone(
+    bot: Bot,
+    msg: Message,
+    dialogue: MainDialogue,
+    db: Db,
+) -> HandlerResult {
+    let (group_db_id, group_name) = match dialogue.get().await?.unwrap() {
+        State::GroupTimeZone {
+            group_db_id,
+            group_name,
+        } => (group_db_id, group_name),
+        _ => {
+            bot.send_message(msg.chat.id, "Abnormal status, exited!")
+                .await?;
+            dialogue.update(State::Menu).await?;
+            return Ok(());
+        }
+    };
+
+    let input = msg.text().unwrap_or_default().trim();
+    let time_zone = if input.eq_ignore_ascii_case("local") { "" } else { input };
+    let return_str = match group::new(db).set_time_zone(group_db_id, time_zone).await {
+        Ok(true) => "Success".to_string(),
+        Ok(false) => "Failed".to_string(),
+        Err(e) => {
+            bot.send_message(msg.chat.id, format!("{e}, please try again:"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    dialogue
+        .update(State::GroupChoose {
+            group_db_id,
+            group_name,
+        })
+        .await?;
+    bot.send_message(msg.chat.id, return_str)
+        .reply_markup(group_menu())
+        .await?;
+    Ok(())
+}
+
+const QUIET_HOURS_TIPS: &str = "No pushes within the quiet hours, in the group time zone:
+22:00-07:00 - skip the pushes
+22:00-07:00 defer - send them at the end
+add \"welcome\" to welcome the members joined at night at the end, in one message
+e.g. 23:00-08:00 defer welcome
+off - no quiet hours";
+
+/// Group: set the quiet hours
+pub async fn group_quiet_hours(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    db: Db,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(State::GroupChoose { group_db_id, group_name }) = dialogue.get().await? else {
+        bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+            .await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+
+    let current = match group::new(db).get_by_id(group_db_id).await {
+        Some(info) if !info.quiet_hours.is_empty() => info.quiet_hours,
+        _ => "off".to_string(),
+    };
+    dialogue
+        .update(State::GroupQuietHours {
+            group_db_id,
+            group_name: group_name.clone(),
+        })
+        .await?;
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        format!("{group_name}\nCurrent quiet hours: {current}\n\n{QUIET_HOURS_TIPS}"),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Group: submit the quiet hours
+pub async fn handle_group_quiet_hours(
+    bot: Bot,
+    msg: Message,
+    dialogue: MainDialogue,
+    db: Db,
+) -> HandlerResult {
+    let Some(State::GroupQuietHours { group_db_id, group_name }) = dialogue.get().await? else {
+        bot.send_message(msg.chat.id, "Abnormal status, exited!").await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+
+    let return_str = match group::new(db.clone())
+        .set_quiet_hours(group_db_id, msg.text().unwrap_or_default())
+        .await
+    {
+        Ok(true) => "Success",
+        Ok(false) => "Failed",
+        Err(e) => {
+            bot.send_message(msg.chat.id, format!("Wrong format: {e}\n\n{QUIET_HOURS_TIPS}"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    dialogue
+        .update(State::GroupChoose {
+            group_db_id,
+            group_name: group_name.clone(),
+        })
+        .await?;
+    bot.send_message(
+        msg.chat.id,
+        format!("{return_str}\n\n{}", group_menu_text(db, group_db_id, &group_name).await),
+    )
+    .reply_markup(group_menu())
+    .await?;
+    Ok(())
+}
+
+/// Group: pause or resume the scheduled pushes (`polling`) or the welcome message (`welcome`).
+pub async fn group_toggle_mute(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    db: Db,
+    target: &str,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let (group_db_id, group_name) = match dialogue.get().await?.unwrap() {
+        State::GroupChoose {
+            group_db_id,
+            group_name,
+        } => (group_db_id, group_name),
+        _ => {
+            bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+                .await?;
+            dialogue.update(State::Menu).await?;
+            return Ok(());
+        }
+    };
+
+    let group_ser = group::new(db.clone());
+    let Some(info) = group_ser.get_by_id(group_db_id).await else {
+        bot.answer_callback_query(q.id.clone()).text("Group not found").await?;
+        return Ok(());
+    };
+    match target {
+        "polling" => group_ser.set_mute_polling(&info.group_id, !info.mute_polling).await?,
+        _ => group_ser.set_mute_welcome(&info.group_id, !info.mute_welcome).await?,
+    }
+
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        group_menu_text(db, group_db_id, &group_name).await,
+    )
+    .reply_markup(group_menu())
+    .await?;
+    Ok(())
+}
+
+/// Group: the welcome message of the group, falls back to the global one (/himsg).
+pub async fn group_welcome(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    db: Db,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let (group_db_id, group_name) = match dialogue.get().await?.unwrap() {
+        State::GroupChoose {
+            group_db_id,
+            group_name,
+        } => (group_db_id, group_name),
+        _ => {
+            bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+                .await?;
+            dialogue.update(State::Menu).await?;
+            return Ok(());
+        }
+    };
+
+    let current = match msg::new(db).group_welcome_msg(group_db_id).await {
+        Some(welcome_msg) => format!(
+            "Group welcome message:\n\n{} {}",
+            welcome_msg.media_kind.icon(),
+            welcome_msg.text
+        ),
+        None => "No group welcome message, the global one (/himsg) is used.".to_string(),
+    };
+    let keyboard = InlineKeyboardMarkup::new(vec![
+        vec![
+            InlineKeyboardButton::callback("⚙️ Settings", "group_welcome_set"),
+            InlineKeyboardButton::callback("🌍 Use Global", "group_welcome_reset"),
+        ],
+        vec![InlineKeyboardButton::callback(
+            "⬅️ Back",
+            format!("group_{}_{}", group_db_id, group_name),
+        )],
+    ]);
+    bot.edit_message_text(message.chat().id, message.id(), current)
+        .reply_markup(keyboard)
+        .await?;
+    Ok(())
+}
+
+/// Group: start to set the group welcome message
+pub async fn group_welcome_set(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    match dialogue.get().await?.unwrap() {
+        State::GroupChoose {
+            group_db_id,
+            group_name,
+        } => {
+            dialogue
+                .update(State::GroupWelcomeMsg {
+                    group_db_id,
+                    group_name,
+                })
+                .await?;
+            bot.edit_message_text(message.chat().id, message.id(), welcome_tips())
+                .await?;
+        }
+        _ => {
+            bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+                .await?;
+            dialogue.update(State::Menu).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Group: submit the group welcome message
+pub async fn handle_group_welcome_msg(
+    bot: Bot,
+    msg: Message,
+    dialogue: MainDialogue,
+) -> HandlerResult {
+    let (group_db_id, group_name) = match dialogue.get().await?.unwrap() {
+        State::GroupWelcomeMsg {
+            group_db_id,
+            group_name,
+        } => (group_db_id, group_name),
+        _ => {
+            bot.send_message(msg.chat.id, "Abnormal status, exited!")
+                .await?;
+            dialogue.update(State::Menu).await?;
+            return Ok(());
+        }
+    };
+
+    let content = match media::content_of(&msg) {
+        Some(content) if content.media_kind != MediaKind::Text || !content.text.is_empty() => content,
+        _ => {
+            bot.send_message(msg.chat.id, welcome_tips()).await?;
+            return Ok(());
+        }
+    };
+    if let Err(e) = template::validate(&content.text, &MsgType::Welcome) {
+        bot.send_message(msg.chat.id, format!("{e}\nEnter message:"))
+            .await?;
+        return Ok(());
+    }
+
+    dialogue
+        .update(State::SetWelcomeButtons {
+            group_db_id,
+            group_name,
+            content,
+        })
+        .await?;
+    bot.send_message(msg.chat.id, format!("Set the buttons, or 'skip':\n{BUTTON_TIPS}"))
+        .await?;
+    Ok(())
+}
+
+/// Group: remove the group welcome message, use the global one again.
+pub async fn group_welcome_reset(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    db: Db,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let group_db_id = match dialogue.get().await?.unwrap() {
+        State::GroupChoose { group_db_id, .. } => group_db_id,
+        _ => {
+            bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+                .await?;
+            dialogue.update(State::Menu).await?;
+            return Ok(());
+        }
+    };
+
+    msg::new(db).remove_group_welcome_msg(group_db_id).await?;
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        "The global welcome message is used now.",
+    )
+    .reply_markup(group_menu())
+    .await?;
+    Ok(())
+}
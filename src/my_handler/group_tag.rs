@@ -0,0 +1,332 @@
+//! # Group tags
+//! Tag the groups (e.g. vip, cn, en), a push of the tag goes to all the groups having it,
+//! the groups tagged later get the future sends too.
+use crate::my_handler::group_set::{group_menu, SCHEDULE_TIPS, WINDOW_TIPS};
+use crate::service::polling_msg::Schedule;
+use crate::service::{group, msg, polling_msg, pool, Db};
+use crate::{HandlerResult, MainDialogue, State};
+use chrono::{NaiveDate, Utc};
+use teloxide::payloads::EditMessageTextSetters;
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use teloxide::Bot;
+
+/// Group: set the tags of the group
+pub async fn group_tags(bot: Bot, q: CallbackQuery, dialogue: MainDialogue, db: Db) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(State::GroupChoose { group_db_id, group_name }) = dialogue.get().await? else {
+        bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+            .await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+
+    let tags = group::new(db).tags(group_db_id).await?;
+    dialogue
+        .update(State::GroupTags {
+            group_db_id,
+            group_name: group_name.clone(),
+        })
+        .await?;
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        format!(
+            "{group_name}\nCurrent tags: {}\n\nSend the tags separated by spaces, e.g. vip cn\nSend \"-\" to remove all.",
+            if tags.is_empty() { "none".to_string() } else { tags.join(", ") }
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Group: submit the tags
+pub async fn handle_group_tags(bot: Bot, msg: Message, dialogue: MainDialogue, db: Db) -> HandlerResult {
+    let Some(State::GroupTags { group_db_id, group_name }) = dialogue.get().await? else {
+        bot.send_message(msg.chat.id, "Abnormal status, exited!").await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+
+    let input = msg.text().unwrap_or_default().trim();
+    let tags = if input == "-" {
+        Vec::new()
+    } else {
+        match group::parse_tags(input) {
+            Ok(tags) => tags,
+            Err(e) => {
+                bot.send_message(msg.chat.id, format!("{e}\nPlease enter the tags again:"))
+                    .await?;
+                return Ok(());
+            }
+        }
+    };
+    group::new(db).set_tags(group_db_id, &tags).await?;
+
+    dialogue
+        .update(State::GroupChoose {
+            group_db_id,
+            group_name: group_name.clone(),
+        })
+        .await?;
+    bot.send_message(
+        msg.chat.id,
+        format!(
+            "{group_name}\nTags: {}",
+            if tags.is_empty() { "none".to_string() } else { tags.join(", ") }
+        ),
+    )
+    .reply_markup(group_menu())
+    .await?;
+    Ok(())
+}
+
+/// All the tags, click one for its groups and pushes.
+pub async fn tag_list(bot: Bot, q: CallbackQuery, dialogue: MainDialogue, db: Db) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    dialogue.update(State::Group).await?;
+
+    let tags = group::new(db).all_tags().await?;
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = tags
+        .iter()
+        .map(|(tag, count)| {
+            vec![InlineKeyboardButton::callback(
+                format!("🏷 {tag} ({count})"),
+                format!("tag_view_{tag}"),
+            )]
+        })
+        .collect();
+    keyboard.push(vec![InlineKeyboardButton::callback("⬅️ Back", "cancel_group")]);
+
+    let text = if tags.is_empty() {
+        "No tags yet, tag the groups in their menu (🏷 Tags)."
+    } else {
+        "Tags, a push of the tag goes to all the groups having it:"
+    };
+    bot.edit_message_text(message.chat().id, message.id(), text)
+        .reply_markup(InlineKeyboardMarkup::new(keyboard))
+        .await?;
+    Ok(())
+}
+
+/// The groups and the pushes of the tag
+pub async fn tag_view(bot: Bot, q: CallbackQuery, dialogue: MainDialogue, db: Db, tag: &str) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    dialogue.update(State::TagChoose { tag: tag.to_string() }).await?;
+
+    let (text, keyboard) = tag_view_content(&db, tag).await?;
+    bot.edit_message_text(message.chat().id, message.id(), text)
+        .reply_markup(keyboard)
+        .await?;
+    Ok(())
+}
+
+async fn tag_view_content(db: &Db, tag: &str) -> anyhow::Result<(String, InlineKeyboardMarkup)> {
+    let groups = group::new(db.clone()).groups_by_tag(tag).await?;
+    let pushes = polling_msg::new(db.clone()).get_tag_msgs(tag).await?;
+
+    let mut keyboard = vec![vec![InlineKeyboardButton::callback("📲 Add Push", "tag_add_push")]];
+    for push_info in &pushes {
+        let icon = if push_info.schedule().is_ok_and(|s| s.is_one_shot()) { "⏱" } else { "🔁" };
+        keyboard.push(vec![InlineKeyboardButton::callback(
+            format!(
+                "{}{icon} {} - {}{}",
+                if push_info.disabled { "⏸ " } else { "" },
+                push_info.send_time,
+                push_info.msg_title,
+                match push_info.window_str().as_str() {
+                    "" => String::new(),
+                    window => format!(" ({window})"),
+                }
+            ),
+            format!("push_view_{}", push_info.id),
+        )]);
+    }
+    keyboard.push(vec![InlineKeyboardButton::callback("⬅️ Back", "tag_list")]);
+
+    let names = groups
+        .iter()
+        .map(|info| info.group_name.as_str())
+        .collect::<Vec<&str>>()
+        .join(", ");
+    let text = format!(
+        "🏷 {tag}\nGroups ({}): {names}\nPushes: {}\n\nClick the push for its options:",
+        groups.len(),
+        pushes.len()
+    );
+    Ok((text, InlineKeyboardMarkup::new(keyboard)))
+}
+
+/// Tag add push: show the message list
+pub async fn tag_add_push(bot: Bot, q: CallbackQuery, dialogue: MainDialogue, db: Db) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(State::TagChoose { tag }) = dialogue.get().await? else {
+        bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+            .await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> =
+        vec![vec![InlineKeyboardButton::callback("⬅️ Back", format!("tag_view_{tag}"))]];
+    for msg_info in msg::new(db.clone()).all().await {
+        keyboard.push(vec![InlineKeyboardButton::callback(
+            msg_info.msg_title,
+            format!("tag_msg_{}", msg_info.id),
+        )]);
+    }
+    for pool_info in pool::new(db).all().await? {
+        keyboard.push(vec![InlineKeyboardButton::callback(
+            format!("🔀 {}", pool_info.pool_name),
+            format!("tag_pool_{}", pool_info.id),
+        )]);
+    }
+    bot.edit_message_text(message.chat().id, message.id(), format!("🏷 {tag}\nPlease specify the message:\n"))
+        .reply_markup(InlineKeyboardMarkup::new(keyboard))
+        .await?;
+    Ok(())
+}
+
+/// Tag add push: choose the message, or the pool (`msg_db_id` 0)
+pub async fn tag_msg_choose(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    msg_db_id: i64,
+    pool_id: i64,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(State::TagChoose { tag }) = dialogue.get().await? else {
+        bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+            .await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+
+    dialogue.update(State::TagPushMsg { tag, msg_db_id, pool_id }).await?;
+    bot.edit_message_text(message.chat().id, message.id(), SCHEDULE_TIPS)
+        .await?;
+    Ok(())
+}
+
+/// Tag add push: set the schedule, the groups of the tag use their own time zones.
+pub async fn handle_tag_push_datetime(bot: Bot, msg: Message, dialogue: MainDialogue, db: Db) -> HandlerResult {
+    let Some(State::TagPushMsg { tag, msg_db_id, pool_id }) = dialogue.get().await? else {
+        bot.send_message(msg.chat.id, "Abnormal status, exited!").await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+    let schedule = match Schedule::parse(msg.text().unwrap_or_default()) {
+        Ok(schedule) => schedule,
+        Err(e) => {
+            bot.send_message(msg.chat.id, format!("Wrong format: {e}\n\n{SCHEDULE_TIPS}"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    if let Some(once) = schedule.one_shot_at() {
+        if once <= polling_msg::wall_clock(&Utc::now(), None) {
+            bot.send_message(msg.chat.id, "The time has passed, please enter a future time:")
+                .await?;
+            return Ok(());
+        }
+        // One-shot push has no validity window.
+        save_tag_push(&db, &tag, msg_db_id, pool_id, schedule.as_str(), None, None).await?;
+        return show_saved_tag_push(bot, msg, dialogue, db, tag).await;
+    }
+
+    dialogue
+        .update(State::TagPushWindow {
+            tag,
+            msg_db_id,
+            send_time: schedule.to_string(),
+            pool_id,
+        })
+        .await?;
+    bot.send_message(msg.chat.id, WINDOW_TIPS).await?;
+    Ok(())
+}
+
+/// Tag add push: set the validity window and save the push
+pub async fn handle_tag_push_window(bot: Bot, msg: Message, dialogue: MainDialogue, db: Db) -> HandlerResult {
+    let Some(State::TagPushWindow { tag, msg_db_id, send_time, pool_id }) = dialogue.get().await? else {
+        bot.send_message(msg.chat.id, "Abnormal status, exited!").await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+
+    let input = msg.text().unwrap_or_default().trim();
+    let (start_date, end_date) = if input.eq_ignore_ascii_case("skip") {
+        (None, None)
+    } else {
+        match polling_msg::parse_date_window(input) {
+            Ok(window) => window,
+            Err(e) => {
+                bot.send_message(msg.chat.id, format!("Wrong format: {e}\n\n{WINDOW_TIPS}"))
+                    .await?;
+                return Ok(());
+            }
+        }
+    };
+    save_tag_push(&db, &tag, msg_db_id, pool_id, &send_time, start_date, end_date).await?;
+    show_saved_tag_push(bot, msg, dialogue, db, tag).await
+}
+
+async fn save_tag_push(
+    db: &Db,
+    tag: &str,
+    msg_db_id: i64,
+    pool_id: i64,
+    send_time: &str,
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+) -> anyhow::Result<()> {
+    let polling_ser = polling_msg::new(db.clone());
+    let insert_id = polling_ser.add_tag_polling_msg(msg_db_id, tag, send_time).await?;
+    if pool_id > 0 {
+        polling_ser.set_pool(insert_id, pool_id).await?;
+    }
+    if start_date.is_some() || end_date.is_some() {
+        polling_ser.set_date_window(insert_id, start_date, end_date).await?;
+    }
+    Ok(())
+}
+
+/// Back to the tag view after the push is saved
+async fn show_saved_tag_push(bot: Bot, msg: Message, dialogue: MainDialogue, db: Db, tag: String) -> HandlerResult {
+    dialogue.update(State::TagChoose { tag: tag.clone() }).await?;
+    let (text, keyboard) = tag_view_content(&db, &tag).await?;
+    bot.send_message(msg.chat.id, format!("Success\n\n{text}"))
+        .reply_markup(keyboard)
+        .await?;
+    Ok(())
+}
+
+/// Tag: delete the push
+pub async fn tag_delete_push(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    db: Db,
+    push_id: i64,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(State::TagChoose { tag }) = dialogue.get().await? else {
+        bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+            .await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+
+    let is_ok = polling_msg::new(db.clone()).delete_polling_msg_by_id(push_id).await?;
+    let (text, keyboard) = tag_view_content(&db, &tag).await?;
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        format!("{}\n\n{text}", if is_ok { "Success" } else { "Failed" }),
+    )
+    .reply_markup(keyboard)
+    .await?;
+    Ok(())
+}
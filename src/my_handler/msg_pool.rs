@@ -0,0 +1,170 @@
+//! # Message pools
+//! Build the pools from the message list, a push of the pool sends the next message every slot.
+use crate::commands::start_command::poll_msg_menu;
+use crate::service::pool::PoolOrder;
+use crate::service::{msg, pool, Db};
+use crate::{HandlerResult, MainDialogue, State};
+use teloxide::payloads::EditMessageTextSetters;
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use teloxide::Bot;
+
+/// All the pools
+pub async fn pool_list(bot: Bot, q: CallbackQuery, dialogue: MainDialogue, db: Db) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    dialogue.update(State::Menu).await?;
+
+    let pools = pool::new(db).all().await?;
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = pools
+        .iter()
+        .map(|pool_info| {
+            vec![InlineKeyboardButton::callback(
+                format!("🔀 {}", pool_info.pool_name),
+                format!("pool_view_{}", pool_info.id),
+            )]
+        })
+        .collect();
+    keyboard.push(vec![
+        InlineKeyboardButton::callback("➕ New pool", "pool_new"),
+        InlineKeyboardButton::callback("⬅️ Back", "poll_msg_menu"),
+    ]);
+
+    let text = if pools.is_empty() {
+        "No pools yet.\nA pool sends one of its messages every slot of the push, e.g. a tip of the day."
+    } else {
+        "Pools, a push of the pool sends its next message every slot:"
+    };
+    bot.edit_message_text(message.chat().id, message.id(), text)
+        .reply_markup(InlineKeyboardMarkup::new(keyboard))
+        .await?;
+    Ok(())
+}
+
+/// New pool: ask the name
+pub async fn pool_new(bot: Bot, q: CallbackQuery, dialogue: MainDialogue) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    dialogue.update(State::PoolName).await?;
+    bot.edit_message_text(message.chat().id, message.id(), "Send the name of the pool:")
+        .await?;
+    Ok(())
+}
+
+/// New pool: submit the name
+pub async fn handle_pool_name(bot: Bot, msg: Message, dialogue: MainDialogue, db: Db) -> HandlerResult {
+    let pool_id = match pool::new(db.clone()).add_pool(msg.text().unwrap_or_default()).await {
+        Ok(pool_id) => pool_id,
+        Err(e) => {
+            bot.send_message(msg.chat.id, format!("{e}\nPlease enter the name again:"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    dialogue.update(State::Menu).await?;
+    let Some((text, keyboard)) = pool_view_content(&db, pool_id).await? else {
+        bot.send_message(msg.chat.id, "Failed").reply_markup(poll_msg_menu()).await?;
+        return Ok(());
+    };
+    bot.send_message(msg.chat.id, text).reply_markup(keyboard).await?;
+    Ok(())
+}
+
+/// The pool and its messages
+pub async fn pool_view(bot: Bot, q: CallbackQuery, db: Db, pool_id: i64) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some((text, keyboard)) = pool_view_content(&db, pool_id).await? else {
+        bot.edit_message_text(message.chat().id, message.id(), "The pool was deleted")
+            .reply_markup(poll_msg_menu())
+            .await?;
+        return Ok(());
+    };
+    bot.edit_message_text(message.chat().id, message.id(), text)
+        .reply_markup(keyboard)
+        .await?;
+    Ok(())
+}
+
+async fn pool_view_content(db: &Db, pool_id: i64) -> anyhow::Result<Option<(String, InlineKeyboardMarkup)>> {
+    let pool_ser = pool::new(db.clone());
+    let Some(pool_info) = pool_ser.get(pool_id).await? else {
+        return Ok(None);
+    };
+    let items = pool_ser.items(pool_id).await?;
+
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = msg::new(db.clone())
+        .all()
+        .await
+        .into_iter()
+        .map(|msg_info| {
+            let checked = items.iter().any(|item| item.hv_msg_id == msg_info.id);
+            vec![InlineKeyboardButton::callback(
+                format!("{} {}", if checked { "☑️" } else { "⬜" }, msg_info.msg_title),
+                format!("pool_item_{}_{}", pool_id, msg_info.id),
+            )]
+        })
+        .collect();
+    let order = match pool_info.order_mode {
+        PoolOrder::Sequential => "🔢 Sequential",
+        PoolOrder::Random => "🎲 Random",
+    };
+    keyboard.push(vec![
+        InlineKeyboardButton::callback(order, format!("pool_order_{pool_id}")),
+        InlineKeyboardButton::callback("🗑 Delete", format!("pool_delete_{pool_id}")),
+    ]);
+    keyboard.push(vec![InlineKeyboardButton::callback("⬅️ Back", "pool_list")]);
+
+    let list = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| format!("{}. {}", i + 1, item.msg_title))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let text = format!(
+        "🔀 {}\nOrder: {order}\nMessages ({}):\n{list}\n\nClick the messages to add or remove them, schedule the pool in the group or tag menu.",
+        pool_info.pool_name,
+        items.len(),
+    );
+    Ok(Some((text, InlineKeyboardMarkup::new(keyboard))))
+}
+
+/// Add the message to the pool, or remove it
+pub async fn pool_toggle_item(bot: Bot, q: CallbackQuery, db: Db, pool_id: i64, msg_id: i64) -> HandlerResult {
+    pool::new(db.clone()).toggle_item(pool_id, msg_id).await?;
+    pool_view(bot, q, db, pool_id).await
+}
+
+/// Switch the order between sequential and random
+pub async fn pool_toggle_order(bot: Bot, q: CallbackQuery, db: Db, pool_id: i64) -> HandlerResult {
+    let pool_ser = pool::new(db.clone());
+    if let Some(pool_info) = pool_ser.get(pool_id).await? {
+        let order = match pool_info.order_mode {
+            PoolOrder::Sequential => PoolOrder::Random,
+            PoolOrder::Random => PoolOrder::Sequential,
+        };
+        pool_ser.set_order(pool_id, order).await?;
+    }
+    pool_view(bot, q, db, pool_id).await
+}
+
+/// Delete the pool: confirm first, its pushes go too.
+pub async fn confirm_delete_pool(bot: Bot, q: CallbackQuery, db: Db, pool_id: i64) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let pushes = pool::new(db).push_count(pool_id).await?;
+    let keyboard = InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback("🗑 Delete", format!("pool_remove_{pool_id}")),
+        InlineKeyboardButton::callback("⬅️ Back", format!("pool_view_{pool_id}")),
+    ]]);
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        format!("Delete the pool and its {pushes} pushes? The messages are kept."),
+    )
+    .reply_markup(keyboard)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_pool(bot: Bot, q: CallbackQuery, dialogue: MainDialogue, db: Db, pool_id: i64) -> HandlerResult {
+    pool::new(db.clone()).delete_pool(pool_id).await?;
+    pool_list(bot, q, dialogue, db).await
+}
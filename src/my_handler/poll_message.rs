@@ -1,10 +1,16 @@
 use teloxide::Bot;
 use teloxide::prelude::*;
-use teloxide::payloads::{EditMessageTextSetters, SendMessageSetters};
+use teloxide::payloads::{AnswerCallbackQuerySetters, EditMessageTextSetters, SendMessageSetters};
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
 use crate::{HandlerResult, MainDialogue, State};
 use crate::commands::start_command::poll_msg_menu;
-use crate::service::{msg, Db};
-use crate::service::msg::MsgType;
+use crate::service::{drip, msg, polling_msg, pool, Db};
+use crate::my_handler::preview::send_preview;
+use crate::service::msg::{buttons, media, template};
+use crate::service::msg::{MediaKind, MsgContent, MsgType};
+
+/// The syntax of the buttons under the message
+pub const BUTTON_TIPS: &str = "One row per line, buttons separated by '|', e.g.\nRegister - https://example.com/register | Rules - https://t.me/example/2";
 
 pub async fn init_add_poll_message(
     bot: Bot,
@@ -13,31 +19,110 @@ pub async fn init_add_poll_message(
 ) -> HandlerResult {
     dialogue.update(State::AddPollingMsg).await?;
     let message = q.message.as_ref().unwrap();
-    bot.edit_message_text(message.chat().id,  message.id(), "Step 1: Add the message content:").await?;
+    let placeholders = template::placeholders(&MsgType::Polling)
+        .iter()
+        .map(|name| format!("{{{name}}}"))
+        .collect::<Vec<String>>()
+        .join(" ");
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        format!("Step 1: Add the message content, text, a photo/video/document/animation with caption or an album.\nThe formatting is kept, forwarding a message works too.\nPlaceholders: {placeholders}"),
+    )
+    .await?;
     Ok(())
 }
 
-/// Step 1: Add the poll message content
+/// Step 1: Add the poll message content, text or media (photo, video, document, animation)
 pub async fn add_poll_message(
     bot: Bot,
     message: Message,
     dialogue: MainDialogue,
 ) -> HandlerResult {
-    let poll_message_content = message.text().unwrap().trim();
-    if poll_message_content.is_empty() {
-        bot.send_message(message.chat.id, "").await?;
+    let Some(content) = media::content_of(&message) else {
+        bot.send_message(message.chat.id, "Input Error").await?;
+        return Ok(());
+    };
+    if content.media_kind == MediaKind::Text && content.text.is_empty() {
+        bot.send_message(message.chat.id, "Please enter the content:").await?;
         return Ok(())
     }
 
-    if let Some(add_msg) = message.text() {
+    if let Err(e) = template::validate(&content.text, &MsgType::Polling) {
+        bot.send_message(message.chat.id, format!("{e}\nPlease enter the content again:"))
+            .await?;
+        return Ok(());
+    }
+
+    // The first part of the album, the others follow as separate messages.
+    if let Some(media_group_id) = message.media_group_id() {
+        let mut album = MsgContent::default();
+        if let Err(e) = media::add_album_item(&mut album, content) {
+            bot.send_message(message.chat.id, format!("{e}\nPlease enter the content again:"))
+                .await?;
+            return Ok(());
+        }
         dialogue
-            .update(State::AddPollingTitle(add_msg.to_string()))
+            .update(State::AddPollingAlbum {
+                media_group_id: media_group_id.to_string(),
+                content: album,
+            })
             .await?;
-        bot.send_message(message.chat.id, "Step 2: Set the message title:")
+        bot.send_message(message.chat.id, "Album received.\nStep 2: Set the message title:")
             .await?;
-    } else {
-        bot.send_message(message.chat.id, "Input Error").await?;
+        return Ok(());
+    }
+
+    dialogue
+        .update(State::AddPollingContentTitle(content))
+        .await?;
+    bot.send_message(message.chat.id, "Step 2: Set the message title:")
+        .await?;
+    Ok(())
+}
+
+/// Step 1: The other parts of the album
+pub async fn add_poll_album_item(
+    bot: Bot,
+    message: Message,
+    dialogue: MainDialogue,
+) -> HandlerResult {
+    let (media_group_id, mut album) = match dialogue.get().await?.unwrap() {
+        State::AddPollingAlbum { media_group_id, content } => (media_group_id, content),
+        _ => {
+            bot.send_message(message.chat.id, "Status error, auto reset to default")
+                .await?;
+            dialogue.update(State::Menu).await?;
+            return Ok(());
+        }
+    };
+
+    let Some(content) = media::content_of(&message) else {
+        return Ok(());
+    };
+    if message.media_group_id() != Some(media_group_id.as_str()) {
+        bot.send_message(message.chat.id, "Only one album per message.\nStep 2: Set the message title:")
+            .await?;
+        return Ok(());
+    }
+
+    if let Err(e) = template::validate(&content.text, &MsgType::Polling) {
+        bot.send_message(message.chat.id, format!("{e}\nThe item is skipped."))
+            .await?;
+        return Ok(());
     }
+    if let Err(e) = media::add_album_item(&mut album, content) {
+        bot.send_message(message.chat.id, format!("{e}\nThe item is skipped."))
+            .await?;
+        return Ok(());
+    }
+
+    dialogue
+        .update(State::AddPollingAlbum {
+            media_group_id,
+            content: album,
+        })
+        .await?;
     Ok(())
 }
 
@@ -46,17 +131,18 @@ pub async fn add_poll_message_title(
     bot: Bot,
     message: Message,
     dialogue: MainDialogue,
-    db: Db,
 ) -> HandlerResult {
     let state = dialogue.get().await?.unwrap();
-    let message_title = message.text().unwrap().trim();
+    let message_title = message.text().unwrap_or_default().trim();
     if message_title.is_empty() {
         bot.send_message(message.chat.id, "Input Error").await?;
         return Ok(());
     }
 
     let message_content= match state {
-        State::AddPollingTitle(add_msg) => {add_msg},
+        State::AddPollingTitle(text) => MsgContent::text(&text),
+        State::AddPollingContentTitle(content) => content,
+        State::AddPollingAlbum { content, .. } => content,
         _ => {
             bot.send_message(message.chat.id, "Status error, auto reset to default")
                 .await?;
@@ -66,21 +152,134 @@ pub async fn add_poll_message_title(
         }
     };
 
-    let insert_id = msg::new(db).add_msg(MsgType::Polling, &message_content, message_title).await;
-    if insert_id <= 0  {
+    // Albums can not have buttons
+    if message_content.media_kind == MediaKind::Album {
+        return preview_poll_message_draft(bot, &message, dialogue, message_content, message_title).await;
+    }
+
+    dialogue
+        .update(State::AddPollingButtons {
+            content: message_content,
+            title: message_title.to_string(),
+        })
+        .await?;
+    bot.send_message(message.chat.id, format!("Step 3: Set the buttons, or 'skip':\n{BUTTON_TIPS}"))
+        .await?;
+    Ok(())
+}
+
+/// Step 3: Add the buttons of the poll message, then preview it.
+pub async fn add_poll_message_buttons(
+    bot: Bot,
+    message: Message,
+    dialogue: MainDialogue,
+) -> HandlerResult {
+    let (mut content, title) = match dialogue.get().await?.unwrap() {
+        State::AddPollingButtons { content, title } => (content, title),
+        _ => {
+            bot.send_message(message.chat.id, "Status error, auto reset to default")
+                .await?;
+            dialogue.update(State::Menu).await?;
+            return Ok(());
+        }
+    };
+
+    content.buttons = match parse_buttons_input(message.text().unwrap_or_default()) {
+        Ok(buttons) => buttons,
+        Err(e) => {
+            bot.send_message(message.chat.id, format!("{e}\nPlease enter the buttons again, or 'skip':"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    preview_poll_message_draft(bot, &message, dialogue, content, &title).await
+}
+
+/// The buttons input of the admin, 'skip' is no buttons.
+pub fn parse_buttons_input(input: &str) -> anyhow::Result<String> {
+    let input = input.trim();
+    if input.eq_ignore_ascii_case("skip") {
+        return Ok(String::new());
+    }
+    buttons::normalize(input)
+}
+
+/// Step 4: Send the draft to the admin the same way the groups get it, and wait for the confirmation.
+/// Telegram rejecting the draft (e.g. a bad file or link) is reported, the admin enters it again.
+async fn preview_poll_message_draft(
+    bot: Bot,
+    message: &Message,
+    dialogue: MainDialogue,
+    content: MsgContent,
+    title: &str,
+) -> HandlerResult {
+    if let Err(e) = send_preview(&bot, message.chat.id, message.from.as_ref(), &content, None).await {
+        dialogue.update(State::AddPollingMsg).await?;
         bot.send_message(
             message.chat.id,
-            "The addition was error, please try again later",
+            format!("Telegram can not send the message: {e}\nStep 1: Please enter the content again:"),
         )
+        .await?;
+        return Ok(());
+    }
+
+    dialogue
+        .update(State::AddPollingConfirm {
+            content,
+            title: title.to_string(),
+        })
+        .await?;
+    bot.send_message(message.chat.id, format!("Save [{title}] as above?"))
+        .reply_markup(InlineKeyboardMarkup::new(vec![vec![
+            InlineKeyboardButton::callback("✅ Confirm", "pollmsg_confirm"),
+            InlineKeyboardButton::callback("✏️ Edit", "pollmsg_edit"),
+            InlineKeyboardButton::callback("Cancel", "cancel"),
+        ]]))
+        .await?;
+    Ok(())
+}
+
+/// Step 4: Save the confirmed message
+pub async fn confirm_poll_message(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    db: Db,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let (content, message_title) = match dialogue.get().await?.unwrap() {
+        State::AddPollingConfirm { content, title } => (content, title),
+        _ => {
+            bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+                .await?;
+            dialogue.update(State::Menu).await?;
+            return Ok(());
+        }
+    };
+
+    let insert_id = msg::new(db)
+        .add_content_msg(MsgType::Polling, &content, &message_title)
+        .await;
+    if insert_id <= 0 {
+        bot.answer_callback_query(q.id)
+            .text("The addition was error, please try again later")
             .await?;
         return Ok(());
     }
 
-    bot.send_message(message.chat.id, format!("[{}] addition was successful!", message_title))
-        .reply_markup(poll_msg_menu()).await?;
+    dialogue.update(State::Menu).await?;
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        format!("[{}] addition was successful!", message_title),
+    )
+    .reply_markup(poll_msg_menu())
+    .await?;
     Ok(())
 }
 
+/// List the messages, click one to manage it.
 pub async fn list_poll_message(
     bot: Bot,
     q: CallbackQuery,
@@ -96,18 +295,510 @@ pub async fn list_poll_message(
         return Ok(())
     }
 
-    let mut list_str = String::from("***List***\n");
-    for msg_item in msg_list {
-        list_str.push_str("\n");
-        list_str.push_str(&format!(
-            "{}:\n{}\n",
-            msg_item.msg_title,
-            msg_item.msg_text
+    let mut keyboard_buttons: Vec<Vec<InlineKeyboardButton>> = msg_list
+        .iter()
+        .map(|msg_item| {
+            vec![InlineKeyboardButton::callback(
+                format!("{} {}", msg_item.media_kind.icon(), msg_item.msg_title),
+                format!("pollmsg_view_{}", msg_item.id),
+            )]
+        })
+        .collect();
+    keyboard_buttons.push(vec![InlineKeyboardButton::callback("⬅️ Back", "poll_msg_menu")]);
+
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        format!("***List*** ({})\n\nClick the message to manage it:", msg_list.len()),
+    )
+    .reply_markup(InlineKeyboardMarkup::new(keyboard_buttons))
+    .await?;
+    Ok(())
+}
+
+/// Telegram text message limit, in UTF-16 code units
+const VIEW_MAX_LEN: usize = 4096;
+
+/// Add the stored text below the header of the view, cut to fit in one message.
+fn with_text(mut view: String, text: &str) -> String {
+    const CUT: &str = "\n… (too long, cut here)";
+    view.push_str("-------------------\n");
+    let used = view.encode_utf16().count();
+    if used + text.encode_utf16().count() <= VIEW_MAX_LEN {
+        view.push_str(text);
+        return view;
+    }
+
+    let budget = VIEW_MAX_LEN.saturating_sub(used + CUT.encode_utf16().count());
+    let mut len = 0;
+    for c in text.chars() {
+        len += c.len_utf16();
+        if len > budget {
+            break;
+        }
+        view.push(c);
+    }
+    view.push_str(CUT);
+    view
+}
+
+/// The view of one message and its action buttons, `None` when it was deleted.
+async fn poll_message_view(db: Db, msg_db_id: i64) -> anyhow::Result<Option<(String, InlineKeyboardMarkup)>> {
+    let Some(msg_item) = msg::new(db.clone()).get_msg(msg_db_id).await? else {
+        return Ok(None);
+    };
+    let push_count = polling_msg::new(db).count_by_msg_id(msg_db_id).await?;
+
+    let mut view = format!(
+        "{} {}\nGroup pushes: {}\nCreated: {}\n",
+        msg_item.media_kind.icon(),
+        msg_item.msg_title,
+        push_count,
+        msg_item.created_at.format("%Y-%m-%d %H:%M UTC"),
+    );
+    if !msg_item.buttons.is_empty() {
+        view.push_str(&format!("Buttons:\n{}\n", msg_item.buttons));
+    }
+    let view = with_text(view, &msg_item.msg_text);
+
+    // The album items have no history, see `Msg::save_revision`.
+    let mut manage = Vec::new();
+    if msg_item.media_kind != MediaKind::Album {
+        manage.push(InlineKeyboardButton::callback("🕘 History", format!("pollmsg_history_{msg_db_id}")));
+    }
+    manage.push(InlineKeyboardButton::callback("🗑 Delete", format!("pollmsg_delete_{msg_db_id}")));
+    let keyboard = InlineKeyboardMarkup::new(vec![
+        vec![
+            InlineKeyboardButton::callback("👁 Preview", format!("pollmsg_preview_{msg_db_id}")),
+            InlineKeyboardButton::callback("✏️ Text", format!("pollmsg_text_{msg_db_id}")),
+            InlineKeyboardButton::callback("🏷 Title", format!("pollmsg_title_{msg_db_id}")),
+        ],
+        manage,
+        vec![InlineKeyboardButton::callback("⬅️ Back", "list_poll_message")],
+    ]);
+    Ok(Some((view, keyboard)))
+}
+
+/// Show one message
+pub async fn view_poll_message(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    db: Db,
+    msg_db_id: i64,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    // Leave the editing states when going back to the view
+    dialogue.update(State::Menu).await?;
+    match poll_message_view(db, msg_db_id).await? {
+        Some((view, keyboard)) => {
+            bot.edit_message_text(message.chat().id, message.id(), view)
+                .reply_markup(keyboard)
+                .await?;
+        }
+        None => {
+            bot.edit_message_text(message.chat().id, message.id(), "The message was deleted")
+                .reply_markup(poll_msg_menu())
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Send the message to the admin as the groups get it.
+pub async fn preview_poll_message(
+    bot: Bot,
+    q: CallbackQuery,
+    db: Db,
+    msg_db_id: i64,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(content) = msg::new(db).get_content(msg_db_id).await? else {
+        bot.answer_callback_query(q.id).text("The message was deleted").await?;
+        return Ok(());
+    };
+
+    match send_preview(&bot, message.chat().id, Some(&q.from), &content, None).await {
+        Ok(_) => bot.answer_callback_query(q.id).await?,
+        Err(e) => {
+            bot.answer_callback_query(q.id)
+                .text(format!("Preview failed: {e}"))
+                .show_alert(true)
+                .await?
+        }
+    };
+    Ok(())
+}
+
+/// Start to edit the text (caption) of the message
+pub async fn init_edit_poll_text(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    db: Db,
+    msg_db_id: i64,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(msg_item) = msg::new(db).get_msg(msg_db_id).await? else {
+        bot.answer_callback_query(q.id).text("The message was deleted").await?;
+        return Ok(());
+    };
+    if msg_item.media_kind == MediaKind::Album {
+        bot.answer_callback_query(q.id)
+            .text("The captions of the album can not be edited, add the album again.")
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    }
+
+    dialogue.update(State::EditPollingText { msg_db_id }).await?;
+    let placeholders = template::placeholders(&MsgType::Polling)
+        .iter()
+        .map(|name| format!("{{{name}}}"))
+        .collect::<Vec<String>>()
+        .join(" ");
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        format!("Enter the new text of [{}]:\nPlaceholders: {placeholders}", msg_item.msg_title),
+    )
+    .reply_markup(InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
+        "⬅️ Cancel",
+        format!("pollmsg_view_{msg_db_id}"),
+    )]]))
+    .await?;
+    Ok(())
+}
+
+/// Submit the new text (caption) of the message
+pub async fn handle_edit_poll_text(
+    bot: Bot,
+    message: Message,
+    dialogue: MainDialogue,
+    db: Db,
+) -> HandlerResult {
+    let msg_db_id = match dialogue.get().await?.unwrap() {
+        State::EditPollingText { msg_db_id } => msg_db_id,
+        _ => {
+            bot.send_message(message.chat.id, "Status error, auto reset to default")
+                .await?;
+            dialogue.update(State::Menu).await?;
+            return Ok(());
+        }
+    };
+
+    let text = match media::content_of(&message) {
+        Some(content) if content.media_kind == MediaKind::Text && !content.text.is_empty() => content.text,
+        _ => {
+            bot.send_message(message.chat.id, "Please enter the text:").await?;
+            return Ok(());
+        }
+    };
+    if let Err(e) = template::validate(&text, &MsgType::Polling) {
+        bot.send_message(message.chat.id, format!("{e}\nPlease enter the text again:"))
+            .await?;
+        return Ok(());
+    }
+
+    // The message with the new text, the same way the groups will get it.
+    let Some(mut content) = msg::new(db.clone()).get_content(msg_db_id).await? else {
+        dialogue.update(State::Menu).await?;
+        bot.send_message(message.chat.id, "The message was deleted")
+            .reply_markup(poll_msg_menu())
+            .await?;
+        return Ok(());
+    };
+    content.text = text.clone();
+    if let Err(e) = send_preview(&bot, message.chat.id, message.from.as_ref(), &content, None).await {
+        bot.send_message(
+            message.chat.id,
+            format!("Telegram can not send the message: {e}\nPlease enter the text again:"),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    dialogue
+        .update(State::EditPollingTextConfirm { msg_db_id, text })
+        .await?;
+    bot.send_message(message.chat.id, "Save the new text as above?")
+        .reply_markup(InlineKeyboardMarkup::new(vec![vec![
+            InlineKeyboardButton::callback("✅ Confirm", "pollmsg_savetext"),
+            InlineKeyboardButton::callback("✏️ Edit", format!("pollmsg_text_{msg_db_id}")),
+            InlineKeyboardButton::callback("Cancel", format!("pollmsg_view_{msg_db_id}")),
+        ]]))
+        .await?;
+    Ok(())
+}
+
+/// Save the confirmed new text of the message
+pub async fn confirm_edit_poll_text(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    db: Db,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(State::EditPollingTextConfirm { msg_db_id, text }) = dialogue.get().await? else {
+        bot.edit_message_text(message.chat().id, message.id(), "Abnormal status, exited!")
+            .await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+
+    let is_ok = msg::new(db.clone())
+        .edit_msg(msg_db_id, &text, &q.from.id.to_string())
+        .await;
+    dialogue.update(State::Menu).await?;
+    send_poll_message_view(&bot, message.chat().id, db, msg_db_id, is_ok).await
+}
+
+/// Start to edit the title of the message
+pub async fn init_edit_poll_title(
+    bot: Bot,
+    q: CallbackQuery,
+    dialogue: MainDialogue,
+    msg_db_id: i64,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    dialogue.update(State::EditPollingTitle { msg_db_id }).await?;
+    bot.edit_message_text(message.chat().id, message.id(), "Enter the new title:")
+        .reply_markup(InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
+            "⬅️ Cancel",
+            format!("pollmsg_view_{msg_db_id}"),
+        )]]))
+        .await?;
+    Ok(())
+}
+
+/// Submit the new title of the message
+pub async fn handle_edit_poll_title(
+    bot: Bot,
+    message: Message,
+    dialogue: MainDialogue,
+    db: Db,
+) -> HandlerResult {
+    let msg_db_id = match dialogue.get().await?.unwrap() {
+        State::EditPollingTitle { msg_db_id } => msg_db_id,
+        _ => {
+            bot.send_message(message.chat.id, "Status error, auto reset to default")
+                .await?;
+            dialogue.update(State::Menu).await?;
+            return Ok(());
+        }
+    };
+
+    let title = message.text().unwrap_or_default().trim();
+    if title.is_empty() {
+        bot.send_message(message.chat.id, "Enter the new title:").await?;
+        return Ok(());
+    }
+
+    let is_ok = msg::new(db.clone())
+        .edit_title(msg_db_id, title, &editor_id(&message))
+        .await;
+    dialogue.update(State::Menu).await?;
+    send_poll_message_view(&bot, message.chat.id, db, msg_db_id, is_ok).await
+}
+
+/// User id of the admin editing the message
+fn editor_id(message: &Message) -> String {
+    message
+        .from
+        .as_ref()
+        .map(|user| user.id.to_string())
+        .unwrap_or_default()
+}
+
+/// Revisions shown in the history
+const HISTORY_LIMIT: i64 = 10;
+
+/// The history of the message, click one to see and restore it.
+pub async fn poll_message_history(
+    bot: Bot,
+    q: CallbackQuery,
+    db: Db,
+    msg_db_id: i64,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let revisions = msg::new(db).revisions(msg_db_id, HISTORY_LIMIT).await?;
+
+    let mut keyboard_buttons: Vec<Vec<InlineKeyboardButton>> = revisions
+        .iter()
+        .map(|revision| {
+            vec![InlineKeyboardButton::callback(
+                format!(
+                    "{} {} by {}",
+                    revision.created_at.format("%Y-%m-%d %H:%M"),
+                    revision.msg_title,
+                    revision.editor_name.as_deref().unwrap_or(&revision.edited_by)
+                ),
+                format!("pollmsg_revision_{}", revision.id),
+            )]
+        })
+        .collect();
+    keyboard_buttons.push(vec![InlineKeyboardButton::callback(
+        "⬅️ Back",
+        format!("pollmsg_view_{msg_db_id}"),
+    )]);
+
+    let text = if revisions.is_empty() {
+        "No edits yet.".to_string()
+    } else {
+        format!(
+            "The last {} edits, the content before each edit (UTC).\nClick to see and restore it:",
+            revisions.len()
+        )
+    };
+    bot.edit_message_text(message.chat().id, message.id(), text)
+        .reply_markup(InlineKeyboardMarkup::new(keyboard_buttons))
+        .await?;
+    Ok(())
+}
+
+/// One revision of the message
+pub async fn view_poll_message_revision(
+    bot: Bot,
+    q: CallbackQuery,
+    db: Db,
+    revision_id: i64,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(revision) = msg::new(db).get_revision(revision_id).await? else {
+        bot.answer_callback_query(q.id).text("The revision was deleted").await?;
+        return Ok(());
+    };
+
+    let mut view = format!(
+        "{} {}\nEdited by {} at {}\n",
+        revision.media_kind.icon(),
+        revision.msg_title,
+        revision.editor_name.as_deref().unwrap_or(&revision.edited_by),
+        revision.created_at.format("%Y-%m-%d %H:%M UTC"),
+    );
+    if !revision.buttons.is_empty() {
+        view.push_str(&format!("Buttons:\n{}\n", revision.buttons));
+    }
+    let view = with_text(view, &revision.msg_text);
+
+    bot.edit_message_text(message.chat().id, message.id(), view)
+        .reply_markup(InlineKeyboardMarkup::new(vec![vec![
+            InlineKeyboardButton::callback("♻️ Restore", format!("pollmsg_restore_{revision_id}")),
+            InlineKeyboardButton::callback("⬅️ Back", format!("pollmsg_history_{}", revision.hv_msg_id)),
+        ]]))
+        .await?;
+    Ok(())
+}
+
+/// Restore the message to the revision
+pub async fn restore_poll_message_revision(
+    bot: Bot,
+    q: CallbackQuery,
+    db: Db,
+    revision_id: i64,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let msg_ser = msg::new(db.clone());
+    let Some(revision) = msg_ser.get_revision(revision_id).await? else {
+        bot.answer_callback_query(q.id).text("The revision was deleted").await?;
+        return Ok(());
+    };
+
+    let is_ok = msg_ser
+        .restore_revision(revision_id, &q.from.id.to_string())
+        .await?;
+    let result = if is_ok { "Restored." } else { "Restoring failed. Please retry." };
+    match poll_message_view(db, revision.hv_msg_id).await? {
+        Some((view, keyboard)) => {
+            bot.edit_message_text(message.chat().id, message.id(), format!("{result}\n\n{view}"))
+                .reply_markup(keyboard)
+                .await?;
+        }
+        None => {
+            bot.edit_message_text(message.chat().id, message.id(), "The message was deleted")
+                .reply_markup(poll_msg_menu())
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Reply the result of the editing with the view of the message
+async fn send_poll_message_view(
+    bot: &Bot,
+    chat_id: ChatId,
+    db: Db,
+    msg_db_id: i64,
+    is_ok: bool,
+) -> HandlerResult {
+    let result = if is_ok { "Saved." } else { "Saving failed. Please retry." };
+    match poll_message_view(db, msg_db_id).await? {
+        Some((view, keyboard)) => {
+            bot.send_message(chat_id, format!("{result}\n\n{view}"))
+                .reply_markup(keyboard)
+                .await?;
+        }
+        None => {
+            bot.send_message(chat_id, "The message was deleted")
+                .reply_markup(poll_msg_menu())
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Ask to confirm the deletion, the group pushes using the message are deleted too.
+pub async fn confirm_delete_poll_message(
+    bot: Bot,
+    q: CallbackQuery,
+    db: Db,
+    msg_db_id: i64,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(msg_item) = msg::new(db.clone()).get_msg(msg_db_id).await? else {
+        bot.answer_callback_query(q.id).text("The message was deleted").await?;
+        return Ok(());
+    };
+    let push_count = polling_msg::new(db.clone()).count_by_msg_id(msg_db_id).await?;
+    let pools = pool::new(db.clone()).pools_of_msg(msg_db_id).await?;
+    let drip_count = drip::new(db).count_by_msg_id(msg_db_id).await?;
+
+    let mut text = format!(
+        "Delete [{}]?\nIt is used by {} group push(es), they are deleted too.",
+        msg_item.msg_title, push_count
+    );
+    if !pools.is_empty() {
+        text.push_str(&format!("\nIt is taken out of {} pool(s): {}", pools.len(), pools.join(", ")));
+    }
+    if drip_count > 0 {
+        text.push_str(&format!(
+            "\nIt is sent by {drip_count} drip step(s), they are deleted too and the members waiting for them do not get it."
         ));
-        list_str.push_str("-------------------")
     }
+    bot.edit_message_text(message.chat().id, message.id(), text)
+        .reply_markup(InlineKeyboardMarkup::new(vec![vec![
+            InlineKeyboardButton::callback("🗑 Delete", format!("pollmsg_remove_{msg_db_id}")),
+            InlineKeyboardButton::callback("⬅️ Cancel", format!("pollmsg_view_{msg_db_id}")),
+        ]]))
+        .await?;
+    Ok(())
+}
 
-    bot.edit_message_text(message.chat().id, message.id(), list_str)
-        .reply_markup(poll_msg_menu()).await?;
+/// Delete the message and its group pushes
+pub async fn delete_poll_message(
+    bot: Bot,
+    q: CallbackQuery,
+    db: Db,
+    msg_db_id: i64,
+) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let is_ok = msg::new(db).remove_msg(msg_db_id).await?;
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        if is_ok { "Deleted." } else { "The message was deleted" },
+    )
+    .reply_markup(poll_msg_menu())
+    .await?;
     Ok(())
-}
\ No newline at end of file
+}
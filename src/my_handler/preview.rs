@@ -0,0 +1,32 @@
+//! # Preview
+//! Show the composed message back to the admin, the same way the groups get it.
+use crate::service::msg::media;
+use crate::service::msg::template::TemplateContext;
+use crate::service::msg::MsgContent;
+use chrono::Local;
+use teloxide::prelude::*;
+use teloxide::types::User;
+use teloxide::RequestError;
+
+/// Group name of the preview when the message is not for one group
+const SAMPLE_GROUP: &str = "Group";
+
+/// Send the content to the admin chat, the admin is the member of the placeholders.
+pub async fn send_preview(
+    bot: &Bot,
+    chat_id: ChatId,
+    admin: Option<&User>,
+    content: &MsgContent,
+    group_name: Option<&str>,
+) -> Result<Message, RequestError> {
+    let ctx = TemplateContext {
+        user_id: admin.map(|user| user.id.0),
+        first_name: admin.map(|user| user.first_name.clone()).unwrap_or_default(),
+        username: admin.and_then(|user| user.username.clone()),
+        group_name: group_name.unwrap_or(SAMPLE_GROUP).to_string(),
+        member_count: None,
+        now: Some(Local::now().naive_local()),
+    };
+    bot.send_message(chat_id, "Preview:").await?;
+    media::send_content(bot, chat_id, content, &ctx).await
+}
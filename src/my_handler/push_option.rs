@@ -0,0 +1,167 @@
+//! # Push options
+//! The menu of one push (group or tag push): auto-delete and pin of the sent messages, delete the push.
+use crate::service::polling_msg::{Cleanup, Pin, PollingMsg};
+use crate::service::{group, polling_msg, Db};
+use crate::{HandlerResult, MainDialogue, State};
+use teloxide::payloads::EditMessageTextSetters;
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use teloxide::Bot;
+
+const CLEANUP_TIPS: &str = "Auto-delete the sent messages, so only the latest one stays:
+previous - delete the previous one when the next is sent
+30m / 2h / 1d - delete it after the time (up to 48h)
+previous 2h - both
+off - keep all the messages";
+
+const PIN_TIPS: &str = "Pin the sent messages, the bot needs the right to pin:
+silent - pin without notifying the members
+notify - pin and notify
+then optionally when to unpin:
+next - when the next one is sent
+12h / 1d - after the time
+e.g. silent next, notify 1d
+off - do not pin";
+
+/// Push: show the options
+pub async fn push_menu(bot: Bot, q: CallbackQuery, db: Db, push_id: i64) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    let Some(push) = polling_msg::new(db).get_by_id(push_id).await? else {
+        bot.answer_callback_query(q.id).text("The push is gone").await?;
+        return Ok(());
+    };
+
+    bot.edit_message_text(message.chat().id, message.id(), push_menu_text(&push))
+        .reply_markup(push_menu_keyboard(&push))
+        .await?;
+    Ok(())
+}
+
+fn push_menu_text(push: &PollingMsg) -> String {
+    let target = if push.tag.is_empty() {
+        push.group_name.clone()
+    } else {
+        format!("🏷 {}", push.tag)
+    };
+    let window = match push.window_str().as_str() {
+        "" => String::new(),
+        window => format!("\nValid: {window}"),
+    };
+    format!(
+        "{target}\nMessage: {}{}\nSchedule: {}{window}\nStatus: {}\nAuto-delete: {}\nPin: {}",
+        if push.pool_id > 0 { "🔀 " } else { "" },
+        push.msg_title,
+        push.send_time,
+        if push.disabled { "⏸ disabled" } else { "▶️ on" },
+        push.cleanup,
+        push.pin,
+    )
+}
+
+fn push_menu_keyboard(push: &PollingMsg) -> InlineKeyboardMarkup {
+    let (back, delete) = if push.tag.is_empty() {
+        ("group_view_push".to_string(), format!("group_delete_push_{}", push.id))
+    } else {
+        (format!("tag_view_{}", push.tag), format!("tag_delete_push_{}", push.id))
+    };
+    InlineKeyboardMarkup::new(vec![
+        vec![
+            InlineKeyboardButton::callback("🧹 Auto-delete", format!("push_cleanup_{}", push.id)),
+            InlineKeyboardButton::callback("📌 Pin", format!("push_pin_{}", push.id)),
+        ],
+        vec![InlineKeyboardButton::callback("🗑 Delete", delete)],
+        vec![InlineKeyboardButton::callback("⬅️ Back", back)],
+    ])
+}
+
+/// Push: set the auto-delete
+pub async fn push_cleanup(bot: Bot, q: CallbackQuery, dialogue: MainDialogue, push_id: i64) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    dialogue.update(State::PushCleanup { push_id }).await?;
+    bot.edit_message_text(message.chat().id, message.id(), CLEANUP_TIPS)
+        .await?;
+    Ok(())
+}
+
+/// Push: submit the auto-delete
+pub async fn handle_push_cleanup(bot: Bot, msg: Message, dialogue: MainDialogue, db: Db) -> HandlerResult {
+    let Some(State::PushCleanup { push_id }) = dialogue.get().await? else {
+        bot.send_message(msg.chat.id, "Abnormal status, exited!").await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+    let cleanup = match Cleanup::parse(msg.text().unwrap_or_default()) {
+        Ok(cleanup) => cleanup,
+        Err(e) => {
+            bot.send_message(msg.chat.id, format!("Wrong format: {e}\n\n{CLEANUP_TIPS}"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    polling_msg::new(db.clone()).set_cleanup(push_id, cleanup).await?;
+    show_saved_push(bot, msg, dialogue, db, push_id).await
+}
+
+/// Push: set the pin
+pub async fn push_pin(bot: Bot, q: CallbackQuery, dialogue: MainDialogue, push_id: i64) -> HandlerResult {
+    let message = q.message.as_ref().unwrap();
+    dialogue.update(State::PushPin { push_id }).await?;
+    bot.edit_message_text(message.chat().id, message.id(), PIN_TIPS)
+        .await?;
+    Ok(())
+}
+
+/// Push: submit the pin
+pub async fn handle_push_pin(bot: Bot, msg: Message, dialogue: MainDialogue, db: Db) -> HandlerResult {
+    let Some(State::PushPin { push_id }) = dialogue.get().await? else {
+        bot.send_message(msg.chat.id, "Abnormal status, exited!").await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+    let pin = match Pin::parse(msg.text().unwrap_or_default()) {
+        Ok(pin) => pin,
+        Err(e) => {
+            bot.send_message(msg.chat.id, format!("Wrong format: {e}\n\n{PIN_TIPS}"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    polling_msg::new(db.clone()).set_pin(push_id, pin).await?;
+    show_saved_push(bot, msg, dialogue, db, push_id).await
+}
+
+/// Back to the push options after saving one
+async fn show_saved_push(bot: Bot, msg: Message, dialogue: MainDialogue, db: Db, push_id: i64) -> HandlerResult {
+    let Some(push) = polling_msg::new(db.clone()).get_by_id(push_id).await? else {
+        bot.send_message(msg.chat.id, "The push is gone").await?;
+        dialogue.update(State::Menu).await?;
+        return Ok(());
+    };
+    back_to_push_list(&dialogue, &db, &push).await?;
+    bot.send_message(msg.chat.id, format!("Success\n\n{}", push_menu_text(&push)))
+        .reply_markup(push_menu_keyboard(&push))
+        .await?;
+    Ok(())
+}
+
+/// The state of the group or tag the push belongs to, for the menu buttons.
+async fn back_to_push_list(dialogue: &MainDialogue, db: &Db, push: &PollingMsg) -> HandlerResult {
+    if !push.tag.is_empty() {
+        dialogue.update(State::TagChoose { tag: push.tag.clone() }).await?;
+        return Ok(());
+    }
+    match group::new(db.clone()).get_by_group_id(&push.group_id).await {
+        Some(info) => {
+            dialogue
+                .update(State::GroupChoose {
+                    group_db_id: info.id,
+                    group_name: info.group_name,
+                })
+                .await?
+        }
+        None => dialogue.update(State::Menu).await?,
+    }
+    Ok(())
+}
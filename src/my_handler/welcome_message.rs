@@ -1,4 +1,8 @@
 use crate::commands::start_command::hi_msg_menu;
+use crate::my_handler::group_set::group_menu;
+use crate::my_handler::poll_message::{parse_buttons_input, BUTTON_TIPS};
+use crate::my_handler::preview::send_preview;
+use crate::service::msg::{media, template, MediaKind, MsgType};
 use crate::service::{msg, Db};
 use crate::{HandlerResult, MainDialogue, State};
 use log::info;
@@ -12,31 +16,98 @@ pub async fn handle_set_welcome_msg(
     bot: Bot,
     message: Message,
     dialogue: MainDialogue,
-    db: Db,
 ) -> HandlerResult {
-    let welcome_msg = message.text().unwrap().trim();
+    let content = match media::content_of(&message) {
+        Some(content) if content.media_kind != MediaKind::Text || !content.text.is_empty() => content,
+        _ => {
+            bot.send_message(message.chat_id().unwrap(), "Enter message:\n")
+                .await?;
+            return Ok(());
+        }
+    };
 
-    if welcome_msg.is_empty() {
-        bot.send_message(message.chat_id().unwrap(), "Enter message:\n")
+    if let Err(e) = template::validate(&content.text, &MsgType::Welcome) {
+        bot.send_message(message.chat.id, format!("{e}\nEnter message:"))
             .await?;
         return Ok(());
     }
 
-    let is_ok = msg::new(db).add_welcome_msg(&welcome_msg).await;
-
-    if is_ok {
-        bot.send_message(
-            message.chat_id().unwrap(),
-            "Welcome message saved. Triggers on new member join.",
-        )
-        .reply_markup(hi_msg_menu())
+    dialogue
+        .update(State::SetWelcomeButtons {
+            group_db_id: msg::GLOBAL_WELCOME,
+            group_name: String::new(),
+            content,
+        })
+        .await?;
+    bot.send_message(message.chat.id, format!("Set the buttons, or 'skip':\n{BUTTON_TIPS}"))
         .await?;
+    Ok(())
+}
+
+/// Buttons of the welcome message (global or group), preview and save it.
+pub async fn handle_set_welcome_buttons(
+    bot: Bot,
+    message: Message,
+    dialogue: MainDialogue,
+    db: Db,
+) -> HandlerResult {
+    let (group_db_id, group_name, mut content) = match dialogue.get().await?.unwrap() {
+        State::SetWelcomeButtons {
+            group_db_id,
+            group_name,
+            content,
+        } => (group_db_id, group_name, content),
+        _ => {
+            bot.send_message(message.chat.id, "Abnormal status, exited!")
+                .await?;
+            dialogue.update(State::Menu).await?;
+            return Ok(());
+        }
+    };
+    let is_global = group_db_id == msg::GLOBAL_WELCOME;
+    let menu = if is_global { hi_msg_menu() } else { group_menu() };
+    // Back to the menu the setting started from
+    let back = if is_global {
+        State::Menu
     } else {
-        bot.send_message(message.chat.id, "Setting failed. Please retry.")
+        State::GroupChoose {
+            group_db_id,
+            group_name: group_name.clone(),
+        }
+    };
+
+    content.buttons = match parse_buttons_input(message.text().unwrap_or_default()) {
+        Ok(buttons) => buttons,
+        Err(e) => {
+            bot.send_message(message.chat.id, format!("{e}\nPlease enter the buttons again, or 'skip':"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let preview_group = (!is_global).then_some(group_name.as_str());
+    if let Err(e) = send_preview(&bot, message.chat.id, message.from.as_ref(), &content, preview_group).await {
+        bot.send_message(message.chat.id, format!("Preview failed, the message is not saved: {e}"))
+            .reply_markup(menu)
             .await?;
+        dialogue.update(back).await?;
+        return Ok(());
     }
 
-    dialogue.update(State::Menu).await?;
+    let is_ok = msg::new(db)
+        .set_group_welcome_msg(group_db_id, &content)
+        .await;
+    dialogue.update(back).await?;
+    bot.send_message(
+        message.chat.id,
+        match (is_ok, is_global) {
+            (true, true) => "Welcome message saved. Triggers on new member join.",
+            (true, false) => "Group welcome message saved.",
+            (false, _) => "Setting failed. Please retry.",
+        },
+    )
+    .reply_markup(menu)
+    .await?;
     Ok(())
 }
 
@@ -48,11 +119,23 @@ pub async fn setting_welcome_message(
     info!("Into the setting welcome message");
     dialogue.update(State::SetWelcomeMsg).await?;
     let message = q.message.as_ref().unwrap();
-    bot.edit_message_text(message.chat().id, message.id(), "Enter welcome message:\n")
+    bot.edit_message_text(message.chat().id, message.id(), welcome_tips())
         .await?;
     Ok(())
 }
 
+/// The prompt of the welcome message, with the placeholders it supports.
+pub fn welcome_tips() -> String {
+    format!(
+        "Enter welcome message, text or a photo/video/document/animation with caption.\nThe formatting is kept, forwarding a message works too.\nPlaceholders: {}",
+        template::placeholders(&MsgType::Welcome)
+            .iter()
+            .map(|name| format!("{{{name}}}"))
+            .collect::<Vec<String>>()
+            .join(" ")
+    )
+}
+
 pub async fn current_welcome_message(bot: Bot, q: CallbackQuery, db: Db) -> HandlerResult {
     info!("Into the current welcome message");
     let welcome_message = msg::new(db).welcome_msg().await;
@@ -65,7 +148,7 @@ pub async fn current_welcome_message(bot: Bot, q: CallbackQuery, db: Db) -> Hand
             ```
             {welcome_message}
             ```
-        💡: *bot will send this message to new group members*
+        💡: *bot will send this message to new members of groups without their own welcome message*
     "
         ),
     )
@@ -0,0 +1,510 @@
+//! # Scheduler
+//! The poll task, run every minute: the scheduled pushes of the groups, the deferred slots,
+//! the auto-unpin and auto-delete of the sent messages, the drip steps and the held welcomes.
+use crate::my_handler;
+use crate::service::msg::template::TemplateContext;
+use crate::service::msg::MediaKind;
+use crate::service::polling_msg::PinMode;
+use crate::service::quiet::{QuietHours, QuietMode};
+use crate::service::send_queue::{classify, Failure, SendQueue, Sending};
+use crate::service::{delivery, drip, group, msg, polling_msg, pool, quiet, Db};
+use chrono::{DateTime, Timelike, Utc};
+use log::info;
+use std::sync::Arc;
+use teloxide::payloads::{PinChatMessageSetters, UnpinChatMessageSetters};
+use teloxide::prelude::Requester;
+use teloxide::types::{ChatId, MessageId};
+use teloxide::Bot;
+
+/// Default catch-up window of the poll task, `POLL_GRACE_MINUTES` in .env overrides it.
+pub(crate) const DEFAULT_GRACE_MINUTES: i64 = 10;
+
+/// Run the poll task at the start of every minute, an error is logged and the next tick goes on.
+pub(crate) async fn run(bot: Bot, db: Db, queue: Arc<SendQueue>, grace_minutes: i64) {
+    loop {
+        match poll_task(&bot, db.clone(), &queue, grace_minutes).await {
+            Ok(_) => log::info!("Poll task completed successfully"),
+            Err(e) => log::error!("Poll task error: {:?}", e),
+        }
+        // Waiting for the next minute
+        let wait = 60 - Utc::now().second() as u64;
+        tokio::time::sleep(std::time::Duration::from_secs(wait)).await;
+    }
+}
+
+/// Polling thread enter
+///
+/// Every due slot within the grace window is claimed in the delivery ledger before sending,
+/// so the missed minutes are caught up and no slot is sent twice.
+/// The sends are queued, see [`SendQueue`], their results are recorded when the queue gets to them.
+async fn poll_task(
+    bot: &Bot,
+    db: Db,
+    queue: &SendQueue,
+    grace_minutes: i64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // Current minute, the schedules have minute precision.
+    let now = Utc::now();
+    let now = now.with_second(0).and_then(|t| t.with_nanosecond(0)).unwrap_or(now);
+    let current_time = now.format("%Y-%m-%d %H:%M UTC").to_string();
+    info!("Executing poll task at {}...", current_time);
+
+    let from = now - chrono::Duration::minutes(grace_minutes.max(0));
+    let polling_ser = polling_msg::new(db.clone());
+    let slots = polling_ser.get_polling_slots(&from, &now).await?;
+
+    let msg_ser = msg::new(db.clone());
+    let ledger = delivery::new(db.clone());
+    for (scheduled_at, push_msg) in slots {
+        // A failed slot does not stop the others, the result is in the ledger.
+        // An error before the claim is tried again by the next tick.
+        if let Err(e) = deliver(bot, &db, queue, &msg_ser, &ledger, &scheduled_at, &push_msg).await {
+            log::error!(
+                "Failed to deliver push {} to group {} scheduled at {}: {:?}",
+                push_msg.id, push_msg.group_id, scheduled_at, e
+            );
+            continue;
+        }
+        // Skipped within the quiet hours, or failed before the send.
+        settle_one_shot(&db, &push_msg).await;
+    }
+
+    // The slots deferred by the quiet hours, sent at the end of the window.
+    for deferred in ledger.due_deferred(&Utc::now()).await? {
+        if let Err(e) = send_deferred(bot, &db, queue, &msg_ser, &ledger, &deferred).await {
+            log::error!(
+                "Failed to send the deferred push {} to group {} scheduled at {}: {:?}",
+                deferred.polling_msg_id, deferred.group_id, deferred.scheduled_at, e
+            );
+        }
+    }
+
+    // Auto-unpin and auto-delete of the sent messages, also the ones due while the bot was down.
+    for due in ledger.due_unpins(&Utc::now()).await? {
+        unpin_sent(bot, &ledger, &due).await;
+    }
+    for due in ledger.due_deletes(&Utc::now()).await? {
+        delete_sent(bot, &ledger, &due).await;
+    }
+
+    // The drip steps of the new members, also the ones due while the bot was down.
+    let drip_ser = drip::new(db.clone());
+    for due in drip_ser.due(&Utc::now()).await? {
+        send_drip(bot, queue, &msg_ser, &drip_ser, &due).await;
+    }
+
+    // The welcomes held by the quiet hours, one message per group.
+    let quiet_ser = quiet::new(db.clone());
+    let held = quiet_ser.due_welcomes(&Utc::now()).await?;
+    for members in held.chunk_by(|a, b| a.group_id == b.group_id) {
+        send_held_welcomes(bot, &db, queue, &quiet_ser, members).await;
+    }
+
+    // One-shot pushes missed beyond the window never go out.
+    let expired = polling_ser.delete_expired(&from).await?;
+    if expired > 0 {
+        log::warn!("{} one-shot pushes expired without sending", expired);
+    }
+    Ok(())
+}
+
+/// Claim the slot of the push and send it to its group, unless within the quiet hours of the group.
+async fn deliver(
+    bot: &Bot,
+    db: &Db,
+    queue: &SendQueue,
+    msg_ser: &msg::Msg,
+    ledger: &delivery::DeliveryDb,
+    scheduled_at: &DateTime<Utc>,
+    push_msg: &polling_msg::PollingMsg,
+) -> anyhow::Result<()> {
+    // Already sent (or being sent) by an earlier tick.
+    if !ledger.claim(push_msg.id, &push_msg.group_id, scheduled_at).await? {
+        return Ok(());
+    }
+
+    let quiet = group::new(db.clone())
+        .get_by_group_id(&push_msg.group_id)
+        .await
+        .and_then(|info| info.quiet());
+    if let Some(quiet) = quiet {
+        let tz = push_msg.tz();
+        if quiet.is_quiet(scheduled_at, tz.as_ref()) {
+            match quiet.mode {
+                QuietMode::Skip => {
+                    ledger.mark_skipped(push_msg.id, &push_msg.group_id, scheduled_at, "Quiet hours").await?;
+                }
+                QuietMode::Defer => {
+                    let until = quiet.end_after(scheduled_at, tz.as_ref());
+                    ledger.defer(push_msg.id, &push_msg.group_id, scheduled_at, &until).await?;
+                    info!("Push {} to group {} deferred to {}", push_msg.id, push_msg.group_id, until);
+                }
+            }
+            return Ok(());
+        }
+    }
+    send_slot(bot, db, queue, msg_ser, ledger, scheduled_at, push_msg).await
+}
+
+/// Send the deferred slot at the end of the quiet hours, with the push as it is now.
+async fn send_deferred(
+    bot: &Bot,
+    db: &Db,
+    queue: &SendQueue,
+    msg_ser: &msg::Msg,
+    ledger: &delivery::DeliveryDb,
+    deferred: &delivery::Delivery,
+) -> anyhow::Result<()> {
+    if !ledger.take_deferred(deferred.id).await? {
+        return Ok(());
+    }
+    let (push_id, group_id, scheduled_at) = (deferred.polling_msg_id, &deferred.group_id, &deferred.scheduled_at);
+    let polling_ser = polling_msg::new(db.clone());
+    let Some(mut push_msg) = polling_ser.get_by_id(push_id).await? else {
+        ledger.mark_skipped(push_id, group_id, scheduled_at, "The push was deleted").await?;
+        return Ok(());
+    };
+    let Some(info) = group::new(db.clone()).get_by_group_id(group_id).await else {
+        ledger.mark_skipped(push_id, group_id, scheduled_at, "The group is no longer managed").await?;
+        return Ok(());
+    };
+    if push_msg.disabled || info.mute_polling {
+        ledger.mark_skipped(push_id, group_id, scheduled_at, "Paused").await?;
+        settle_one_shot(db, &push_msg).await;
+        return Ok(());
+    }
+
+    // A tag push has no group of its own.
+    push_msg.group_id = info.group_id;
+    push_msg.group_name = info.group_name;
+    push_msg.time_zone = info.time_zone;
+    send_slot(bot, db, queue, msg_ser, ledger, scheduled_at, &push_msg).await?;
+    settle_one_shot(db, &push_msg).await;
+    Ok(())
+}
+
+/// A one-shot push expires once every group it goes to has its final delivery,
+/// a group still pending, deferred or not claimed yet (tried again next tick) keeps it.
+async fn settle_one_shot(db: &Db, push_msg: &polling_msg::PollingMsg) {
+    if !push_msg.schedule().is_ok_and(|s| s.is_one_shot()) {
+        return;
+    }
+    let polling_ser = polling_msg::new(db.clone());
+    let settle = async {
+        let groups = polling_ser.target_groups(push_msg.id).await?;
+        if delivery::new(db.clone()).is_settled(push_msg.id, &groups).await? {
+            polling_ser.delete_polling_msg_by_id(push_msg.id).await?;
+        }
+        anyhow::Ok(())
+    };
+    if let Err(e) = settle.await {
+        log::error!("Failed to settle the one-shot push {}: {:?}", push_msg.id, e);
+    }
+}
+
+/// Send the claimed slot of the push to its group and record the result in the ledger.
+async fn send_slot(
+    bot: &Bot,
+    db: &Db,
+    queue: &SendQueue,
+    msg_ser: &msg::Msg,
+    ledger: &delivery::DeliveryDb,
+    scheduled_at: &DateTime<Utc>,
+    push_msg: &polling_msg::PollingMsg,
+) -> anyhow::Result<()> {
+    let mut content = push_msg.content();
+    if content.media_kind == MediaKind::Album {
+        content.album = msg_ser.album(push_msg.hv_msg_id).await?;
+    }
+
+    // A pool push sends the next message of the pool, the cursor moves once per claimed slot.
+    if push_msg.pool_id > 0 {
+        let next = pool::new(db.clone()).next_msg(push_msg.pool_id, &push_msg.group_id).await?;
+        let next = match next {
+            Some(msg_id) => msg_ser.get_content(msg_id).await?,
+            None => None,
+        };
+        let Some(next) = next else {
+            let error = "The message pool is empty";
+            ledger.mark_failed(push_msg.id, &push_msg.group_id, scheduled_at, error).await?;
+            log::error!("Push {}: {}", push_msg.id, error);
+            my_handler::alert::notify_push_failure(bot, db, push_msg, scheduled_at, error).await;
+            return Ok(());
+        };
+        content = next;
+    }
+
+    info!("Push group_id is: {:?}", push_msg);
+    let group_id: i64 = match push_msg.group_id.parse() {
+        Ok(group_id) => group_id,
+        Err(e) => {
+            let error = format!("Invalid group id {:?}: {e}", push_msg.group_id);
+            ledger.mark_failed(push_msg.id, &push_msg.group_id, scheduled_at, &error).await?;
+            log::error!("Push {}: {}", push_msg.id, error);
+            return Ok(());
+        }
+    };
+    let member_count = if content.uses("member_count") {
+        bot.get_chat_member_count(ChatId(group_id)).await.ok()
+    } else {
+        None
+    };
+    let ctx = TemplateContext {
+        group_name: push_msg.group_name.clone(),
+        member_count,
+        now: Some(polling_msg::wall_clock(scheduled_at, push_msg.tz().as_ref())),
+        ..Default::default()
+    };
+    // The result is recorded once the queue has sent it, the poll task goes on.
+    let sending = queue.enqueue(ChatId(group_id), content, ctx);
+    let (bot, db, push_msg, scheduled_at) = (bot.clone(), db.clone(), push_msg.clone(), *scheduled_at);
+    tokio::spawn(async move {
+        if let Err(e) = record_sent(&bot, &db, &push_msg, &scheduled_at, sending).await {
+            log::error!(
+                "Failed to record push {} to group {} scheduled at {}: {:?}",
+                push_msg.id, push_msg.group_id, scheduled_at, e
+            );
+        }
+    });
+    Ok(())
+}
+
+/// Record the result of the queued slot in the ledger, then pin or clean up as the push options say.
+async fn record_sent(
+    bot: &Bot,
+    db: &Db,
+    push_msg: &polling_msg::PollingMsg,
+    scheduled_at: &DateTime<Utc>,
+    sending: Sending,
+) -> anyhow::Result<()> {
+    let ledger = delivery::new(db.clone());
+    match sending.result().await {
+        Ok(sent) => {
+            ledger.mark_sent(push_msg.id, &push_msg.group_id, scheduled_at, sent.id.0).await?;
+            info!(
+                "Successfully sent message to group {} scheduled at {}",
+                push_msg.group_id, scheduled_at
+            );
+            after_sent(bot, &ledger, push_msg, scheduled_at, sent.chat.id, sent.id).await?;
+        }
+        Err(e) => {
+            ledger
+                .mark_failed(push_msg.id, &push_msg.group_id, scheduled_at, &e.to_string())
+                .await?;
+            log::error!(
+                "Failed to send push {} to group {} scheduled at {} ({}): {}",
+                push_msg.id,
+                push_msg.group_id,
+                scheduled_at,
+                if e.permanent { "permanent" } else { "gave up" },
+                e
+            );
+            // Retrying does not help, the admins have to fix or disable it.
+            if e.permanent {
+                my_handler::alert::notify_push_failure(bot, db, push_msg, scheduled_at, &e.to_string())
+                    .await;
+            }
+        }
+    }
+    settle_one_shot(db, push_msg).await;
+    Ok(())
+}
+
+/// Pin the sent message, then unpin and delete the previous ones as the push options say.
+async fn after_sent(
+    bot: &Bot,
+    ledger: &delivery::DeliveryDb,
+    push_msg: &polling_msg::PollingMsg,
+    scheduled_at: &DateTime<Utc>,
+    chat_id: ChatId,
+    message_id: MessageId,
+) -> anyhow::Result<()> {
+    let pin = push_msg.pin;
+    if !pin.is_off() {
+        let pinned = bot
+            .pin_chat_message(chat_id, message_id)
+            .disable_notification(pin.mode == PinMode::Silent)
+            .await;
+        match pinned {
+            Ok(_) => {
+                let unpin_at = pin.unpin_after.map(|minutes| Utc::now() + chrono::Duration::minutes(minutes));
+                ledger
+                    .mark_pinned(push_msg.id, &push_msg.group_id, scheduled_at, unpin_at.as_ref())
+                    .await?;
+            }
+            // The bot may lack the right, the message is sent anyway.
+            Err(e) => log::warn!("Failed to pin message {} in group {}: {}", message_id, chat_id, e),
+        }
+    }
+    if pin.unpin_previous {
+        for previous in ledger.previous_pinned(push_msg.id, &push_msg.group_id, scheduled_at).await? {
+            unpin_sent(bot, ledger, &previous).await;
+        }
+    }
+
+    let cleanup = push_msg.cleanup;
+    if let Some(minutes) = cleanup.delete_after {
+        let delete_at = Utc::now() + chrono::Duration::minutes(minutes);
+        ledger.schedule_delete(push_msg.id, &push_msg.group_id, scheduled_at, &delete_at).await?;
+    }
+    // The new one is visible, the previous ones go.
+    if cleanup.delete_previous {
+        for previous in ledger.previous_sent(push_msg.id, &push_msg.group_id, scheduled_at).await? {
+            delete_sent(bot, ledger, &previous).await;
+        }
+    }
+    Ok(())
+}
+
+/// Unpin the pinned message of the delivery, a permanent failure is not tried again.
+async fn unpin_sent(bot: &Bot, ledger: &delivery::DeliveryDb, pinned: &delivery::Delivery) {
+    let (Ok(group_id), Some(message_id)) = (pinned.group_id.parse::<i64>(), pinned.message_id) else {
+        return;
+    };
+    let result = bot
+        .unpin_chat_message(ChatId(group_id))
+        .message_id(MessageId(message_id))
+        .await;
+    if let Err(e) = &result {
+        log::warn!("Failed to unpin message {} in group {}: {}", message_id, group_id, e);
+        if classify(e) != Failure::Permanent {
+            return;
+        }
+    }
+    if let Err(e) = ledger.mark_unpinned(pinned.id).await {
+        log::error!("Failed to mark delivery {} unpinned: {:?}", pinned.id, e);
+    }
+}
+
+/// Delete the sent message of the delivery, only the first item of an album is tracked.
+/// A permanent failure (already deleted, older than 48 hours...) is not tried again.
+async fn delete_sent(bot: &Bot, ledger: &delivery::DeliveryDb, sent: &delivery::Delivery) {
+    let (Ok(group_id), Some(message_id)) = (sent.group_id.parse::<i64>(), sent.message_id) else {
+        return;
+    };
+    let result = bot.delete_message(ChatId(group_id), MessageId(message_id)).await;
+    if let Err(e) = &result {
+        log::warn!("Failed to delete message {} in group {}: {}", message_id, group_id, e);
+        if classify(e) != Failure::Permanent {
+            return;
+        }
+    }
+    if let Err(e) = ledger.mark_deleted(sent.id).await {
+        log::error!("Failed to mark delivery {} deleted: {:?}", sent.id, e);
+    }
+}
+
+/// Send the drip step to the group of the member, a step is sent once at most.
+async fn send_drip(bot: &Bot, queue: &SendQueue, msg_ser: &msg::Msg, drip_ser: &drip::DripDb, due: &drip::DripDue) {
+    // Within the quiet hours the step waits, it is still due at the end.
+    let tz = group::parse_time_zone(&due.time_zone).unwrap_or(None);
+    if QuietHours::parse(&due.quiet_hours).ok().flatten().is_some_and(|quiet| quiet.is_quiet(&Utc::now(), tz.as_ref())) {
+        return;
+    }
+    match drip_ser.claim(due.id).await {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(e) => {
+            log::error!("Failed to claim drip step {}: {:?}", due.id, e);
+            return;
+        }
+    }
+    // The welcome of the group is paused, its drip steps too.
+    if due.mute_welcome {
+        info!("Drip step {} of group {} dropped, the welcome is paused", due.id, due.group_id);
+        return;
+    }
+
+    let content = match msg_ser.get_content(due.hv_msg_id).await {
+        Ok(Some(content)) => content,
+        Ok(None) => return,
+        Err(e) => {
+            log::error!("Failed to load the message {} of drip step {}: {:?}", due.hv_msg_id, due.id, e);
+            return;
+        }
+    };
+    let Ok(group_id) = due.group_id.parse::<i64>() else {
+        log::error!("Drip step {}: invalid group id {:?}", due.id, due.group_id);
+        return;
+    };
+    let member_count = if content.uses("member_count") {
+        bot.get_chat_member_count(ChatId(group_id)).await.ok()
+    } else {
+        None
+    };
+    let ctx = TemplateContext {
+        user_id: Some(due.user_id as u64),
+        first_name: due.first_name.clone(),
+        username: due.username.clone(),
+        group_name: due.group_name.clone(),
+        member_count,
+        now: Some(polling_msg::wall_clock(&Utc::now(), tz.as_ref())),
+    };
+    let sending = queue.enqueue(ChatId(group_id), content, ctx);
+    let (step_id, user_id) = (due.id, due.user_id);
+    tokio::spawn(async move {
+        if let Err(e) = sending.result().await {
+            log::error!("Failed to send drip step {} to {} in group {}: {}", step_id, user_id, group_id, e);
+        }
+    });
+}
+
+/// Welcome the members who joined the group within the quiet hours, in one message.
+async fn send_held_welcomes(bot: &Bot, db: &Db, queue: &SendQueue, quiet_ser: &quiet::QuietDb, members: &[quiet::HeldWelcome]) {
+    let mut claimed = Vec::new();
+    for held in members {
+        match quiet_ser.claim(held.id).await {
+            Ok(true) => claimed.push(held),
+            Ok(false) => {}
+            Err(e) => log::error!("Failed to claim the held welcome {}: {:?}", held.id, e),
+        }
+    }
+    let Some(first) = claimed.first() else {
+        return;
+    };
+    let Some(info) = group::new(db.clone()).get_by_group_id(&first.group_id).await else {
+        return;
+    };
+    if info.mute_welcome {
+        info!("{} held welcomes of group {} dropped, the welcome is paused", claimed.len(), info.group_id);
+        return;
+    }
+    let Ok(group_id) = info.group_id.parse::<i64>() else {
+        log::error!("Held welcome: invalid group id {:?}", info.group_id);
+        return;
+    };
+
+    let content = msg::new(db.clone()).welcome_msg_for(Some(info.id)).await;
+    let member_count = if content.uses("member_count") {
+        bot.get_chat_member_count(ChatId(group_id)).await.ok()
+    } else {
+        None
+    };
+    let mut ctx = TemplateContext {
+        group_name: info.group_name.clone(),
+        member_count,
+        now: Some(polling_msg::wall_clock(&Utc::now(), info.tz().as_ref())),
+        ..Default::default()
+    };
+    match claimed.as_slice() {
+        [one] => {
+            ctx.user_id = Some(one.user_id as u64);
+            ctx.first_name = one.first_name.clone();
+            ctx.username = one.username.clone();
+        }
+        // Several members in one message, named without the mention links.
+        many => {
+            ctx.first_name = many
+                .iter()
+                .map(|held| held.first_name.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ");
+        }
+    }
+    let sending = queue.enqueue(ChatId(group_id), content, ctx);
+    tokio::spawn(async move {
+        if let Err(e) = sending.result().await {
+            log::error!("Failed to send the held welcomes to group {}: {}", group_id, e);
+        }
+    });
+}
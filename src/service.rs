@@ -2,6 +2,11 @@ pub mod user;
 pub mod msg;
 pub mod group;
 pub mod polling_msg;
+pub mod delivery;
+pub mod send_queue;
+pub mod pool;
+pub mod drip;
+pub mod quiet;
 
 use sqlx::SqlitePool;
 
@@ -22,6 +27,15 @@ pub async fn new(path: &str) -> Db {
 /// hv_msg 设置消息
 /// hv_group 机器人加入的群
 /// hv_polling_msg 群定时推送消息设置
+/// hv_push_delivery 定时推送的发送记录
+/// hv_msg_album 相册消息的图片/视频
+/// hv_msg_revision 消息的修改历史
+/// hv_group_tag 群的标签, 推送和广播可以按标签选群
+/// hv_msg_pool 消息池, 一个推送轮流发送池里的消息
+/// hv_pool_cursor 每个群本轮已发送的池消息
+/// hv_drip_step 新成员入群后按延时依次发送的消息
+/// hv_drip_pending 待发送给新成员的步骤, 重启后继续
+/// hv_welcome_held 免打扰时段入群的成员, 时段结束后一起欢迎
 async fn init_db(conn: &SqlitePool) -> bool {
     // user table
     let _ = sqlx::query(
@@ -31,6 +45,7 @@ id INTEGER PRIMARY KEY AUTOINCREMENT,
 user_id VARCHAR(32) NOT NULL,
 user_name VARCHAR(32) NOT NULL,
 is_admin BOOLEAN DEFAULT FALSE,
+notify_failures BOOLEAN NOT NULL DEFAULT TRUE,
 created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP);
 
 CREATE TABLE IF NOT EXISTS hv_msg (
@@ -38,6 +53,10 @@ id INTEGER PRIMARY KEY AUTOINCREMENT,
 msg_title VARCHAR(32) DEFAULT '',
 msg_type INTEGER NOT NULL DEFAULT 1,
 msg_text TEXT NOT NULL,
+hv_group_id INTEGER NOT NULL DEFAULT 0,
+media_kind VARCHAR(16) NOT NULL DEFAULT 'text',
+file_id TEXT NOT NULL DEFAULT '',
+buttons TEXT NOT NULL DEFAULT '',
 created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP);
 
 CREATE TABLE IF NOT EXISTS hv_group (
@@ -46,6 +65,9 @@ group_id VARCHAR(32) NOT NULL,
 group_name VARCHAR(32) NOT NULL,
 mute_polling BOOLEAN DEFAULT FALSE,
 mute_welcome BOOLEAN DEFAULT FALSE,
+time_zone VARCHAR(64) NOT NULL DEFAULT '',
+polling_resumed_at TIMESTAMP,
+quiet_hours VARCHAR(32) NOT NULL DEFAULT '',
 created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP);
 
 CREATE TABLE IF NOT EXISTS hv_tele_group (
@@ -60,12 +82,168 @@ CREATE TABLE IF NOT EXISTS hv_polling_msg (
 id INTEGER PRIMARY KEY AUTOINCREMENT,
 hv_msg_id INTEGER NOT NULL,
 group_id VARCHAR(32) NOT NULL,
-send_time VARCHAR(8) NOT NULL,
+send_time VARCHAR(64) NOT NULL,
+tag VARCHAR(32) NOT NULL DEFAULT '',
+disabled BOOLEAN NOT NULL DEFAULT FALSE,
+delete_previous BOOLEAN NOT NULL DEFAULT FALSE,
+delete_after INTEGER,
+pin_mode VARCHAR(16) NOT NULL DEFAULT 'off',
+unpin_previous BOOLEAN NOT NULL DEFAULT FALSE,
+unpin_after INTEGER,
+pool_id INTEGER NOT NULL DEFAULT 0,
+start_date DATE,
+end_date DATE,
+created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP);
+
+CREATE TABLE IF NOT EXISTS hv_push_delivery (
+id INTEGER PRIMARY KEY AUTOINCREMENT,
+polling_msg_id INTEGER NOT NULL,
+group_id VARCHAR(32) NOT NULL,
+scheduled_at TIMESTAMP NOT NULL,
+status VARCHAR(16) NOT NULL,
+message_id INTEGER,
+error TEXT NOT NULL DEFAULT '',
+attempted_at TIMESTAMP,
+delete_at TIMESTAMP,
+deleted_at TIMESTAMP,
+pinned BOOLEAN NOT NULL DEFAULT FALSE,
+unpin_at TIMESTAMP,
+defer_until TIMESTAMP,
+created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+UNIQUE (polling_msg_id, group_id, scheduled_at));
+
+CREATE TABLE IF NOT EXISTS hv_msg_album (
+id INTEGER PRIMARY KEY AUTOINCREMENT,
+hv_msg_id INTEGER NOT NULL,
+position INTEGER NOT NULL,
+media_kind VARCHAR(16) NOT NULL,
+file_id TEXT NOT NULL,
+caption TEXT NOT NULL DEFAULT '');
+
+CREATE TABLE IF NOT EXISTS hv_msg_revision (
+id INTEGER PRIMARY KEY AUTOINCREMENT,
+hv_msg_id INTEGER NOT NULL,
+msg_title VARCHAR(32) DEFAULT '',
+msg_text TEXT NOT NULL,
+media_kind VARCHAR(16) NOT NULL DEFAULT 'text',
+file_id TEXT NOT NULL DEFAULT '',
+buttons TEXT NOT NULL DEFAULT '',
+edited_by VARCHAR(32) NOT NULL,
+created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP);
+
+CREATE TABLE IF NOT EXISTS hv_group_tag (
+id INTEGER PRIMARY KEY AUTOINCREMENT,
+hv_group_id INTEGER NOT NULL,
+tag VARCHAR(32) NOT NULL,
+created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+UNIQUE (hv_group_id, tag));
+
+CREATE TABLE IF NOT EXISTS hv_msg_pool (
+id INTEGER PRIMARY KEY AUTOINCREMENT,
+pool_name VARCHAR(32) NOT NULL,
+order_mode VARCHAR(16) NOT NULL DEFAULT 'sequential',
+created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP);
+
+CREATE TABLE IF NOT EXISTS hv_msg_pool_item (
+id INTEGER PRIMARY KEY AUTOINCREMENT,
+pool_id INTEGER NOT NULL,
+hv_msg_id INTEGER NOT NULL,
+position INTEGER NOT NULL,
+UNIQUE (pool_id, hv_msg_id));
+
+CREATE TABLE IF NOT EXISTS hv_pool_cursor (
+id INTEGER PRIMARY KEY AUTOINCREMENT,
+pool_id INTEGER NOT NULL,
+group_id VARCHAR(32) NOT NULL,
+hv_msg_id INTEGER NOT NULL,
+position INTEGER NOT NULL,
+created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP);
+
+CREATE TABLE IF NOT EXISTS hv_drip_step (
+id INTEGER PRIMARY KEY AUTOINCREMENT,
+hv_group_id INTEGER NOT NULL,
+delay_minutes INTEGER NOT NULL,
+hv_msg_id INTEGER NOT NULL,
+created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP);
+
+CREATE TABLE IF NOT EXISTS hv_drip_pending (
+id INTEGER PRIMARY KEY AUTOINCREMENT,
+step_id INTEGER NOT NULL,
+group_id VARCHAR(32) NOT NULL,
+user_id INTEGER NOT NULL,
+first_name VARCHAR(64) NOT NULL DEFAULT '',
+username VARCHAR(32),
+send_at TIMESTAMP NOT NULL,
+created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP);
+
+CREATE TABLE IF NOT EXISTS hv_welcome_held (
+id INTEGER PRIMARY KEY AUTOINCREMENT,
+group_id VARCHAR(32) NOT NULL,
+user_id INTEGER NOT NULL,
+first_name VARCHAR(64) NOT NULL DEFAULT '',
+username VARCHAR(32),
+send_at TIMESTAMP NOT NULL,
 created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP);
 ",
     )
     .execute(conn)
     .await
     .unwrap();
+
+    // Columns added after the first release, the old database has no them.
+    add_column(conn, "hv_group", "time_zone", "VARCHAR(64) NOT NULL DEFAULT ''").await;
+    add_column(conn, "hv_polling_msg", "start_date", "DATE").await;
+    add_column(conn, "hv_polling_msg", "end_date", "DATE").await;
+    add_column(conn, "hv_group", "polling_resumed_at", "TIMESTAMP").await;
+    add_column(conn, "hv_msg", "hv_group_id", "INTEGER NOT NULL DEFAULT 0").await;
+    add_column(conn, "hv_msg", "media_kind", "VARCHAR(16) NOT NULL DEFAULT 'text'").await;
+    add_column(conn, "hv_msg", "file_id", "TEXT NOT NULL DEFAULT ''").await;
+    add_column(conn, "hv_msg", "buttons", "TEXT NOT NULL DEFAULT ''").await;
+    add_column(conn, "hv_polling_msg", "tag", "VARCHAR(32) NOT NULL DEFAULT ''").await;
+    add_column(conn, "hv_user", "notify_failures", "BOOLEAN NOT NULL DEFAULT TRUE").await;
+    add_column(conn, "hv_polling_msg", "disabled", "BOOLEAN NOT NULL DEFAULT FALSE").await;
+    add_column(conn, "hv_polling_msg", "delete_previous", "BOOLEAN NOT NULL DEFAULT FALSE").await;
+    add_column(conn, "hv_polling_msg", "delete_after", "INTEGER").await;
+    add_column(conn, "hv_polling_msg", "pin_mode", "VARCHAR(16) NOT NULL DEFAULT 'off'").await;
+    add_column(conn, "hv_polling_msg", "unpin_previous", "BOOLEAN NOT NULL DEFAULT FALSE").await;
+    add_column(conn, "hv_polling_msg", "unpin_after", "INTEGER").await;
+    add_column(conn, "hv_polling_msg", "pool_id", "INTEGER NOT NULL DEFAULT 0").await;
+    add_column(conn, "hv_group", "quiet_hours", "VARCHAR(32) NOT NULL DEFAULT ''").await;
+
+    migrate_data(conn).await;
     true
 }
+
+/// One-time data fixes, tracked by `PRAGMA user_version`.
+async fn migrate_data(conn: &SqlitePool) {
+    let version: i32 = sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(conn)
+        .await
+        .unwrap();
+
+    // v1: groups were added muted, but nothing read the flags, so they all behaved unmuted.
+    if version < 1 {
+        sqlx::query("UPDATE hv_group SET mute_polling = FALSE, mute_welcome = FALSE; PRAGMA user_version = 1;")
+            .execute(conn)
+            .await
+            .unwrap();
+    }
+}
+
+/// Add the column when the table does not have it yet.
+async fn add_column(conn: &SqlitePool, table: &str, column: &str, definition: &str) {
+    let exists: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?")
+        .bind(table)
+        .bind(column)
+        .fetch_one(conn)
+        .await
+        .unwrap();
+    if exists > 0 {
+        return;
+    }
+
+    sqlx::query(&format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"))
+        .execute(conn)
+        .await
+        .unwrap();
+}
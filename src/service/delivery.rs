@@ -0,0 +1,398 @@
+//! # Delivery
+//! The ledger of the group push deliveries, one row per (push, group, scheduled instant).
+//! A tag push has one row per group of the tag.
+//!
+//! The scheduler claims the slot before sending, a claimed slot is never sent again,
+//! so the catch-up after downtime and a double tick can not deliver twice.
+//! The result of the attempt (Telegram message id or error text) stays as the audit trail.
+//!
+//! The sent message id is also what the auto-delete of the push removes later,
+//! `deleted_at` is set once it is gone so a restart does not try again.
+//! Likewise `pinned` tracks the messages the push pinned, until they are unpinned.
+//!
+//! A slot within the quiet hours of the group is `skipped`, or stays pending with `defer_until`
+//! set to the end of the quiet hours, see [`crate::service::quiet`].
+use crate::service::Db;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::Row;
+
+pub struct DeliveryDb {
+    conn: Db,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, sqlx::Type)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Sent,
+    Failed,
+    Skipped, // quiet hours
+}
+
+#[derive(Debug)]
+pub struct Delivery {
+    pub id: i64,
+    pub polling_msg_id: i64,
+    pub group_id: String, // Telegram chat id
+    pub scheduled_at: DateTime<Utc>,
+    pub status: DeliveryStatus,
+    pub message_id: Option<i32>, // Telegram message id
+    pub error: String, // Telegram error text of the failed attempt
+    pub attempted_at: Option<DateTime<Utc>>, // the time of the send, `None` while pending
+    pub delete_at: Option<DateTime<Utc>>, // auto-delete of the sent message
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub pinned: bool, // pinned by the push, not unpinned yet
+    pub unpin_at: Option<DateTime<Utc>>,
+    pub defer_until: Option<DateTime<Utc>>, // deferred by the quiet hours, pending until then
+    pub msg_title: Option<String>, // 从 hv_msg 表 (或消息池) 关联获取, 推送删除后为空
+    pub created_at: DateTime<Utc>,
+}
+
+impl DeliveryStatus {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "⏳",
+            DeliveryStatus::Sent => "✅",
+            DeliveryStatus::Failed => "❌",
+            DeliveryStatus::Skipped => "🌙",
+        }
+    }
+}
+
+pub fn new(conn: Db) -> DeliveryDb {
+    DeliveryDb { conn }
+}
+
+impl DeliveryDb {
+    /// Claim the slot, only the first caller gets `true` and may send it.
+    pub async fn claim(
+        &self,
+        polling_msg_id: i64,
+        group_id: &str,
+        scheduled_at: &DateTime<Utc>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO hv_push_delivery (polling_msg_id, group_id, scheduled_at, status) VALUES (?, ?, ?, ?)",
+        )
+        .bind(polling_msg_id)
+        .bind(group_id)
+        .bind(scheduled_at)
+        .bind(DeliveryStatus::Pending)
+        .execute(&self.conn.sqlite_pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn mark_sent(
+        &self,
+        polling_msg_id: i64,
+        group_id: &str,
+        scheduled_at: &DateTime<Utc>,
+        message_id: i32,
+    ) -> Result<bool> {
+        self.set_status(polling_msg_id, group_id, scheduled_at, DeliveryStatus::Sent, Some(message_id), "")
+            .await
+    }
+
+    /// The send failed, `error` is the Telegram error text.
+    pub async fn mark_failed(
+        &self,
+        polling_msg_id: i64,
+        group_id: &str,
+        scheduled_at: &DateTime<Utc>,
+        error: &str,
+    ) -> Result<bool> {
+        self.set_status(polling_msg_id, group_id, scheduled_at, DeliveryStatus::Failed, None, error)
+            .await
+    }
+
+    /// Not sent, within the quiet hours of the group.
+    pub async fn mark_skipped(
+        &self,
+        polling_msg_id: i64,
+        group_id: &str,
+        scheduled_at: &DateTime<Utc>,
+        reason: &str,
+    ) -> Result<bool> {
+        self.set_status(polling_msg_id, group_id, scheduled_at, DeliveryStatus::Skipped, None, reason)
+            .await
+    }
+
+    /// Send the claimed slot at `until`, the end of the quiet hours.
+    pub async fn defer(
+        &self,
+        polling_msg_id: i64,
+        group_id: &str,
+        scheduled_at: &DateTime<Utc>,
+        until: &DateTime<Utc>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE hv_push_delivery SET defer_until = ? WHERE polling_msg_id = ? AND group_id = ? AND scheduled_at = ?",
+        )
+        .bind(until)
+        .bind(polling_msg_id)
+        .bind(group_id)
+        .bind(scheduled_at)
+        .execute(&self.conn.sqlite_pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The deferred slots due at `now`, oldest first.
+    pub async fn due_deferred(&self, now: &DateTime<Utc>) -> Result<Vec<Delivery>> {
+        let deliveries = sqlx::query(&format!(
+            "{DELIVERY_SELECT} WHERE d.defer_until <= ? AND d.status = ? ORDER BY d.scheduled_at, d.id"
+        ))
+        .bind(now)
+        .bind(DeliveryStatus::Pending)
+        .map(delivery_from_row)
+        .fetch_all(&self.conn.sqlite_pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    /// Take the deferred slot to send it, only the first caller gets `true`.
+    pub async fn take_deferred(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query("UPDATE hv_push_delivery SET defer_until = NULL WHERE id = ? AND defer_until IS NOT NULL")
+            .bind(id)
+            .execute(&self.conn.sqlite_pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Has every group its final delivery of the push (sent, failed or skipped)?
+    /// A one-shot push is done then, a pending or deferred slot still needs it.
+    pub async fn is_settled(&self, polling_msg_id: i64, group_ids: &[String]) -> Result<bool> {
+        let settled: Vec<String> = sqlx::query_scalar(
+            "SELECT DISTINCT group_id FROM hv_push_delivery WHERE polling_msg_id = ? AND status <> ? AND defer_until IS NULL",
+        )
+        .bind(polling_msg_id)
+        .bind(DeliveryStatus::Pending)
+        .fetch_all(&self.conn.sqlite_pool)
+        .await?;
+
+        Ok(group_ids.iter().all(|group_id| settled.contains(group_id)))
+    }
+
+    async fn set_status(
+        &self,
+        polling_msg_id: i64,
+        group_id: &str,
+        scheduled_at: &DateTime<Utc>,
+        status: DeliveryStatus,
+        message_id: Option<i32>,
+        error: &str,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE hv_push_delivery SET status = ?, message_id = ?, error = ?, attempted_at = ? WHERE polling_msg_id = ? AND group_id = ? AND scheduled_at = ?",
+        )
+        .bind(status)
+        .bind(message_id)
+        .bind(error)
+        .bind(Utc::now())
+        .bind(polling_msg_id)
+        .bind(group_id)
+        .bind(scheduled_at)
+        .execute(&self.conn.sqlite_pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete the sent message of the slot at `delete_at`.
+    pub async fn schedule_delete(
+        &self,
+        polling_msg_id: i64,
+        group_id: &str,
+        scheduled_at: &DateTime<Utc>,
+        delete_at: &DateTime<Utc>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE hv_push_delivery SET delete_at = ? WHERE polling_msg_id = ? AND group_id = ? AND scheduled_at = ?",
+        )
+        .bind(delete_at)
+        .bind(polling_msg_id)
+        .bind(group_id)
+        .bind(scheduled_at)
+        .execute(&self.conn.sqlite_pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The sent messages of the push in the group before the slot, not deleted yet.
+    pub async fn previous_sent(
+        &self,
+        polling_msg_id: i64,
+        group_id: &str,
+        before: &DateTime<Utc>,
+    ) -> Result<Vec<Delivery>> {
+        let deliveries = sqlx::query(&format!(
+            "{DELIVERY_SELECT} WHERE d.polling_msg_id = ? AND d.group_id = ? AND d.scheduled_at < ? AND d.status = ? AND d.message_id IS NOT NULL AND d.deleted_at IS NULL"
+        ))
+        .bind(polling_msg_id)
+        .bind(group_id)
+        .bind(before)
+        .bind(DeliveryStatus::Sent)
+        .map(delivery_from_row)
+        .fetch_all(&self.conn.sqlite_pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    /// The sent messages due to be deleted at `now`.
+    pub async fn due_deletes(&self, now: &DateTime<Utc>) -> Result<Vec<Delivery>> {
+        let deliveries = sqlx::query(&format!(
+            "{DELIVERY_SELECT} WHERE d.delete_at <= ? AND d.status = ? AND d.message_id IS NOT NULL AND d.deleted_at IS NULL"
+        ))
+        .bind(now)
+        .bind(DeliveryStatus::Sent)
+        .map(delivery_from_row)
+        .fetch_all(&self.conn.sqlite_pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    /// The sent message is deleted (or can not be deleted any more), a deleted message is not pinned.
+    pub async fn mark_deleted(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query("UPDATE hv_push_delivery SET deleted_at = ?, pinned = FALSE WHERE id = ?")
+            .bind(Utc::now())
+            .bind(id)
+            .execute(&self.conn.sqlite_pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The sent message of the slot is pinned, to unpin at `unpin_at` if any.
+    pub async fn mark_pinned(
+        &self,
+        polling_msg_id: i64,
+        group_id: &str,
+        scheduled_at: &DateTime<Utc>,
+        unpin_at: Option<&DateTime<Utc>>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE hv_push_delivery SET pinned = TRUE, unpin_at = ? WHERE polling_msg_id = ? AND group_id = ? AND scheduled_at = ?",
+        )
+        .bind(unpin_at)
+        .bind(polling_msg_id)
+        .bind(group_id)
+        .bind(scheduled_at)
+        .execute(&self.conn.sqlite_pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The pinned messages of the push in the group before the slot.
+    pub async fn previous_pinned(
+        &self,
+        polling_msg_id: i64,
+        group_id: &str,
+        before: &DateTime<Utc>,
+    ) -> Result<Vec<Delivery>> {
+        let deliveries = sqlx::query(&format!(
+            "{DELIVERY_SELECT} WHERE d.polling_msg_id = ? AND d.group_id = ? AND d.scheduled_at < ? AND d.pinned = TRUE"
+        ))
+        .bind(polling_msg_id)
+        .bind(group_id)
+        .bind(before)
+        .map(delivery_from_row)
+        .fetch_all(&self.conn.sqlite_pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    /// The pinned messages due to be unpinned at `now`.
+    pub async fn due_unpins(&self, now: &DateTime<Utc>) -> Result<Vec<Delivery>> {
+        let deliveries = sqlx::query(&format!(
+            "{DELIVERY_SELECT} WHERE d.unpin_at <= ? AND d.pinned = TRUE"
+        ))
+        .bind(now)
+        .map(delivery_from_row)
+        .fetch_all(&self.conn.sqlite_pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    /// The pinned message is unpinned (or can not be unpinned any more).
+    pub async fn mark_unpinned(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query("UPDATE hv_push_delivery SET pinned = FALSE WHERE id = ?")
+            .bind(id)
+            .execute(&self.conn.sqlite_pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get(
+        &self,
+        polling_msg_id: i64,
+        group_id: &str,
+        scheduled_at: &DateTime<Utc>,
+    ) -> Result<Option<Delivery>> {
+        let delivery = sqlx::query(&format!(
+            "{DELIVERY_SELECT} WHERE d.polling_msg_id = ? AND d.group_id = ? AND d.scheduled_at = ?"
+        ))
+        .bind(polling_msg_id)
+        .bind(group_id)
+        .bind(scheduled_at)
+        .map(delivery_from_row)
+        .fetch_optional(&self.conn.sqlite_pool)
+        .await?;
+
+        Ok(delivery)
+    }
+
+    /// The last deliveries of the group (Telegram chat id), newest first.
+    pub async fn recent_by_group(&self, group_id: &str, limit: i64) -> Result<Vec<Delivery>> {
+        let deliveries = sqlx::query(&format!(
+            "{DELIVERY_SELECT} WHERE d.group_id = ? ORDER BY d.scheduled_at DESC, d.id DESC LIMIT ?"
+        ))
+        .bind(group_id)
+        .bind(limit)
+        .map(delivery_from_row)
+        .fetch_all(&self.conn.sqlite_pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+}
+
+const DELIVERY_SELECT: &str = r#"
+    SELECT d.*, COALESCE(m.msg_title, p.pool_name) AS msg_title
+    FROM hv_push_delivery d
+    LEFT JOIN hv_polling_msg pm ON d.polling_msg_id = pm.id
+    LEFT JOIN hv_msg m ON pm.hv_msg_id = m.id
+    LEFT JOIN hv_msg_pool p ON pm.pool_id = p.id
+"#;
+
+fn delivery_from_row(row: sqlx::sqlite::SqliteRow) -> Delivery {
+    Delivery {
+        id: row.get("id"),
+        polling_msg_id: row.get("polling_msg_id"),
+        group_id: row.get("group_id"),
+        scheduled_at: row.get("scheduled_at"),
+        status: row.get("status"),
+        message_id: row.get("message_id"),
+        error: row.get("error"),
+        attempted_at: row.get("attempted_at"),
+        delete_at: row.get("delete_at"),
+        deleted_at: row.get("deleted_at"),
+        pinned: row.get("pinned"),
+        unpin_at: row.get("unpin_at"),
+        defer_until: row.get("defer_until"),
+        msg_title: row.get("msg_title"),
+        created_at: row.get("created_at"),
+    }
+}
@@ -0,0 +1,222 @@
+//! # Drip sequence
+//! The onboarding of the new members: the steps of the group (delay, message) after the welcome,
+//! e.g. the rules after 1 hour and the FAQ after 1 day.
+//!
+//! The join enqueues one pending row per step with its send time, so a restart does not lose them.
+//! The poll task claims the due rows by deleting them, leaving the group cancels the rest.
+use crate::service::polling_msg::{minutes_str, parse_minutes};
+use crate::service::Db;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use sqlx::Row;
+
+/// The longest delay of a step, 30 days
+pub const DRIP_DELAY_MAX_MINUTES: i64 = 30 * 24 * 60;
+
+pub struct DripDb {
+    conn: Db,
+}
+
+#[derive(Debug)]
+pub struct DripStep {
+    pub id: i64,
+    pub hv_group_id: i64,
+    pub delay_minutes: i64, // after the join
+    pub hv_msg_id: i64,
+    pub msg_title: String, // 从 hv_msg 表关联获取
+}
+
+/// The step due to the member
+#[derive(Debug)]
+pub struct DripDue {
+    pub id: i64,
+    pub group_id: String, // Telegram chat id
+    pub user_id: i64,
+    pub first_name: String,
+    pub username: Option<String>,
+    pub send_at: DateTime<Utc>,
+    pub hv_msg_id: i64,
+    pub group_name: String, // 从 hv_group 表关联获取
+    pub time_zone: String,
+    pub quiet_hours: String,
+    pub mute_welcome: bool,
+}
+
+/// The new member the steps go to
+pub struct Member<'a> {
+    pub user_id: i64,
+    pub first_name: &'a str,
+    pub username: Option<&'a str>,
+}
+
+/// The delay of the step: `now` (or `0`), `30m`, `1h`, `1d`
+pub fn parse_delay(text: &str) -> Result<i64> {
+    let delay = match text.trim().to_lowercase().as_str() {
+        "" => bail!("Empty delay"),
+        "now" | "0" => 0,
+        word => parse_minutes(word)?,
+    };
+    if !(0..=DRIP_DELAY_MAX_MINUTES).contains(&delay) {
+        bail!("The delay must be up to {} days", DRIP_DELAY_MAX_MINUTES / (24 * 60));
+    }
+    Ok(delay)
+}
+
+pub fn delay_str(delay_minutes: i64) -> String {
+    match delay_minutes {
+        0 => "now".to_string(),
+        minutes => format!("after {}", minutes_str(minutes)),
+    }
+}
+
+pub fn new(conn: Db) -> DripDb {
+    DripDb { conn }
+}
+
+impl DripDb {
+    /// The steps of the group, in order of the delay
+    pub async fn steps(&self, group_db_id: i64) -> Result<Vec<DripStep>> {
+        let steps = sqlx::query(
+            "SELECT s.id, s.hv_group_id, s.delay_minutes, s.hv_msg_id, m.msg_title FROM hv_drip_step s JOIN hv_msg m ON s.hv_msg_id = m.id WHERE s.hv_group_id = ? ORDER BY s.delay_minutes, s.id",
+        )
+        .bind(group_db_id)
+        .map(|row: sqlx::sqlite::SqliteRow| DripStep {
+            id: row.get("id"),
+            hv_group_id: row.get("hv_group_id"),
+            delay_minutes: row.get("delay_minutes"),
+            hv_msg_id: row.get("hv_msg_id"),
+            msg_title: row.get("msg_title"),
+        })
+        .fetch_all(&self.conn.sqlite_pool)
+        .await?;
+        Ok(steps)
+    }
+
+    /// Add the step to the group, return id. The members joined before do not get it.
+    pub async fn add_step(&self, group_db_id: i64, delay_minutes: i64, msg_id: i64) -> Result<i64> {
+        if !(0..=DRIP_DELAY_MAX_MINUTES).contains(&delay_minutes) {
+            bail!("The delay must be up to {} days", DRIP_DELAY_MAX_MINUTES / (24 * 60));
+        }
+        let result = sqlx::query("INSERT INTO hv_drip_step (hv_group_id, delay_minutes, hv_msg_id) VALUES (?, ?, ?)")
+            .bind(group_db_id)
+            .bind(delay_minutes)
+            .bind(msg_id)
+            .execute(&self.conn.sqlite_pool)
+            .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Delete the step, the members waiting for it do not get it.
+    pub async fn delete_step(&self, id: i64) -> Result<bool> {
+        let mut tx = self.conn.sqlite_pool.begin().await?;
+        sqlx::query("DELETE FROM hv_drip_pending WHERE step_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        let result = sqlx::query("DELETE FROM hv_drip_step WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The member joined: queue the steps of the group from the join time, return how many.
+    /// A member joining again starts over.
+    pub async fn enqueue(
+        &self,
+        group_db_id: i64,
+        group_id: &str,
+        member: &Member<'_>,
+        joined_at: &DateTime<Utc>,
+    ) -> Result<usize> {
+        let steps = self.steps(group_db_id).await?;
+        let mut tx = self.conn.sqlite_pool.begin().await?;
+        sqlx::query("DELETE FROM hv_drip_pending WHERE group_id = ? AND user_id = ?")
+            .bind(group_id)
+            .bind(member.user_id)
+            .execute(&mut *tx)
+            .await?;
+        for step in &steps {
+            let send_at = *joined_at + chrono::Duration::minutes(step.delay_minutes);
+            sqlx::query(
+                "INSERT INTO hv_drip_pending (step_id, group_id, user_id, first_name, username, send_at) VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(step.id)
+            .bind(group_id)
+            .bind(member.user_id)
+            .bind(member.first_name)
+            .bind(member.username)
+            .bind(send_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(steps.len())
+    }
+
+    /// The steps due at `now`, also the ones missed while the bot was down.
+    pub async fn due(&self, now: &DateTime<Utc>) -> Result<Vec<DripDue>> {
+        let due = sqlx::query(
+            "SELECT p.id, p.group_id, p.user_id, p.first_name, p.username, p.send_at, s.hv_msg_id, g.group_name, g.time_zone, g.quiet_hours, g.mute_welcome
+            FROM hv_drip_pending p
+            JOIN hv_drip_step s ON p.step_id = s.id
+            JOIN hv_group g ON s.hv_group_id = g.id
+            WHERE p.send_at <= ? ORDER BY p.send_at, p.id",
+        )
+        .bind(now)
+        .map(|row: sqlx::sqlite::SqliteRow| DripDue {
+            id: row.get("id"),
+            group_id: row.get("group_id"),
+            user_id: row.get("user_id"),
+            first_name: row.get("first_name"),
+            username: row.get("username"),
+            send_at: row.get("send_at"),
+            hv_msg_id: row.get("hv_msg_id"),
+            group_name: row.get("group_name"),
+            time_zone: row.get("time_zone"),
+            quiet_hours: row.get("quiet_hours"),
+            mute_welcome: row.get("mute_welcome"),
+        })
+        .fetch_all(&self.conn.sqlite_pool)
+        .await?;
+        Ok(due)
+    }
+
+    /// Claim the due step, only the first caller gets `true` and may send it.
+    pub async fn claim(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM hv_drip_pending WHERE id = ?")
+            .bind(id)
+            .execute(&self.conn.sqlite_pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The member left: drop the steps not sent yet, return how many.
+    pub async fn cancel(&self, group_id: &str, user_id: i64) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM hv_drip_pending WHERE group_id = ? AND user_id = ?")
+            .bind(group_id)
+            .bind(user_id)
+            .execute(&self.conn.sqlite_pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// How many drip steps send the msg, deleting the msg drops them and their queued sends
+    pub async fn count_by_msg_id(&self, msg_id: i64) -> Result<i64> {
+        let count = sqlx::query_scalar("SELECT COUNT(*) FROM hv_drip_step WHERE hv_msg_id = ?")
+            .bind(msg_id)
+            .fetch_one(&self.conn.sqlite_pool)
+            .await?;
+        Ok(count)
+    }
+
+    /// How many steps wait to be sent in the group
+    pub async fn pending_count(&self, group_id: &str) -> Result<i64> {
+        let count = sqlx::query_scalar("SELECT COUNT(*) FROM hv_drip_pending WHERE group_id = ?")
+            .bind(group_id)
+            .fetch_one(&self.conn.sqlite_pool)
+            .await?;
+        Ok(count)
+    }
+}
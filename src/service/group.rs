@@ -1,7 +1,10 @@
+use crate::service::msg::MsgType;
+use crate::service::quiet::QuietHours;
 use crate::service::Db;
 use chrono::Utc;
+use chrono_tz::Tz;
 use sqlx::Row;
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 
 pub struct Group {
     conn: Db,
@@ -14,13 +17,61 @@ pub struct GroupInfo {
     pub group_name: String,
     pub mute_polling: bool,
     pub mute_welcome: bool,
+    pub time_zone: String, // IANA 时区, 空为服务器时区
+    pub polling_resumed_at: Option<chrono::DateTime<Utc>>, // 恢复推送的时间, 暂停期间的推送不补发
+    pub quiet_hours: String, // 免打扰时段, 空为关闭, 见 [`QuietHours`]
     pub created_at: chrono::DateTime<Utc>,
 }
 
+impl GroupInfo {
+    /// Time zone of the group, `None` is the server zone.
+    pub fn tz(&self) -> Option<Tz> {
+        parse_time_zone(&self.time_zone).unwrap_or(None)
+    }
+
+    /// The quiet hours of the group, `None` when off.
+    pub fn quiet(&self) -> Option<QuietHours> {
+        QuietHours::parse(&self.quiet_hours).unwrap_or(None)
+    }
+}
+
 pub fn new(conn: Db) -> Group {
     Group { conn }
 }
 
+/// Parse the IANA time zone name of the group, empty is the server local zone (`None`).
+pub fn parse_time_zone(name: &str) -> Result<Option<Tz>> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Ok(None);
+    }
+    name.parse::<Tz>()
+        .map(Some)
+        .map_err(|_| anyhow!("Unknown time zone: {name}"))
+}
+
+/// Longest tag, in bytes (the tag is in the callback data, at most 64 bytes)
+pub const TAG_MAX_LEN: usize = 32;
+
+/// Parse the tags separated by spaces or commas, e.g. `vip, #cn en`.
+/// Tags are lowercase letters, digits and `-`, the leading `#` is dropped.
+pub fn parse_tags(input: &str) -> Result<Vec<String>> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input.split([' ', ',', '\n']).map(str::trim).filter(|tag| !tag.is_empty()) {
+        let tag = tag.trim_start_matches('#').to_lowercase();
+        if tag.is_empty()
+            || tag.len() > TAG_MAX_LEN
+            || !tag.chars().all(|c| c.is_alphanumeric() || c == '-')
+        {
+            bail!("Invalid tag: {tag}, use letters, digits and '-', at most {TAG_MAX_LEN} bytes");
+        }
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    Ok(tags)
+}
+
 impl Group {
     pub async fn all(&self) -> Vec<GroupInfo> {
         sqlx::query("SELECT * FROM hv_group")
@@ -30,6 +81,9 @@ impl Group {
                 group_name: row.get("group_name"),
                 mute_polling: row.get("mute_polling"),
                 mute_welcome: row.get("mute_welcome"),
+                time_zone: row.get("time_zone"),
+                polling_resumed_at: row.get("polling_resumed_at"),
+                quiet_hours: row.get("quiet_hours"),
                 created_at: row.get("created_at"),
             })
             .fetch_all(&self.conn.sqlite_pool)
@@ -45,6 +99,15 @@ impl Group {
             .unwrap()
     }
 
+    /// Get the group by the Telegram chat id
+    pub async fn get_by_group_id(&self, group_id: &str) -> Option<GroupInfo> {
+        sqlx::query_as::<_, GroupInfo>("SELECT * FROM hv_group WHERE group_id = ?")
+            .bind(group_id)
+            .fetch_optional(&self.conn.sqlite_pool)
+            .await
+            .unwrap()
+    }
+
     pub async fn add_group(&self, group_id: &str, group_name: &str) -> Result<i64> {
         // 先查询
         let existing = sqlx::query("SELECT id FROM hv_group WHERE group_id = ?")
@@ -62,8 +125,8 @@ impl Group {
             ")
                     .bind(group_id)
                     .bind(group_name)
-                    .bind(true)
-                    .bind(true)
+                    .bind(false)
+                    .bind(false)
                     .execute(&self.conn.sqlite_pool)
                     .await?;
 
@@ -72,13 +135,18 @@ impl Group {
         }
     }
 
+    /// Pause (mute) or resume the scheduled pushes of the group.
+    /// The slots missed while paused are not caught up after resuming.
     pub async fn set_mute_polling(&self, group_id: &str, mute: bool) -> Result<()> {
         sqlx::query("
         UPDATE hv_group 
-        SET mute_polling = ? 
+        SET mute_polling = ?,
+            polling_resumed_at = CASE WHEN ? THEN polling_resumed_at ELSE ? END
         WHERE group_id = ?
     ")
             .bind(mute)
+            .bind(mute)
+            .bind(Utc::now())
             .bind(group_id)
             .execute(&self.conn.sqlite_pool)
             .await?;
@@ -101,6 +169,37 @@ impl Group {
     }
 
 
+    /// Set the time zone of the group (by the database id), empty resets to the server zone.
+    pub async fn set_time_zone(&self, id: i64, time_zone: &str) -> Result<bool> {
+        let time_zone = match parse_time_zone(time_zone)? {
+            Some(tz) => tz.name().to_string(),
+            None => String::new(),
+        };
+        let result = sqlx::query("UPDATE hv_group SET time_zone = ? WHERE id = ?")
+            .bind(time_zone)
+            .bind(id)
+            .execute(&self.conn.sqlite_pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Set the quiet hours, see [`QuietHours::parse`], `off` turns them off.
+    pub async fn set_quiet_hours(&self, id: i64, input: &str) -> Result<bool> {
+        let quiet_hours = match QuietHours::parse(input)? {
+            Some(quiet) => quiet.to_string(),
+            None => String::new(),
+        };
+        let result = sqlx::query("UPDATE hv_group SET quiet_hours = ? WHERE id = ?")
+            .bind(quiet_hours)
+            .bind(id)
+            .execute(&self.conn.sqlite_pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Is the scheduled push on (not muted)? Unknown group is `false`.
     pub async fn should_do_polling(&self, group_id: &str) -> Result<bool> {
         let result: Option<(bool,)> = sqlx::query_as(
             "SELECT mute_polling FROM hv_group WHERE group_id = ?"
@@ -110,11 +209,12 @@ impl Group {
             .await?;
 
         match result {
-            Some((mute_polling,)) => Ok(mute_polling),
+            Some((mute_polling,)) => Ok(!mute_polling),
             None => Ok(false)
         }
     }
 
+    /// Is the welcome message on (not muted)? Unknown group is `false`.
     pub async fn should_do_welcome(&self, group_id: &str) -> Result<bool> {
         let result: Option<(bool,)> = sqlx::query_as(
             "SELECT mute_welcome FROM hv_group WHERE group_id = ?"
@@ -124,20 +224,94 @@ impl Group {
             .await?;
 
         match result {
-            Some((mute_welcome,)) => Ok(mute_welcome),
+            Some((mute_welcome,)) => Ok(!mute_welcome),
             None => Ok(false)
         }
     }
 
     pub async fn delete_group(&self, group_id: &str) -> Result<bool> {
+        let mut tx = self.conn.sqlite_pool.begin().await?;
+        // Everything of the group goes with it: the tags, its own pushes (the tag pushes stay for
+        // the other groups), its welcome message and its history, the drip sequence, the held welcomes,
+        // the pool cursors.
+        // The delivery ledger stays as the audit trail.
+        for query in [
+            "DELETE FROM hv_group_tag WHERE hv_group_id IN (SELECT id FROM hv_group WHERE group_id = ?)",
+            "DELETE FROM hv_polling_msg WHERE tag = '' AND group_id IN (SELECT id FROM hv_group WHERE group_id = ?)",
+            "DELETE FROM hv_drip_step WHERE hv_group_id IN (SELECT id FROM hv_group WHERE group_id = ?)",
+            "DELETE FROM hv_drip_pending WHERE group_id = ?",
+            "DELETE FROM hv_welcome_held WHERE group_id = ?",
+            "DELETE FROM hv_pool_cursor WHERE group_id = ?",
+        ] {
+            sqlx::query(query).bind(group_id).execute(&mut *tx).await?;
+        }
+        for query in [
+            "DELETE FROM hv_msg_revision WHERE hv_msg_id IN (SELECT id FROM hv_msg WHERE msg_type = ? AND hv_group_id IN (SELECT id FROM hv_group WHERE group_id = ?))",
+            "DELETE FROM hv_msg WHERE msg_type = ? AND hv_group_id IN (SELECT id FROM hv_group WHERE group_id = ?)",
+        ] {
+            sqlx::query(query)
+                .bind(MsgType::Welcome as i32)
+                .bind(group_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
         let result = sqlx::query(
             "DELETE FROM hv_group WHERE group_id = ?"
         )
             .bind(group_id)
-            .execute(&self.conn.sqlite_pool)
+            .execute(&mut *tx)
             .await?;
+        tx.commit().await?;
 
-      
         Ok(result.rows_affected() > 0)
     }
+
+    /// Tags of the group (by the database id), in order of the name
+    pub async fn tags(&self, id: i64) -> Result<Vec<String>> {
+        let tags = sqlx::query_scalar("SELECT tag FROM hv_group_tag WHERE hv_group_id = ? ORDER BY tag")
+            .bind(id)
+            .fetch_all(&self.conn.sqlite_pool)
+            .await?;
+        Ok(tags)
+    }
+
+    /// Replace the tags of the group (by the database id), empty removes all.
+    pub async fn set_tags(&self, id: i64, tags: &[String]) -> Result<()> {
+        let mut tx = self.conn.sqlite_pool.begin().await?;
+        sqlx::query("DELETE FROM hv_group_tag WHERE hv_group_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        for tag in tags {
+            sqlx::query("INSERT OR IGNORE INTO hv_group_tag (hv_group_id, tag) VALUES (?, ?)")
+                .bind(id)
+                .bind(tag)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// All the tags in use and their group count
+    pub async fn all_tags(&self) -> Result<Vec<(String, i64)>> {
+        let tags = sqlx::query_as(
+            "SELECT t.tag, COUNT(*) FROM hv_group_tag t JOIN hv_group g ON t.hv_group_id = g.id GROUP BY t.tag ORDER BY t.tag",
+        )
+        .fetch_all(&self.conn.sqlite_pool)
+        .await?;
+        Ok(tags)
+    }
+
+    /// The groups having the tag
+    pub async fn groups_by_tag(&self, tag: &str) -> Result<Vec<GroupInfo>> {
+        let groups = sqlx::query_as::<_, GroupInfo>(
+            "SELECT g.* FROM hv_group g JOIN hv_group_tag t ON t.hv_group_id = g.id WHERE t.tag = ? ORDER BY g.id",
+        )
+        .bind(tag)
+        .fetch_all(&self.conn.sqlite_pool)
+        .await?;
+        Ok(groups)
+    }
 }
@@ -1,5 +1,12 @@
+pub mod buttons;
+pub mod entities;
+pub mod markup;
+pub mod media;
+pub mod template;
+
 use crate::service::{polling_msg, Db};
 use chrono::Utc;
+use serde::{Deserialize, Serialize};
 use sqlx::Row;
 use std::cmp::PartialEq;
 use std::fmt::Debug;
@@ -9,6 +16,12 @@ pub struct Msg {
     conn: Db,
 }
 
+/// `hv_msg.hv_group_id` of the global welcome message
+pub const GLOBAL_WELCOME: i64 = 0;
+
+/// Used when no welcome message is set
+const DEFAULT_WELCOME: &str = "Welcome {mention} to {group_name}! Nice to meet you";
+
 pub fn new(conn: Db) -> Msg {
     Msg { conn }
 }
@@ -17,11 +30,81 @@ pub fn new(conn: Db) -> Msg {
 pub struct Message {
     pub id: i64, // 或 i32，取决于数据库字段类型
     pub msg_type: MsgType,
-    pub msg_text: String,
+    pub msg_text: String, // 文本, 媒体消息为 caption
     pub msg_title: String,
+    pub media_kind: MediaKind,
+    pub file_id: String, // Telegram file_id, 文本消息为空
+    pub buttons: String, // 按钮定义, see `buttons`
     pub created_at: chrono::DateTime<Utc>, // 或其他时间类型
 }
 
+impl Message {
+    pub fn content(&self) -> MsgContent {
+        MsgContent {
+            media_kind: self.media_kind,
+            file_id: self.file_id.clone(),
+            text: self.msg_text.clone(),
+            album: Vec::new(),
+            buttons: self.buttons.clone(),
+        }
+    }
+}
+
+/// Kind of the message content, media are sent by the Telegram `file_id`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+pub enum MediaKind {
+    #[default]
+    Text,
+    Photo,
+    Video,
+    Document,
+    Animation,
+    Album, // media group, the items are in `hv_msg_album`
+}
+
+/// The content of the message, text or media with the caption.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MsgContent {
+    pub media_kind: MediaKind,
+    pub file_id: String,
+    pub text: String, // 文本, 媒体消息为 caption
+    #[serde(default)]
+    pub album: Vec<MsgContent>, // album items (photo, video, document), in order
+    #[serde(default)]
+    pub buttons: String, // 按钮定义, see `buttons`
+}
+
+impl MsgContent {
+    pub fn text(text: &str) -> MsgContent {
+        MsgContent {
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Does the text, or any caption of the album, use the placeholder?
+    pub fn uses(&self, placeholder: &str) -> bool {
+        template::uses(&self.text, placeholder)
+            || self.album.iter().any(|item| template::uses(&item.text, placeholder))
+    }
+}
+
+/// The content of the message before an edit
+#[derive(Debug)]
+pub struct Revision {
+    pub id: i64,
+    pub hv_msg_id: i64,
+    pub msg_title: String,
+    pub msg_text: String,
+    pub media_kind: MediaKind,
+    pub file_id: String,
+    pub buttons: String,
+    pub edited_by: String, // user id of the admin
+    pub editor_name: Option<String>, // 从 hv_user 表关联获取
+    pub created_at: chrono::DateTime<Utc>, // the time of the edit
+}
+
 #[derive(Debug, sqlx::Type, PartialEq, Clone)]
 #[sqlx(type_name = "INTEGER")]
 #[repr(i32)]
@@ -35,47 +118,111 @@ impl Msg {
     pub async fn all(&self) -> Vec<Message> {
         sqlx::query("SELECT * FROM hv_msg WHERE msg_type = ? ")
             .bind(MsgType::Polling as i32)
-            .map(|row: sqlx::sqlite::SqliteRow| Message {
-                id: row.get("id"),
-                msg_type: row.try_get("msg_type").unwrap(),
-                msg_text: row.get("msg_text"),
-                msg_title: row.get("msg_title"),
-                created_at: row.get("created_at"),
-            })
+            .map(message_from_row)
             .fetch_all(&self.conn.sqlite_pool)
             .await
             .unwrap()
     }
 
+    /// Get the message by the id
+    pub async fn get_msg(&self, id: i64) -> Result<Option<Message>> {
+        let msg = sqlx::query("SELECT * FROM hv_msg WHERE id = ?")
+            .bind(id)
+            .map(message_from_row)
+            .fetch_optional(&self.conn.sqlite_pool)
+            .await?;
+        Ok(msg)
+    }
+
+    /// The content of the message to send, with the album items.
+    pub async fn get_content(&self, id: i64) -> Result<Option<MsgContent>> {
+        let Some(msg) = self.get_msg(id).await? else {
+            return Ok(None);
+        };
+        let mut content = msg.content();
+        if content.media_kind == MediaKind::Album {
+            content.album = self.album(id).await?;
+        }
+        Ok(Some(content))
+    }
+
     /// Add the new message, return id.
     pub async fn add_msg(&self, msg_type: MsgType, msg_text: &str, msg_title: &str) -> i64 {
-        sqlx::query("INSERT INTO hv_msg (msg_type, msg_text, msg_title) VALUES (?, ?, ?)")
+        self.add_content_msg(msg_type, &MsgContent::text(msg_text), msg_title)
+            .await
+    }
+
+    /// Add the new message with text or media content, return id.
+    pub async fn add_content_msg(&self, msg_type: MsgType, content: &MsgContent, msg_title: &str) -> i64 {
+        let insert_id = sqlx::query("INSERT INTO hv_msg (msg_type, msg_text, msg_title, media_kind, file_id, buttons) VALUES (?, ?, ?, ?, ?, ?)")
             .bind(msg_type.clone() as i32)
-            .bind(msg_text)
+            .bind(&content.text)
             .bind(msg_title)
+            .bind(content.media_kind)
+            .bind(&content.file_id)
+            .bind(&content.buttons)
             .execute(&self.conn.sqlite_pool)
             .await
             .unwrap()
-            .last_insert_rowid()
+            .last_insert_rowid();
+
+        for (position, item) in content.album.iter().enumerate() {
+            sqlx::query("INSERT INTO hv_msg_album (hv_msg_id, position, media_kind, file_id, caption) VALUES (?, ?, ?, ?, ?)")
+                .bind(insert_id)
+                .bind(position as i64)
+                .bind(item.media_kind)
+                .bind(&item.file_id)
+                .bind(&item.text)
+                .execute(&self.conn.sqlite_pool)
+                .await
+                .unwrap();
+        }
+        insert_id
+    }
+
+    /// The items of the album message, in order
+    pub async fn album(&self, msg_id: i64) -> Result<Vec<MsgContent>> {
+        let items = sqlx::query("SELECT media_kind, file_id, caption FROM hv_msg_album WHERE hv_msg_id = ? ORDER BY position")
+            .bind(msg_id)
+            .map(|row: sqlx::sqlite::SqliteRow| MsgContent {
+                media_kind: row.get("media_kind"),
+                file_id: row.get("file_id"),
+                text: row.get("caption"),
+                ..Default::default()
+            })
+            .fetch_all(&self.conn.sqlite_pool)
+            .await?;
+        Ok(items)
     }
 
     
-    /// Add welcome message
+    /// Add welcome message (the global one, for all groups)
     pub async fn add_welcome_msg(&self,msg_text: &str) -> bool {
+        self.set_group_welcome_msg(GLOBAL_WELCOME, &MsgContent::text(msg_text)).await
+    }
+
+    /// Set the welcome message of the group (database id), `GLOBAL_WELCOME` is the global one.
+    /// The previous one is replaced, the welcome messages have no history.
+    pub async fn set_group_welcome_msg(&self, group_db_id: i64, content: &MsgContent) -> bool {
         let msg_type = MsgType::Welcome as i32;
         let msg_title = "welcome";
         
         
-        let has_one: i32 = sqlx::query_scalar("SELECT count(*) FROM hv_msg WHERE msg_type = ?")
+        let has_one: i32 = sqlx::query_scalar("SELECT count(*) FROM hv_msg WHERE msg_type = ? AND hv_group_id = ?")
             .bind(msg_type)
+            .bind(group_db_id)
             .fetch_one(&self.conn.sqlite_pool)
             .await
             .unwrap();
         // has one do update
        if has_one > 0 {
-           let rows_affected = sqlx::query("UPDATE hv_msg set msg_text = ?, msg_title = 'welcome' WHERE msg_type = ?")
-                .bind(msg_text)
+           let rows_affected = sqlx::query("UPDATE hv_msg set msg_text = ?, msg_title = 'welcome', media_kind = ?, file_id = ?, buttons = ? WHERE msg_type = ? AND hv_group_id = ?")
+                .bind(&content.text)
+                .bind(content.media_kind)
+                .bind(&content.file_id)
+                .bind(&content.buttons)
                 .bind(msg_type)
+                .bind(group_db_id)
                 .execute(&self.conn.sqlite_pool)
                 .await
                 .unwrap()
@@ -84,25 +231,109 @@ impl Msg {
            return rows_affected > 0
         }
         
-        let insert_id  = self.add_msg(MsgType::Welcome, msg_text, msg_title).await;
+        let insert_id = sqlx::query("INSERT INTO hv_msg (msg_type, msg_text, msg_title, media_kind, file_id, buttons, hv_group_id) VALUES (?, ?, ?, ?, ?, ?, ?)")
+            .bind(msg_type)
+            .bind(&content.text)
+            .bind(msg_title)
+            .bind(content.media_kind)
+            .bind(&content.file_id)
+            .bind(&content.buttons)
+            .bind(group_db_id)
+            .execute(&self.conn.sqlite_pool)
+            .await
+            .unwrap()
+            .last_insert_rowid();
         insert_id > 0
     }
 
+    /// Remove the welcome message of the group, it falls back to the global one.
+    pub async fn remove_group_welcome_msg(&self, group_db_id: i64) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM hv_msg WHERE msg_type = ? AND hv_group_id = ?")
+            .bind(MsgType::Welcome as i32)
+            .bind(group_db_id)
+            .execute(&self.conn.sqlite_pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The welcome message of the group itself, `None` when it uses the global one.
+    pub async fn group_welcome_msg(&self, group_db_id: i64) -> Option<MsgContent> {
+        sqlx::query("SELECT msg_text, media_kind, file_id, buttons FROM hv_msg WHERE msg_type = ? AND hv_group_id = ?")
+            .bind(MsgType::Welcome as i32)
+            .bind(group_db_id)
+            .fetch_optional(&self.conn.sqlite_pool)
+            .await
+            .unwrap()
+            .map(|row| MsgContent {
+                media_kind: row.get("media_kind"),
+                file_id: row.get("file_id"),
+                text: row.get("msg_text"),
+                album: Vec::new(),
+                buttons: row.get("buttons"),
+            })
+    }
+
+    /// The welcome message for the group: its own first, then the global one.
+    pub async fn welcome_msg_for(&self, group_db_id: Option<i64>) -> MsgContent {
+        if let Some(group_db_id) = group_db_id {
+            if let Some(content) = self.group_welcome_msg(group_db_id).await {
+                return content;
+            }
+        }
+        self.group_welcome_msg(GLOBAL_WELCOME)
+            .await
+            .unwrap_or_else(|| MsgContent::text(DEFAULT_WELCOME))
+    }
+
     /// Remove msg by the id
     /// 1. deleting polling data if you use this msg
-    /// 2. to delete msg.
+    /// 2. to delete msg, its album items and history.
+    /// 3. removing it from the pools and the drip sequences.
+    ///
+    /// All in one transaction, nothing is left pointing at a half removed msg.
     pub async fn remove_msg(&self, msg_id: i64) -> Result<bool>{
-        polling_msg::new(self.conn.clone()).delete_by_msg_id(msg_id).await?;
-        
+        let mut tx = self.conn.sqlite_pool.begin().await?;
+        polling_msg::delete_by_msg_id(&mut tx, msg_id).await?;
+
+        for query in [
+            "DELETE FROM hv_drip_pending WHERE step_id IN (SELECT id FROM hv_drip_step WHERE hv_msg_id = ?)",
+            "DELETE FROM hv_drip_step WHERE hv_msg_id = ?",
+            "DELETE FROM hv_msg_pool_item WHERE hv_msg_id = ?",
+            "DELETE FROM hv_msg_album WHERE hv_msg_id = ?",
+            "DELETE FROM hv_msg_revision WHERE hv_msg_id = ?",
+        ] {
+            sqlx::query(query).bind(msg_id).execute(&mut *tx).await?;
+        }
+
         let result_rows = sqlx::query("DELETE FROM hv_msg WHERE id = ?")
             .bind(msg_id)
-            .execute(&self.conn.sqlite_pool)
+            .execute(&mut *tx)
             .await
             ?.rows_affected();
+        tx.commit().await?;
         Ok(result_rows > 0 )
     }
 
-    pub async fn edit_msg(&self, id: i64, msg_text: &str) -> bool {
+    /// Edit the title, the old content is kept in the history.
+    pub async fn edit_title(&self, id: i64, msg_title: &str, edited_by: &str) -> bool {
+        if !self.save_revision(id, edited_by).await {
+            return false;
+        }
+        let rows_affected = sqlx::query("UPDATE hv_msg set msg_title = ? WHERE id = ?")
+            .bind(msg_title)
+            .bind(id)
+            .execute(&self.conn.sqlite_pool)
+            .await
+            .unwrap()
+            .rows_affected();
+        rows_affected > 0
+    }
+
+    /// Edit the text (caption), the old content is kept in the history.
+    pub async fn edit_msg(&self, id: i64, msg_text: &str, edited_by: &str) -> bool {
+        if !self.save_revision(id, edited_by).await {
+            return false;
+        }
         let rows_affected = sqlx::query("UPDATE hv_msg set msg_text = ? WHERE id = ?")
             .bind(msg_text)
             .bind(id)
@@ -113,13 +344,102 @@ impl Msg {
         rows_affected > 0
     }
 
-    pub async fn welcome_msg(&self) -> String {
-        sqlx::query("SELECT msg_text FROM hv_msg WHERE msg_type = ?")
-            .bind(MsgType::Welcome as i32)
+    /// Keep the current content of the message in the history, `false` when it does not exist.
+    ///
+    /// Only the fields of `hv_msg` are kept: the title, text, media and buttons of a single message.
+    /// The album items are not, an album can not be edited but added again, so it has no history.
+    /// The welcome messages have no history either, see `set_group_welcome_msg`.
+    async fn save_revision(&self, id: i64, edited_by: &str) -> bool {
+        sqlx::query(
+            "INSERT INTO hv_msg_revision (hv_msg_id, msg_title, msg_text, media_kind, file_id, buttons, edited_by)
+SELECT id, msg_title, msg_text, media_kind, file_id, buttons, ? FROM hv_msg WHERE id = ?",
+        )
+        .bind(edited_by)
+        .bind(id)
+        .execute(&self.conn.sqlite_pool)
+        .await
+        .unwrap()
+        .rows_affected()
+            > 0
+    }
+
+    /// The history of the message, the latest first.
+    pub async fn revisions(&self, msg_id: i64, limit: i64) -> Result<Vec<Revision>> {
+        let revisions = sqlx::query(&format!("{REVISION_SELECT} WHERE r.hv_msg_id = ? ORDER BY r.id DESC LIMIT ?"))
+            .bind(msg_id)
+            .bind(limit)
+            .map(revision_from_row)
+            .fetch_all(&self.conn.sqlite_pool)
+            .await?;
+        Ok(revisions)
+    }
+
+    pub async fn get_revision(&self, revision_id: i64) -> Result<Option<Revision>> {
+        let revision = sqlx::query(&format!("{REVISION_SELECT} WHERE r.id = ?"))
+            .bind(revision_id)
+            .map(revision_from_row)
             .fetch_optional(&self.conn.sqlite_pool)
-            .await
-            .unwrap()
-            .map(|row| row.get("msg_text"))
-            .unwrap_or_else(|| "Hi! Nice to meet you".to_string())
+            .await?;
+        Ok(revision)
+    }
+
+    /// Restore the message to the revision, the current content goes to the history too,
+    /// so the restore can be rolled back as well.
+    pub async fn restore_revision(&self, revision_id: i64, edited_by: &str) -> Result<bool> {
+        let Some(revision) = self.get_revision(revision_id).await? else {
+            return Ok(false);
+        };
+        if !self.save_revision(revision.hv_msg_id, edited_by).await {
+            return Ok(false);
+        }
+
+        let result = sqlx::query(
+            "UPDATE hv_msg SET msg_title = ?, msg_text = ?, media_kind = ?, file_id = ?, buttons = ? WHERE id = ?",
+        )
+        .bind(&revision.msg_title)
+        .bind(&revision.msg_text)
+        .bind(revision.media_kind)
+        .bind(&revision.file_id)
+        .bind(&revision.buttons)
+        .bind(revision.hv_msg_id)
+        .execute(&self.conn.sqlite_pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The global welcome message (text or caption)
+    pub async fn welcome_msg(&self) -> String {
+        self.welcome_msg_for(None).await.text
+    }
+}
+
+fn message_from_row(row: sqlx::sqlite::SqliteRow) -> Message {
+    Message {
+        id: row.get("id"),
+        msg_type: row.try_get("msg_type").unwrap(),
+        msg_text: row.get("msg_text"),
+        msg_title: row.get("msg_title"),
+        media_kind: row.get("media_kind"),
+        file_id: row.get("file_id"),
+        buttons: row.get("buttons"),
+        created_at: row.get("created_at"),
+    }
+}
+
+const REVISION_SELECT: &str = "SELECT r.*, u.user_name FROM hv_msg_revision r
+LEFT JOIN hv_user u ON u.user_id = r.edited_by";
+
+fn revision_from_row(row: sqlx::sqlite::SqliteRow) -> Revision {
+    Revision {
+        id: row.get("id"),
+        hv_msg_id: row.get("hv_msg_id"),
+        msg_title: row.get("msg_title"),
+        msg_text: row.get("msg_text"),
+        media_kind: row.get("media_kind"),
+        file_id: row.get("file_id"),
+        buttons: row.get("buttons"),
+        edited_by: row.get("edited_by"),
+        editor_name: row.get("user_name"),
+        created_at: row.get("created_at"),
     }
 }
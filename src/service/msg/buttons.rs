@@ -0,0 +1,87 @@
+//! # Buttons
+//! Inline URL buttons under the message, stored as the definition text in `hv_msg.buttons`.
+//!
+//! One line per row, buttons in a row are separated by `|`, each button is `Label - URL`:
+//!
+//! ```text
+//! Register - https://example.com/register | Rules - https://t.me/example/2
+//! Website - https://example.com
+//! ```
+use anyhow::{anyhow, bail, Result};
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use url::Url;
+
+/// Telegram shows at most 8 buttons in a row
+const ROW_LIMIT: usize = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Button {
+    pub label: String,
+    pub url: Url,
+}
+
+/// Parse the button definition, empty text has no buttons.
+pub fn parse(def: &str) -> Result<Vec<Vec<Button>>> {
+    let mut rows = Vec::new();
+    for (i, line) in def.lines().map(str::trim).enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let row = line
+            .split('|')
+            .map(|button| parse_button(button.trim()))
+            .collect::<Result<Vec<Button>>>()
+            .map_err(|e| anyhow!("Row {}: {e}", i + 1))?;
+        if row.len() > ROW_LIMIT {
+            bail!("Row {}: at most {ROW_LIMIT} buttons in a row", i + 1);
+        }
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// The definition in the canonical form, e.g. to save it.
+pub fn normalize(def: &str) -> Result<String> {
+    Ok(parse(def)?
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|button| format!("{} - {}", button.label, button.url))
+                .collect::<Vec<String>>()
+                .join(" | ")
+        })
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+/// The keyboard of the definition, `None` when there are no (valid) buttons.
+pub fn keyboard(def: &str) -> Option<InlineKeyboardMarkup> {
+    let rows = parse(def).ok()?;
+    if rows.is_empty() {
+        return None;
+    }
+    Some(InlineKeyboardMarkup::new(rows.into_iter().map(|row| {
+        row.into_iter()
+            .map(|button| InlineKeyboardButton::url(button.label, button.url))
+    })))
+}
+
+fn parse_button(button: &str) -> Result<Button> {
+    // The URL has no spaces, the label may have " - ".
+    let (label, url) = button
+        .rsplit_once(" - ")
+        .ok_or_else(|| anyhow!("'{button}' should be 'Label - URL'"))?;
+    let label = label.trim();
+    if label.is_empty() {
+        bail!("'{button}' has no label");
+    }
+
+    let url = Url::parse(url.trim()).map_err(|_| anyhow!("'{}' is not a URL", url.trim()))?;
+    if !matches!(url.scheme(), "http" | "https" | "tg") {
+        bail!("'{url}' should be a http, https or tg link");
+    }
+    Ok(Button {
+        label: label.to_string(),
+        url,
+    })
+}
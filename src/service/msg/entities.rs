@@ -0,0 +1,99 @@
+//! # Entities
+//! Convert the formatting entities of the Telegram message to the HTML we store and send.
+//!
+//! The admin composes the message with the Telegram formatting (bold, links, custom emoji...),
+//! the plain text is escaped, so `<` and `&` in the message are safe.
+use teloxide::types::{MessageEntity, MessageEntityKind};
+use teloxide::utils::html;
+
+/// The HTML of the text with its entities, offsets are UTF-16 code units.
+pub fn to_html(text: &str, entities: &[MessageEntity]) -> String {
+    // Outer entities first: earlier start, then longer.
+    let mut entities: Vec<&MessageEntity> = entities
+        .iter()
+        .filter(|entity| tags(&entity.kind).is_some() && entity.length > 0)
+        .collect();
+    entities.sort_by_key(|entity| (entity.offset, std::cmp::Reverse(entity.length)));
+
+    let mut out = String::with_capacity(text.len());
+    let mut open: Vec<&MessageEntity> = Vec::new();
+    let mut next = 0;
+    let mut offset = 0;
+    for c in text.chars() {
+        apply(offset, &entities, &mut next, &mut open, &mut out);
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            _ => out.push(c),
+        }
+        offset += c.len_utf16();
+    }
+    apply(offset, &entities, &mut next, &mut open, &mut out);
+    // Entities past the end of the text
+    while let Some(entity) = open.pop() {
+        out.push_str(&tags(&entity.kind).unwrap().1);
+    }
+    out
+}
+
+/// Close the entities ending at the offset, then open the ones starting there.
+fn apply<'a>(
+    offset: usize,
+    entities: &[&'a MessageEntity],
+    next: &mut usize,
+    open: &mut Vec<&'a MessageEntity>,
+    out: &mut String,
+) {
+    while let Some(i) = open.iter().rposition(|entity| entity.offset + entity.length <= offset) {
+        // Overlapping entities: close the inner ones and open them again.
+        let reopen = open.split_off(i + 1);
+        for entity in reopen.iter().rev() {
+            out.push_str(&tags(&entity.kind).unwrap().1);
+        }
+        let closed = open.pop().unwrap();
+        out.push_str(&tags(&closed.kind).unwrap().1);
+        for entity in reopen {
+            out.push_str(&tags(&entity.kind).unwrap().0);
+            open.push(entity);
+        }
+    }
+
+    while let Some(entity) = entities.get(*next).filter(|entity| entity.offset <= offset) {
+        out.push_str(&tags(&entity.kind).unwrap().0);
+        open.push(entity);
+        *next += 1;
+    }
+}
+
+/// The (open, close) tags of the entity, `None` for the plain ones (url, mention, hashtag...).
+fn tags(kind: &MessageEntityKind) -> Option<(String, String)> {
+    let simple = |tag: &str| Some((format!("<{tag}>"), format!("</{tag}>")));
+    match kind {
+        MessageEntityKind::Bold => simple("b"),
+        MessageEntityKind::Italic => simple("i"),
+        MessageEntityKind::Underline => simple("u"),
+        MessageEntityKind::Strikethrough => simple("s"),
+        MessageEntityKind::Spoiler => simple("tg-spoiler"),
+        MessageEntityKind::Code => simple("code"),
+        MessageEntityKind::Blockquote => simple("blockquote"),
+        MessageEntityKind::Pre { language: Some(language) } => Some((
+            format!("<pre><code class=\"language-{}\">", html::escape(language)),
+            "</code></pre>".to_string(),
+        )),
+        MessageEntityKind::Pre { language: None } => simple("pre"),
+        MessageEntityKind::TextLink { url } => Some((
+            format!("<a href=\"{}\">", html::escape(url.as_str())),
+            "</a>".to_string(),
+        )),
+        MessageEntityKind::TextMention { user } => Some((
+            format!("<a href=\"tg://user?id={}\">", user.id),
+            "</a>".to_string(),
+        )),
+        MessageEntityKind::CustomEmoji { custom_emoji_id } => Some((
+            format!("<tg-emoji emoji-id=\"{}\">", html::escape(custom_emoji_id)),
+            "</tg-emoji>".to_string(),
+        )),
+        _ => None,
+    }
+}
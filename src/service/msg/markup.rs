@@ -0,0 +1,79 @@
+//! # Markup
+//! Check the message HTML against what Telegram accepts, before it is saved.
+//!
+//! Telegram rejects the whole message on an unknown tag, an unclosed tag or a bare `<` / `&`,
+//! the scheduled send would fail every time, so the admin is told when composing it.
+use anyhow::{bail, Result};
+
+/// Tags of the Telegram HTML style
+const TAGS: [&str; 16] = [
+    "b", "strong", "i", "em", "u", "ins", "s", "strike", "del", "span", "tg-spoiler", "a",
+    "tg-emoji", "code", "pre", "blockquote",
+];
+
+/// Check the tags and the character entities of the text.
+pub fn validate(text: &str) -> Result<()> {
+    let mut open: Vec<&str> = Vec::new();
+    let mut rest = text;
+    while let Some(i) = rest.find(['<', '&']) {
+        let tail = &rest[i..];
+        if tail.starts_with('&') {
+            let len = entity_len(tail).unwrap_or(0);
+            if len == 0 {
+                bail!("Bare '&', write &amp; for the character");
+            }
+            rest = &tail[len..];
+            continue;
+        }
+
+        let Some(end) = tail.find('>') else {
+            bail!("Bare '<', write &lt; for the character");
+        };
+        let tag = &tail[1..end];
+        rest = &tail[end + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            match open.pop() {
+                Some(top) if top == name => {}
+                Some(top) => bail!("</{name}> does not match <{top}>"),
+                None => bail!("</{name}> is not opened"),
+            }
+            continue;
+        }
+
+        let name = tag.split_whitespace().next().unwrap_or_default();
+        if !TAGS.contains(&name) {
+            bail!(
+                "Tag <{name}> is not supported, Telegram supports: {}",
+                TAGS.iter()
+                    .map(|tag| format!("<{tag}>"))
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            );
+        }
+        if name == "a" && !tag.contains("href=") {
+            bail!("<a> needs the href");
+        }
+        open.push(name);
+    }
+
+    if let Some(tag) = open.pop() {
+        bail!("<{tag}> is not closed");
+    }
+    Ok(())
+}
+
+/// Length of the character entity at the start, `&lt;` `&gt;` `&amp;` `&quot;` or numeric.
+fn entity_len(text: &str) -> Option<usize> {
+    let end = text.find(';')?;
+    let name = &text[1..end];
+    let valid = match name.strip_prefix('#') {
+        Some(hex) if hex.starts_with(['x', 'X']) => {
+            hex.len() > 1 && hex[1..].chars().all(|c| c.is_ascii_hexdigit())
+        }
+        Some(dec) => !dec.is_empty() && dec.chars().all(|c| c.is_ascii_digit()),
+        None => matches!(name, "lt" | "gt" | "amp" | "quot"),
+    };
+    valid.then_some(end + 1)
+}
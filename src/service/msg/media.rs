@@ -0,0 +1,195 @@
+//! # Media
+//! Photo, video, document and animation messages, stored by the Telegram `file_id`.
+//!
+//! The caption is the `msg_text` of the message, placeholders work the same as in the text.
+//! An album (media group) is one message too, its items are sent with `send_media_group`.
+use crate::service::msg::{buttons, entities};
+use crate::service::msg::template::{self, TemplateContext};
+use crate::service::msg::{MediaKind, MsgContent};
+use anyhow::{bail, Result};
+use teloxide::payloads::{
+    SendAnimationSetters, SendDocumentSetters, SendMessageSetters, SendPhotoSetters,
+    SendVideoSetters,
+};
+use teloxide::prelude::*;
+use teloxide::types::{
+    InputFile, InputMedia, InputMediaDocument, InputMediaPhoto, InputMediaVideo, ParseMode,
+    Recipient,
+};
+use teloxide::RequestError;
+
+/// Telegram sends 2-10 items in one media group
+pub const ALBUM_LIMIT: usize = 10;
+
+/// The content of the message sent (or forwarded) by the admin, the formatting is kept as HTML.
+/// `None` for the unsupported kinds (sticker, voice...).
+///
+/// A part of an album is returned as the single media, see [`add_album_item`].
+pub fn content_of(msg: &Message) -> Option<MsgContent> {
+    if let Some(text) = msg.text() {
+        let text = entities::to_html(text, msg.entities().unwrap_or_default());
+        return Some(MsgContent::text(text.trim()));
+    }
+
+    let (media_kind, file_id) = if let Some(photos) = msg.photo() {
+        // The largest size is the last one
+        (MediaKind::Photo, photos.last()?.file.id.clone())
+    } else if let Some(video) = msg.video() {
+        (MediaKind::Video, video.file.id.clone())
+    } else if let Some(animation) = msg.animation() {
+        // Animations also carry the document, check it first.
+        (MediaKind::Animation, animation.file.id.clone())
+    } else if let Some(document) = msg.document() {
+        (MediaKind::Document, document.file.id.clone())
+    } else {
+        return None;
+    };
+
+    Some(MsgContent {
+        media_kind,
+        file_id,
+        text: entities::to_html(
+            msg.caption().unwrap_or_default(),
+            msg.caption_entities().unwrap_or_default(),
+        )
+        .trim()
+        .to_string(),
+        ..Default::default()
+    })
+}
+
+/// Add the album part to the album content, the first caption is the caption of the album.
+pub fn add_album_item(album: &mut MsgContent, item: MsgContent) -> Result<()> {
+    if !matches!(
+        item.media_kind,
+        MediaKind::Photo | MediaKind::Video | MediaKind::Document
+    ) {
+        bail!("Only photos, videos and documents can be in an album");
+    }
+    if album.album.len() >= ALBUM_LIMIT {
+        bail!("An album has at most {ALBUM_LIMIT} items");
+    }
+
+    album.media_kind = MediaKind::Album;
+    if album.text.is_empty() {
+        album.text = item.text.clone();
+    }
+    album.album.push(item);
+    Ok(())
+}
+
+/// Send the content with its buttons, the text (or captions) are rendered with the context as HTML.
+///
+/// The first message is returned for the album.
+pub async fn send_content<C>(
+    bot: &Bot,
+    chat_id: C,
+    content: &MsgContent,
+    ctx: &TemplateContext,
+) -> Result<Message, RequestError>
+where
+    C: Into<Recipient>,
+{
+    let text = template::render(&content.text, ctx);
+    let file = InputFile::file_id(content.file_id.clone());
+    let keyboard = buttons::keyboard(&content.buttons);
+    match content.media_kind {
+        MediaKind::Text => {
+            let mut request = bot.send_message(chat_id, text).parse_mode(ParseMode::Html);
+            if let Some(keyboard) = keyboard {
+                request = request.reply_markup(keyboard);
+            }
+            request.await
+        }
+        MediaKind::Photo => {
+            let mut request = bot.send_photo(chat_id, file);
+            if !text.is_empty() {
+                request = request.caption(text).parse_mode(ParseMode::Html);
+            }
+            if let Some(keyboard) = keyboard {
+                request = request.reply_markup(keyboard);
+            }
+            request.await
+        }
+        MediaKind::Video => {
+            let mut request = bot.send_video(chat_id, file);
+            if !text.is_empty() {
+                request = request.caption(text).parse_mode(ParseMode::Html);
+            }
+            if let Some(keyboard) = keyboard {
+                request = request.reply_markup(keyboard);
+            }
+            request.await
+        }
+        MediaKind::Document => {
+            let mut request = bot.send_document(chat_id, file);
+            if !text.is_empty() {
+                request = request.caption(text).parse_mode(ParseMode::Html);
+            }
+            if let Some(keyboard) = keyboard {
+                request = request.reply_markup(keyboard);
+            }
+            request.await
+        }
+        MediaKind::Animation => {
+            let mut request = bot.send_animation(chat_id, file);
+            if !text.is_empty() {
+                request = request.caption(text).parse_mode(ParseMode::Html);
+            }
+            if let Some(keyboard) = keyboard {
+                request = request.reply_markup(keyboard);
+            }
+            request.await
+        }
+        // Media groups can not have the buttons.
+        MediaKind::Album => {
+            let media = content
+                .album
+                .iter()
+                .map(|item| input_media(item, template::render(&item.text, ctx)));
+            let mut sent = bot.send_media_group(chat_id, media).await?;
+            // Telegram returns one message per item, never empty.
+            Ok(sent.remove(0))
+        }
+    }
+}
+
+fn input_media(item: &MsgContent, caption: String) -> InputMedia {
+    let file = InputFile::file_id(item.file_id.clone());
+    match item.media_kind {
+        MediaKind::Video => {
+            let mut media = InputMediaVideo::new(file);
+            if !caption.is_empty() {
+                media = media.caption(caption).parse_mode(ParseMode::Html);
+            }
+            InputMedia::Video(media)
+        }
+        MediaKind::Document => {
+            let mut media = InputMediaDocument::new(file);
+            if !caption.is_empty() {
+                media = media.caption(caption).parse_mode(ParseMode::Html);
+            }
+            InputMedia::Document(media)
+        }
+        _ => {
+            let mut media = InputMediaPhoto::new(file);
+            if !caption.is_empty() {
+                media = media.caption(caption).parse_mode(ParseMode::Html);
+            }
+            InputMedia::Photo(media)
+        }
+    }
+}
+
+impl MediaKind {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            MediaKind::Text => "📝",
+            MediaKind::Photo => "🖼",
+            MediaKind::Video => "🎬",
+            MediaKind::Document => "📄",
+            MediaKind::Animation => "🎞",
+            MediaKind::Album => "🗂",
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! # Template
+//! Placeholders in the welcome and scheduled messages, e.g. `Welcome {mention} to {group_name}!`
+//!
+//! The message text is HTML written by the admin, only the placeholder values are escaped.
+//!
+//! | Placeholder      | Value                                  | Message       |
+//! |------------------|----------------------------------------|---------------|
+//! | `{first_name}`   | first name of the new member           | welcome       |
+//! | `{mention}`      | link mentioning the new member         | welcome       |
+//! | `{username}`     | @username, or the first name           | welcome       |
+//! | `{group_name}`   | group title                            | all           |
+//! | `{member_count}` | number of group members                | all           |
+//! | `{date}`         | date in the group time zone            | all           |
+//! | `{weekday}`      | weekday in the group time zone         | all           |
+use crate::service::msg::{markup, MsgType};
+use anyhow::{bail, Result};
+use chrono::NaiveDateTime;
+use teloxide::types::UserId;
+use teloxide::utils::html;
+
+const MEMBER_PLACEHOLDERS: [&str; 3] = ["first_name", "mention", "username"];
+const GROUP_PLACEHOLDERS: [&str; 4] = ["group_name", "member_count", "date", "weekday"];
+
+/// Values of the placeholders, missing ones render empty.
+#[derive(Debug, Default, Clone)]
+pub struct TemplateContext {
+    pub user_id: Option<u64>,
+    pub first_name: String,
+    pub username: Option<String>,
+    pub group_name: String,
+    pub member_count: Option<u32>,
+    pub now: Option<NaiveDateTime>, // wall clock of the group
+}
+
+/// The placeholders the message type supports
+pub fn placeholders(msg_type: &MsgType) -> Vec<&'static str> {
+    match msg_type {
+        MsgType::Welcome => MEMBER_PLACEHOLDERS
+            .iter()
+            .chain(GROUP_PLACEHOLDERS.iter())
+            .copied()
+            .collect(),
+        MsgType::Polling => GROUP_PLACEHOLDERS.to_vec(),
+    }
+}
+
+/// Check the message when saving it, the unknown placeholders and the HTML Telegram
+/// can not parse are rejected.
+pub fn validate(text: &str, msg_type: &MsgType) -> Result<()> {
+    markup::validate(text)?;
+
+    let supported = placeholders(msg_type);
+    let unknown: Vec<String> = scan(text)
+        .into_iter()
+        .filter_map(|part| match part {
+            Part::Placeholder(name) if !supported.contains(&name) => Some(format!("{{{name}}}")),
+            _ => None,
+        })
+        .collect();
+
+    if !unknown.is_empty() {
+        bail!(
+            "Unknown placeholder {}, supported: {}",
+            unknown.join(", "),
+            supported
+                .iter()
+                .map(|name| format!("{{{name}}}"))
+                .collect::<Vec<String>>()
+                .join(" ")
+        );
+    }
+    Ok(())
+}
+
+/// Does the text use the placeholder? e.g. to skip fetching the member count.
+pub fn uses(text: &str, placeholder: &str) -> bool {
+    scan(text)
+        .into_iter()
+        .any(|part| matches!(part, Part::Placeholder(name) if name == placeholder))
+}
+
+/// Render the placeholders with the HTML escaped values.
+pub fn render(text: &str, ctx: &TemplateContext) -> String {
+    scan(text)
+        .into_iter()
+        .map(|part| match part {
+            Part::Text(text) => text.to_string(),
+            Part::Placeholder(name) => value(name, ctx).unwrap_or_else(|| format!("{{{name}}}")),
+        })
+        .collect()
+}
+
+fn value(name: &str, ctx: &TemplateContext) -> Option<String> {
+    let value = match name {
+        "first_name" => html::escape(&ctx.first_name),
+        "mention" => match ctx.user_id {
+            Some(user_id) => html::user_mention(UserId(user_id), &ctx.first_name),
+            None => html::escape(&ctx.first_name),
+        },
+        "username" => match &ctx.username {
+            Some(username) => html::escape(&format!("@{username}")),
+            None => html::escape(&ctx.first_name),
+        },
+        "group_name" => html::escape(&ctx.group_name),
+        "member_count" => ctx.member_count.map(|n| n.to_string()).unwrap_or_default(),
+        "date" => ctx.now.map(|now| now.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+        "weekday" => ctx.now.map(|now| now.format("%A").to_string()).unwrap_or_default(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+enum Part<'a> {
+    Text(&'a str),
+    Placeholder(&'a str),
+}
+
+/// Split the text into the plain parts and the `{name}` placeholders.
+fn scan(text: &str) -> Vec<Part<'_>> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 1..start + len];
+        if !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c == '_') {
+            parts.push(Part::Text(&rest[..start]));
+            parts.push(Part::Placeholder(name));
+        } else {
+            parts.push(Part::Text(&rest[..=start]));
+            rest = &rest[start + 1..];
+            continue;
+        }
+        rest = &rest[start + len + 1..];
+    }
+    parts.push(Part::Text(rest));
+    parts
+}
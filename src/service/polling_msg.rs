@@ -1,46 +1,407 @@
-use crate::service::Db;
-use anyhow::Result;
+pub mod schedule;
+
+use crate::service::delivery::DeliveryStatus;
+use crate::service::msg::{MediaKind, MsgContent};
+use crate::service::{group, Db};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use sqlx::sqlite::{SqliteConnection, SqliteRow};
 use sqlx::Row;
 
-#[derive(Debug)]
+pub use schedule::Schedule;
+
+#[derive(Debug, Clone)]
 pub struct PollingMsg {
     pub id: i64,
     pub hv_msg_id: i64,
     pub group_id: String,
-    pub send_time: String,
+    pub group_name: String, // 从 hv_group 表关联获取
+    pub send_time: String, // 推送规则: HH:MM 或 cron 表达式
+    pub tag: String, // 标签推送, 发给有该标签的所有群; 空为单群推送
+    pub disabled: bool, // 停用的推送不发送
+    pub cleanup: Cleanup, // 自动删除已发送的消息
+    pub pin: Pin, // 置顶已发送的消息
+    pub pool_id: i64, // 消息池推送, 每次发送池里的下一条; 0 为单条消息
     pub msg_text: String, // 从 hv_msg 表关联获取
     pub msg_title: String,
     pub msg_type: i32, // 从 hv_msg 表关联获取
+    pub media_kind: MediaKind, // 从 hv_msg 表关联获取
+    pub file_id: String,
+    pub buttons: String, // 从 hv_msg 表关联获取
+    pub time_zone: String, // 从 hv_group 表关联获取
+    pub start_date: Option<NaiveDate>, // 有效期, 群时区的日期
+    pub end_date: Option<NaiveDate>,
+    pub polling_resumed_at: Option<DateTime<Utc>>, // 从 hv_group 表关联获取
+    pub created_at: DateTime<Utc>,
+}
+
+impl PollingMsg {
+    /// The message content to send, the album items are loaded by `msg::Msg::album`.
+    pub fn content(&self) -> MsgContent {
+        MsgContent {
+            media_kind: self.media_kind,
+            file_id: self.file_id.clone(),
+            text: self.msg_text.clone(),
+            album: Vec::new(),
+            buttons: self.buttons.clone(),
+        }
+    }
+
+    pub fn schedule(&self) -> Result<Schedule> {
+        Schedule::parse(&self.send_time)
+    }
+
+    /// Time zone of the group, `None` is the server zone.
+    pub fn tz(&self) -> Option<Tz> {
+        group::parse_time_zone(&self.time_zone).unwrap_or(None)
+    }
+
+    /// Is the date (wall clock of the group) within the validity window?
+    pub fn in_window(&self, date: NaiveDate) -> bool {
+        self.start_date.is_none_or(|start| start <= date)
+            && self.end_date.is_none_or(|end| date <= end)
+    }
+
+    /// The validity window for display, e.g. `2026-11-01 ~ 2026-11-30`, empty when unbounded.
+    pub fn window_str(&self) -> String {
+        if self.start_date.is_none() && self.end_date.is_none() {
+            return String::new();
+        }
+        let fmt = |date: Option<NaiveDate>| date.map(|d| d.to_string()).unwrap_or_default();
+        format!("{} ~ {}", fmt(self.start_date), fmt(self.end_date))
+    }
+
+    /// Is the one-shot push already in the past (wall clock of the group)?
+    pub fn is_expired(&self, at: &DateTime<Utc>) -> bool {
+        match self.schedule().ok().and_then(|s| s.one_shot_at()) {
+            Some(once) => once < wall_clock(at, self.tz().as_ref()),
+            None => false,
+        }
+    }
+}
+
+/// The wall clock of the zone at the instant, `None` is the server zone.
+pub fn wall_clock(at: &DateTime<Utc>, tz: Option<&Tz>) -> NaiveDateTime {
+    match tz {
+        Some(tz) => at.with_timezone(tz).naive_local(),
+        None => at.with_timezone(&Local).naive_local(),
+    }
+}
+
+/// Parse the validity window `YYYY-MM-DD ~ YYYY-MM-DD`, either side can be empty.
+/// A single date is the start date.
+pub fn parse_date_window(input: &str) -> Result<(Option<NaiveDate>, Option<NaiveDate>)> {
+    let (start, end) = input.split_once('~').unwrap_or((input, ""));
+    let parse = |date: &str| -> Result<Option<NaiveDate>> {
+        let date = date.trim();
+        if date.is_empty() {
+            return Ok(None);
+        }
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map(Some)
+            .map_err(|_| anyhow!("Bad date '{date}', use YYYY-MM-DD"))
+    };
+
+    let (start, end) = (parse(start)?, parse(end)?);
+    if let (Some(start), Some(end)) = (start, end) {
+        if start > end {
+            bail!("The start date is after the end date");
+        }
+    }
+    Ok((start, end))
+}
+
+/// The longest auto-delete delay, Telegram bots can not delete messages older than 48 hours.
+pub const DELETE_AFTER_MAX_MINUTES: i64 = 48 * 60;
+
+/// Auto-delete of the sent messages of the push, so only the latest one stays in the group.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Cleanup {
+    pub delete_previous: bool,     // delete the previous one when the next is sent
+    pub delete_after: Option<i64>, // minutes after the send
+}
+
+impl Cleanup {
+    /// Parse `previous`, a delay (`30m`, `2h`, `1d`), both (`previous 2h`) or `off`.
+    pub fn parse(input: &str) -> Result<Cleanup> {
+        let mut cleanup = Cleanup::default();
+        for word in input.split_whitespace() {
+            let word = word.to_lowercase();
+            match word.as_str() {
+                "off" | "keep" => return Ok(Cleanup::default()),
+                "previous" | "prev" => cleanup.delete_previous = true,
+                _ => {
+                    let minutes = parse_minutes(&word)?;
+                    if !(1..=DELETE_AFTER_MAX_MINUTES).contains(&minutes) {
+                        bail!("The delay must be between 1m and 48h");
+                    }
+                    cleanup.delete_after = Some(minutes);
+                }
+            }
+        }
+        if cleanup == Cleanup::default() {
+            bail!("Empty option");
+        }
+        Ok(cleanup)
+    }
+
+    pub fn is_off(&self) -> bool {
+        *self == Cleanup::default()
+    }
+}
+
+impl std::fmt::Display for Cleanup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if self.delete_previous {
+            parts.push("previous".to_string());
+        }
+        if let Some(minutes) = self.delete_after {
+            parts.push(minutes_str(minutes));
+        }
+        if parts.is_empty() {
+            write!(f, "off")
+        } else {
+            write!(f, "{}", parts.join(" "))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, sqlx::Type)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+pub enum PinMode {
+    #[default]
+    Off,
+    Silent, // pin without notifying the members
+    Notify,
+}
+
+/// Pin the sent messages of the push, and unpin them later.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Pin {
+    pub mode: PinMode,
+    pub unpin_previous: bool,     // unpin the previous one when the next is sent
+    pub unpin_after: Option<i64>, // minutes after the send
+}
+
+impl Pin {
+    /// Parse `silent` or `notify`, then optionally `next` and a delay (`12h`), or `off`.
+    /// e.g. `silent next`, `notify 1d`.
+    pub fn parse(input: &str) -> Result<Pin> {
+        let mut pin = Pin::default();
+        for word in input.split_whitespace() {
+            let word = word.to_lowercase();
+            match word.as_str() {
+                "off" => return Ok(Pin::default()),
+                "silent" => pin.mode = PinMode::Silent,
+                "notify" => pin.mode = PinMode::Notify,
+                "next" => pin.unpin_previous = true,
+                _ => {
+                    let minutes = parse_minutes(&word)?;
+                    if minutes < 1 {
+                        bail!("The delay must be at least 1m");
+                    }
+                    pin.unpin_after = Some(minutes);
+                }
+            }
+        }
+        if pin.mode == PinMode::Off {
+            bail!("Start with silent or notify, or send off");
+        }
+        Ok(pin)
+    }
+
+    pub fn is_off(&self) -> bool {
+        self.mode == PinMode::Off
+    }
+}
+
+impl std::fmt::Display for Pin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = vec![match self.mode {
+            PinMode::Off => return write!(f, "off"),
+            PinMode::Silent => "silent".to_string(),
+            PinMode::Notify => "notify".to_string(),
+        }];
+        if self.unpin_previous {
+            parts.push("next".to_string());
+        }
+        if let Some(minutes) = self.unpin_after {
+            parts.push(minutes_str(minutes));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// Minutes for display, e.g. `90m`, `2h`, `1d`
+pub fn minutes_str(minutes: i64) -> String {
+    match minutes {
+        m if m % (24 * 60) == 0 => format!("{}d", m / (24 * 60)),
+        m if m % 60 == 0 => format!("{}h", m / 60),
+        m => format!("{m}m"),
+    }
+}
+
+/// `30m`, `2h` or `1d` in minutes
+pub fn parse_minutes(word: &str) -> Result<i64> {
+    let unit = match word.chars().last() {
+        Some('m') => 1,
+        Some('h') => 60,
+        Some('d') => 24 * 60,
+        _ => bail!("Bad option '{word}'"),
+    };
+    let number: i64 = word[..word.len() - 1]
+        .parse()
+        .map_err(|_| anyhow!("Bad delay '{word}', e.g. 30m, 2h, 1d"))?;
+    number.checked_mul(unit).ok_or_else(|| anyhow!("The delay '{word}' is too long"))
 }
 
 pub struct PollingMsgDb {
     conn: Db,
 }
 
+/// Delete the group msg by the msg id, in the transaction deleting the msg.
+/// When deleting the msg call this function first for the data to keep clean.
+pub async fn delete_by_msg_id(tx: &mut SqliteConnection, msg_id: i64) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM hv_polling_msg WHERE hv_msg_id = ?")
+        .bind(msg_id)
+        .execute(tx)
+        .await?
+        .rows_affected();
+    Ok(result > 0)
+}
+
 pub fn new(conn: Db) -> PollingMsgDb {
     PollingMsgDb { conn }
 }
 
 impl PollingMsgDb {
     
+    /// Add the group push, `send_time` is the schedule rule, see [`Schedule`].
     pub async fn add_polling_msg(
         &self,
         msg_id: i64,
         group_id: i64,
         send_time: &str,
     ) -> Result<i64> {
+        let schedule = Schedule::parse(send_time)?;
         let result = sqlx::query(
             "INSERT INTO hv_polling_msg (hv_msg_id, group_id, send_time) VALUES (?, ?, ?)",
         )
         .bind(msg_id)
         .bind(group_id)
-        .bind(send_time)
+        .bind(schedule.as_str())
+        .execute(&self.conn.sqlite_pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Add the push for all the groups having the tag, the groups tagged later get it too.
+    pub async fn add_tag_polling_msg(&self, msg_id: i64, tag: &str, send_time: &str) -> Result<i64> {
+        let schedule = Schedule::parse(send_time)?;
+        let result = sqlx::query(
+            "INSERT INTO hv_polling_msg (hv_msg_id, group_id, send_time, tag) VALUES (?, 0, ?, ?)",
+        )
+        .bind(msg_id)
+        .bind(schedule.as_str())
+        .bind(tag)
         .execute(&self.conn.sqlite_pool)
         .await?;
 
         Ok(result.last_insert_rowid())
     }
 
+    /// Set the validity window of the push, `None` is unbounded.
+    pub async fn set_date_window(
+        &self,
+        id: i64,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> Result<bool> {
+        let result = sqlx::query("UPDATE hv_polling_msg SET start_date = ?, end_date = ? WHERE id = ?")
+            .bind(start_date)
+            .bind(end_date)
+            .bind(id)
+            .execute(&self.conn.sqlite_pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn set_cleanup(&self, id: i64, cleanup: Cleanup) -> Result<bool> {
+        let result = sqlx::query("UPDATE hv_polling_msg SET delete_previous = ?, delete_after = ? WHERE id = ?")
+            .bind(cleanup.delete_previous)
+            .bind(cleanup.delete_after)
+            .bind(id)
+            .execute(&self.conn.sqlite_pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn set_pin(&self, id: i64, pin: Pin) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE hv_polling_msg SET pin_mode = ?, unpin_previous = ?, unpin_after = ? WHERE id = ?",
+        )
+        .bind(pin.mode)
+        .bind(pin.unpin_previous)
+        .bind(pin.unpin_after)
+        .bind(id)
+        .execute(&self.conn.sqlite_pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Make the push send the messages of the pool instead of its message.
+    pub async fn set_pool(&self, id: i64, pool_id: i64) -> Result<bool> {
+        let result = sqlx::query("UPDATE hv_polling_msg SET pool_id = ?, hv_msg_id = 0 WHERE id = ?")
+            .bind(pool_id)
+            .bind(id)
+            .execute(&self.conn.sqlite_pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Disable (or enable again) the push, a disabled push is kept but not sent.
+    pub async fn set_disabled(&self, id: i64, disabled: bool) -> Result<bool> {
+        let result = sqlx::query("UPDATE hv_polling_msg SET disabled = ? WHERE id = ?")
+            .bind(disabled)
+            .bind(id)
+            .execute(&self.conn.sqlite_pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get the push by the id, the group fields are empty for a tag push.
+    pub async fn get_by_id(&self, id: i64) -> Result<Option<PollingMsg>> {
+        let msg = sqlx::query(
+            r#"
+    SELECT pm.id, pm.hv_msg_id, COALESCE(g.group_id, '') AS group_id, COALESCE(g.group_name, '') AS group_name,
+           pm.send_time, pm.tag, pm.disabled, pm.delete_previous, pm.delete_after, pm.pin_mode, pm.unpin_previous, pm.unpin_after, pm.pool_id, COALESCE(g.time_zone, '') AS time_zone,
+           pm.start_date, pm.end_date, g.polling_resumed_at, pm.created_at,
+           COALESCE(m.msg_text, '') AS msg_text, COALESCE(m.msg_type, 1) AS msg_type,
+           COALESCE(m.msg_title, p.pool_name) AS msg_title, COALESCE(m.media_kind, 'text') AS media_kind,
+           COALESCE(m.file_id, '') AS file_id, COALESCE(m.buttons, '') AS buttons
+    FROM hv_polling_msg pm
+    LEFT JOIN hv_msg m ON pm.hv_msg_id = m.id
+    LEFT JOIN hv_msg_pool p ON pm.pool_id = p.id
+    LEFT JOIN hv_group g ON pm.tag = '' AND pm.group_id = g.id
+    WHERE pm.id = ? AND (m.id IS NOT NULL OR p.id IS NOT NULL)
+"#,
+        )
+        .bind(id)
+        .map(polling_msg_from_row)
+        .fetch_optional(&self.conn.sqlite_pool)
+        .await?;
+
+        Ok(msg)
+    }
+
     // 删除群组的所有关联消息
     pub async fn delete_group_msgs(&self, group_id: &str) -> Result<bool> {
         let result = sqlx::query("DELETE FROM hv_polling_msg WHERE group_id = ?")
@@ -52,14 +413,13 @@ impl PollingMsgDb {
     }
     
   
-    /// Delete the group msg by the msg id
-    /// When deleting the msg call this function first for the data to keep clean.
-    pub async fn delete_by_msg_id(&self, msg_id: i64) -> Result<bool> {
-        let result = sqlx::query("DELETE FROM hv_polling_msg WHERE msg_id = ?")
-        .bind(msg_id)
-            .execute(&self.conn.sqlite_pool)
-            .await?.rows_affected();
-        Ok(result > 0)
+    /// How many group pushes use the msg
+    pub async fn count_by_msg_id(&self, msg_id: i64) -> Result<i64> {
+        let count = sqlx::query_scalar("SELECT count(*) FROM hv_polling_msg WHERE hv_msg_id = ?")
+            .bind(msg_id)
+            .fetch_one(&self.conn.sqlite_pool)
+            .await?;
+        Ok(count)
     }
 
     // 删除单条关联消息
@@ -72,30 +432,13 @@ impl PollingMsgDb {
         Ok(result.rows_affected() > 0)
     }
 
-    // 获取群组的所有关联消息
+    // 获取群组的所有关联消息 (不含标签推送)
     pub async fn get_group_msgs(&self, group_id: i64) -> Result<Vec<PollingMsg>> {
-        let msgs = sqlx::query(
-            r#"
-            SELECT pm.id, pm.hv_msg_id, g.group_id, pm.send_time, 
-                   m.msg_text, m.msg_type, m.msg_title
-            FROM hv_polling_msg pm
-            JOIN hv_msg m ON pm.hv_msg_id = m.id
-            JOIN hv_group g ON pm.group_id = g.id
-            WHERE g.id = ?
-            "#,
-        )
-        .bind(group_id)
-        .map(|row: sqlx::sqlite::SqliteRow| PollingMsg {
-            id: row.get("id"),
-            hv_msg_id: row.get("hv_msg_id"),
-            group_id: row.get("group_id"),
-            send_time: row.get("send_time"),
-            msg_text: row.get("msg_text"),
-            msg_title: row.get("msg_title"),
-            msg_type: row.get("msg_type"),
-        })
-        .fetch_all(&self.conn.sqlite_pool)
-        .await?;
+        let msgs = sqlx::query(&format!("{POLLING_MSG_SELECT} WHERE g.id = ? AND pm.tag = ''"))
+            .bind(group_id)
+            .map(polling_msg_from_row)
+            .fetch_all(&self.conn.sqlite_pool)
+            .await?;
 
         Ok(msgs)
     }
@@ -106,60 +449,170 @@ impl PollingMsgDb {
         group_id: &str,
         send_time: &str,
     ) -> Result<Option<PollingMsg>> {
-        let msg = sqlx::query(
-            r#"
-            SELECT pm.id, pm.hv_msg_id, g.group_id, pm.send_time,
-                   m.msg_text, m.msg_type,m.msg_title
-            FROM hv_polling_msg pm
-            JOIN hv_msg m ON pm.hv_msg_id = m.id
-        JOIN hv_group g ON pm.group_id = g.id
-            WHERE pm.group_id = ? AND pm.send_time = ?
-            "#,
-        )
+        let msg = sqlx::query(&format!(
+            "{POLLING_MSG_SELECT} WHERE pm.group_id = ? AND pm.send_time = ?"
+        ))
         .bind(group_id)
         .bind(send_time)
-        .map(|row: sqlx::sqlite::SqliteRow| PollingMsg {
-            id: row.get("id"),
-            hv_msg_id: row.get("hv_msg_id"),
-            group_id: row.get("group_id"),
-            send_time: row.get("send_time"),
-            msg_text: row.get("msg_text"),
-            msg_title: row.get("msg_title"),
-            msg_type: row.get("msg_type"),
-        })
+        .map(polling_msg_from_row)
         .fetch_optional(&self.conn.sqlite_pool)
         .await?;
 
         Ok(msg)
     }
 
-    pub async fn get_polling_msgs_by_time(
-        &self,
-        send_time: &str,
-    ) -> Result<Vec<PollingMsg>> {
+    /// The pushes of the tag, one per push, the group fields are empty.
+    pub async fn get_tag_msgs(&self, tag: &str) -> Result<Vec<PollingMsg>> {
         let msgs = sqlx::query(
             r#"
-        SELECT pm.id, pm.hv_msg_id, g.group_id, pm.send_time,
-               m.msg_text, m.msg_type, m.msg_title
-        FROM hv_polling_msg pm
-        JOIN hv_msg m ON pm.hv_msg_id = m.id
-        JOIN hv_group g ON pm.group_id = g.id
-        WHERE pm.send_time = ?
-        "#,
+    SELECT pm.id, pm.hv_msg_id, '' AS group_id, '' AS group_name, pm.send_time, pm.tag, pm.disabled, pm.delete_previous, pm.delete_after, pm.pin_mode, pm.unpin_previous, pm.unpin_after, pm.pool_id, '' AS time_zone,
+           pm.start_date, pm.end_date, NULL AS polling_resumed_at, pm.created_at,
+           COALESCE(m.msg_text, '') AS msg_text, COALESCE(m.msg_type, 1) AS msg_type,
+           COALESCE(m.msg_title, p.pool_name) AS msg_title, COALESCE(m.media_kind, 'text') AS media_kind,
+           COALESCE(m.file_id, '') AS file_id, COALESCE(m.buttons, '') AS buttons
+    FROM hv_polling_msg pm
+    LEFT JOIN hv_msg m ON pm.hv_msg_id = m.id
+    LEFT JOIN hv_msg_pool p ON pm.pool_id = p.id
+    WHERE pm.tag = ? AND (m.id IS NOT NULL OR p.id IS NOT NULL)
+    ORDER BY pm.id
+"#,
         )
-            .bind(send_time)
-            .map(|row: sqlx::sqlite::SqliteRow| PollingMsg {
-                id: row.get("id"),
-                hv_msg_id: row.get("hv_msg_id"),
-                group_id: row.get("group_id"),
-                send_time: row.get("send_time"),
-                msg_text: row.get("msg_text"),
-                msg_title: row.get("msg_title"),
-                msg_type: row.get("msg_type"),
-            })
+        .bind(tag)
+        .map(polling_msg_from_row)
+        .fetch_all(&self.conn.sqlite_pool)
+        .await?;
+
+        Ok(msgs)
+    }
+
+    /// Get every due (scheduled instant, push) between `from` and `to` (both included),
+    /// used by the scheduler to catch up the minutes it missed.
+    /// A tag push is returned once per group having the tag now.
+    ///
+    /// Muted groups and disabled pushes are skipped, so are the slots before the push was created,
+    /// before the group resumed pushing or out of the push validity window.
+    pub async fn get_polling_slots(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+    ) -> Result<Vec<(DateTime<Utc>, PollingMsg)>> {
+        let query = format!("{POLLING_MSG_SELECT} WHERE g.mute_polling = FALSE AND pm.disabled = FALSE");
+        let msgs = sqlx::query(&query)
+            .map(polling_msg_from_row)
             .fetch_all(&self.conn.sqlite_pool)
             .await?;
 
-        Ok(msgs)
+        let mut slots = Vec::new();
+        for msg in msgs {
+            let schedule = match msg.schedule() {
+                Ok(schedule) => schedule,
+                Err(e) => {
+                    log::warn!("Push {} has an invalid schedule {:?}: {}", msg.id, msg.send_time, e);
+                    continue;
+                }
+            };
+            let tz = msg.tz();
+
+            let mut at = *from;
+            while at <= *to {
+                let is_due = match &tz {
+                    Some(tz) => schedule.is_due(&at, tz),
+                    None => schedule.is_due(&at, &Local),
+                };
+                if is_due
+                    && at >= msg.created_at
+                    && msg.polling_resumed_at.is_none_or(|resumed_at| at >= resumed_at)
+                    && msg.in_window(wall_clock(&at, tz.as_ref()).date())
+                {
+                    slots.push((at, msg.clone()));
+                }
+                at += Duration::minutes(1);
+            }
+        }
+        slots.sort_by_key(|(at, _)| *at);
+
+        Ok(slots)
+    }
+
+    /// The Telegram chat ids of the groups the push goes to, the muted groups get nothing.
+    pub async fn target_groups(&self, id: i64) -> Result<Vec<String>> {
+        let groups = sqlx::query(&format!("{POLLING_MSG_SELECT} WHERE pm.id = ? AND g.mute_polling = FALSE"))
+            .bind(id)
+            .map(|row: SqliteRow| row.get("group_id"))
+            .fetch_all(&self.conn.sqlite_pool)
+            .await?;
+
+        Ok(groups)
+    }
+
+    /// Delete the one-shot pushes whose date time is before the instant, return the count.
+    /// The ones still being sent (pending in the send queue) or deferred by the quiet hours are kept until settled.
+    pub async fn delete_expired(&self, before: &DateTime<Utc>) -> Result<u64> {
+        let msgs = sqlx::query(POLLING_MSG_SELECT)
+            .map(polling_msg_from_row)
+            .fetch_all(&self.conn.sqlite_pool)
+            .await?;
+        let unsettled: Vec<i64> = sqlx::query_scalar(
+            "SELECT DISTINCT polling_msg_id FROM hv_push_delivery WHERE status = ? OR defer_until IS NOT NULL",
+        )
+        .bind(DeliveryStatus::Pending)
+        .fetch_all(&self.conn.sqlite_pool)
+        .await?;
+
+        let mut count = 0;
+        for msg in msgs.iter().filter(|msg| msg.is_expired(before) && !unsettled.contains(&msg.id)) {
+            if self.delete_polling_msg_by_id(msg.id).await? {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+const POLLING_MSG_SELECT: &str = r#"
+    SELECT pm.id, pm.hv_msg_id, g.group_id, g.group_name, pm.send_time, pm.tag, pm.disabled, pm.delete_previous, pm.delete_after, pm.pin_mode, pm.unpin_previous, pm.unpin_after, pm.pool_id, g.time_zone,
+           pm.start_date, pm.end_date, g.polling_resumed_at, pm.created_at,
+           COALESCE(m.msg_text, '') AS msg_text, COALESCE(m.msg_type, 1) AS msg_type,
+           COALESCE(m.msg_title, p.pool_name) AS msg_title, COALESCE(m.media_kind, 'text') AS media_kind,
+           COALESCE(m.file_id, '') AS file_id, COALESCE(m.buttons, '') AS buttons
+    FROM hv_polling_msg pm
+    LEFT JOIN hv_msg m ON pm.hv_msg_id = m.id
+    LEFT JOIN hv_msg_pool p ON pm.pool_id = p.id
+    JOIN hv_group g ON ((pm.tag = '' AND pm.group_id = g.id)
+        OR (pm.tag <> '' AND g.id IN (SELECT hv_group_id FROM hv_group_tag WHERE tag = pm.tag)))
+        -- the message (or the pool) of the push exists
+        AND (m.id IS NOT NULL OR p.id IS NOT NULL)
+"#;
+
+fn polling_msg_from_row(row: SqliteRow) -> PollingMsg {
+    PollingMsg {
+        id: row.get("id"),
+        hv_msg_id: row.get("hv_msg_id"),
+        group_id: row.get("group_id"),
+        group_name: row.get("group_name"),
+        send_time: row.get("send_time"),
+        tag: row.get("tag"),
+        disabled: row.get("disabled"),
+        pool_id: row.get("pool_id"),
+        cleanup: Cleanup {
+            delete_previous: row.get("delete_previous"),
+            delete_after: row.get("delete_after"),
+        },
+        pin: Pin {
+            mode: row.get("pin_mode"),
+            unpin_previous: row.get("unpin_previous"),
+            unpin_after: row.get("unpin_after"),
+        },
+        msg_text: row.get("msg_text"),
+        msg_title: row.get("msg_title"),
+        msg_type: row.get("msg_type"),
+        media_kind: row.get("media_kind"),
+        file_id: row.get("file_id"),
+        buttons: row.get("buttons"),
+        time_zone: row.get("time_zone"),
+        start_date: row.get("start_date"),
+        end_date: row.get("end_date"),
+        polling_resumed_at: row.get("polling_resumed_at"),
+        created_at: row.get("created_at"),
     }
 }
@@ -0,0 +1,240 @@
+//! # Schedule
+//! Recurrence rules for the group push.
+//!
+//! Three forms are accepted:
+//! - `HH:MM` – every day at that time (the original format).
+//! - `YYYY-MM-DD HH:MM` – one-shot, only once at that date time.
+//! - A five field cron expression `minute hour day-of-month month day-of-week`,
+//!   each field supports `*`, lists `1,3`, ranges `1-5`, steps `*/3` and names (`MON`, `JAN`).
+//!
+//! e.g.
+//! - `30 9 * * 1-5` weekdays at 09:30
+//! - `0 8 * * MON` every Monday at 08:00
+//! - `0 10 1 * *` the 1st of every month at 10:00
+//! - `0 */3 * * *` every 3 hours
+//!
+//! The rule is wall clock time, [`Schedule::is_due`] evaluates it in the group's time zone.
+use anyhow::{anyhow, bail, Result};
+use chrono::{
+    DateTime, Datelike, Duration, LocalResult, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc,
+};
+use std::fmt;
+use std::str::FromStr;
+
+const WEEKDAY_NAMES: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+const MONTH_NAMES: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    expr: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    // `*` in day-of-month / day-of-week, needed for the cron "either day matches" rule.
+    any_day: bool,
+    any_weekday: bool,
+    // One-shot date time, the bit fields are unused.
+    once: Option<NaiveDateTime>,
+}
+
+impl Schedule {
+    /// Parse the rule, `HH:MM`, `YYYY-MM-DD HH:MM` or cron expression.
+    pub fn parse(expr: &str) -> Result<Schedule> {
+        let expr = expr.split_whitespace().collect::<Vec<&str>>().join(" ");
+        if expr.is_empty() {
+            bail!("Schedule is empty");
+        }
+
+        if let Ok(at) = NaiveDateTime::parse_from_str(&expr, "%Y-%m-%d %H:%M") {
+            return Ok(Schedule {
+                minutes: 0,
+                hours: 0,
+                days: 0,
+                months: 0,
+                weekdays: 0,
+                any_day: false,
+                any_weekday: false,
+                once: Some(at),
+                expr,
+            });
+        }
+
+        if let Ok(time) = NaiveTime::parse_from_str(&expr, "%H:%M") {
+            return Ok(Schedule {
+                minutes: 1 << time.minute(),
+                hours: 1 << time.hour(),
+                days: range_bits(1, 31),
+                months: range_bits(1, 12),
+                weekdays: range_bits(0, 6),
+                any_day: true,
+                any_weekday: true,
+                once: None,
+                expr,
+            });
+        }
+
+        let fields: Vec<&str> = expr.split(' ').collect();
+        if fields.len() != 5 {
+            bail!("Expected HH:MM or 5 cron fields (minute hour day month weekday), got: {expr}");
+        }
+
+        let minutes = parse_field(fields[0], 0, 59, &[]).map_err(|e| anyhow!("minute: {e}"))?;
+        let hours = parse_field(fields[1], 0, 23, &[]).map_err(|e| anyhow!("hour: {e}"))?;
+        let days = parse_field(fields[2], 1, 31, &[]).map_err(|e| anyhow!("day: {e}"))?;
+        let months =
+            parse_field(fields[3], 1, 12, &MONTH_NAMES).map_err(|e| anyhow!("month: {e}"))?;
+        let mut weekdays =
+            parse_field(fields[4], 0, 7, &WEEKDAY_NAMES).map_err(|e| anyhow!("weekday: {e}"))?;
+        // 7 is Sunday too
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays & !(1 << 7)) | 1;
+        }
+
+        Ok(Schedule {
+            minutes,
+            hours,
+            days,
+            months,
+            weekdays,
+            any_day: fields[2] == "*",
+            any_weekday: fields[4] == "*",
+            once: None,
+            expr,
+        })
+    }
+
+    /// Is the rule due at this (minute precision) wall clock time?
+    pub fn matches(&self, at: &NaiveDateTime) -> bool {
+        if let Some(once) = self.once {
+            return once.date() == at.date()
+                && once.hour() == at.hour()
+                && once.minute() == at.minute();
+        }
+
+        if !has_bit(self.minutes, at.minute())
+            || !has_bit(self.hours, at.hour())
+            || !has_bit(self.months, at.month())
+        {
+            return false;
+        }
+
+        let day_ok = has_bit(self.days, at.day());
+        let weekday_ok = has_bit(self.weekdays, at.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday_ok,
+            (false, true) => day_ok,
+            // Both restricted: cron fires when either matches.
+            (false, false) => day_ok || weekday_ok,
+        }
+    }
+
+    /// Is the rule due at the instant (minute precision), using the wall clock of the zone.
+    ///
+    /// Daylight saving time:
+    /// - Skipped wall clock times (clocks forward) fire at the first minute after the gap.
+    /// - Repeated wall clock times (clocks back) fire only on the first pass.
+    pub fn is_due<Tz: TimeZone>(&self, at: &DateTime<Utc>, tz: &Tz) -> bool {
+        let local = at.with_timezone(tz).naive_local();
+
+        if self.matches(&local) {
+            return match tz.from_local_datetime(&local) {
+                LocalResult::Ambiguous(earliest, _) => earliest == *at,
+                _ => true,
+            };
+        }
+
+        // Clocks jumped forward, the skipped minutes are due now.
+        let previous = (*at - Duration::minutes(1)).with_timezone(tz).naive_local();
+        let mut skipped = previous + Duration::minutes(1);
+        while skipped < local {
+            if self.matches(&skipped) {
+                return true;
+            }
+            skipped += Duration::minutes(1);
+        }
+        false
+    }
+
+    /// The date time of the one-shot rule, `None` for the recurring rules.
+    pub fn one_shot_at(&self) -> Option<NaiveDateTime> {
+        self.once
+    }
+
+    pub fn is_one_shot(&self) -> bool {
+        self.once.is_some()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.expr
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Schedule::parse(s)
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expr)
+    }
+}
+
+fn has_bit(bits: u64, n: u32) -> bool {
+    bits & (1 << n) != 0
+}
+
+fn range_bits(from: u32, to: u32) -> u64 {
+    (from..=to).fold(0, |bits, n| bits | 1 << n)
+}
+
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64> {
+    let mut bits = 0;
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| anyhow!("bad step '{step}'"))?;
+                if step == 0 {
+                    bail!("step must be greater than 0");
+                }
+                (range, step)
+            }
+            None => (item, 1),
+        };
+
+        let (from, to) = if range == "*" {
+            (min, max)
+        } else if let Some((from, to)) = range.split_once('-') {
+            (parse_value(from, min, names)?, parse_value(to, min, names)?)
+        } else {
+            let value = parse_value(range, min, names)?;
+            // `5/10` means from 5 to the end of the field.
+            (value, if item.contains('/') { max } else { value })
+        };
+
+        if from < min || to > max || from > to {
+            bail!("'{item}' is out of range {min}-{max}");
+        }
+        bits |= (from..=to).step_by(step as usize).fold(0, |bits, n| bits | 1 << n);
+    }
+    Ok(bits)
+}
+
+fn parse_value(value: &str, min: u32, names: &[&str]) -> Result<u32> {
+    if let Ok(n) = value.parse::<u32>() {
+        return Ok(n);
+    }
+    names
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(value))
+        .map(|i| i as u32 + min)
+        .ok_or_else(|| anyhow!("bad value '{value}'"))
+}
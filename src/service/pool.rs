@@ -0,0 +1,238 @@
+//! # Message pool
+//! A pool of stored messages behind one push, every slot sends the next one ("tip of the day").
+//!
+//! The cursor is per (pool, group): `hv_pool_cursor` keeps the messages sent to the group
+//! in the current round, a new round starts when every message of the pool was sent.
+use crate::service::Db;
+use anyhow::{bail, Result};
+use chrono::Utc;
+use sqlx::Row;
+
+/// Longest pool name
+pub const POOL_NAME_MAX_LEN: usize = 32;
+
+pub struct PoolDb {
+    conn: Db,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, sqlx::Type)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+pub enum PoolOrder {
+    Sequential, // in the order of the pool
+    Random,     // random, no repeat within a round
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct Pool {
+    pub id: i64,
+    pub pool_name: String,
+    pub order_mode: PoolOrder,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct PoolItem {
+    pub hv_msg_id: i64,
+    pub position: i64,
+    pub msg_title: String, // 从 hv_msg 表关联获取
+}
+
+pub fn new(conn: Db) -> PoolDb {
+    PoolDb { conn }
+}
+
+impl PoolDb {
+    pub async fn all(&self) -> Result<Vec<Pool>> {
+        let pools = sqlx::query_as("SELECT * FROM hv_msg_pool ORDER BY id")
+            .fetch_all(&self.conn.sqlite_pool)
+            .await?;
+        Ok(pools)
+    }
+
+    pub async fn get(&self, id: i64) -> Result<Option<Pool>> {
+        let pool = sqlx::query_as("SELECT * FROM hv_msg_pool WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.conn.sqlite_pool)
+            .await?;
+        Ok(pool)
+    }
+
+    /// Add the new pool, return id.
+    pub async fn add_pool(&self, pool_name: &str) -> Result<i64> {
+        let pool_name = pool_name.trim();
+        if pool_name.is_empty() || pool_name.chars().count() > POOL_NAME_MAX_LEN {
+            bail!("The pool name must be 1 to {POOL_NAME_MAX_LEN} characters");
+        }
+        let result = sqlx::query("INSERT INTO hv_msg_pool (pool_name, order_mode) VALUES (?, ?)")
+            .bind(pool_name)
+            .bind(PoolOrder::Sequential)
+            .execute(&self.conn.sqlite_pool)
+            .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn set_order(&self, id: i64, order_mode: PoolOrder) -> Result<bool> {
+        let result = sqlx::query("UPDATE hv_msg_pool SET order_mode = ? WHERE id = ?")
+            .bind(order_mode)
+            .bind(id)
+            .execute(&self.conn.sqlite_pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete the pool with its messages list, cursors and pushes.
+    pub async fn delete_pool(&self, id: i64) -> Result<bool> {
+        let mut tx = self.conn.sqlite_pool.begin().await?;
+        for table in ["hv_msg_pool_item", "hv_pool_cursor"] {
+            sqlx::query(&format!("DELETE FROM {table} WHERE pool_id = ?"))
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query("DELETE FROM hv_polling_msg WHERE pool_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        let result = sqlx::query("DELETE FROM hv_msg_pool WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// How many pushes send the pool
+    pub async fn push_count(&self, pool_id: i64) -> Result<i64> {
+        let count = sqlx::query_scalar("SELECT COUNT(*) FROM hv_polling_msg WHERE pool_id = ?")
+            .bind(pool_id)
+            .fetch_one(&self.conn.sqlite_pool)
+            .await?;
+        Ok(count)
+    }
+
+    /// The names of the pools the message is in, deleting the message takes it out of them
+    pub async fn pools_of_msg(&self, msg_id: i64) -> Result<Vec<String>> {
+        let names = sqlx::query_scalar(
+            "SELECT p.pool_name FROM hv_msg_pool p JOIN hv_msg_pool_item i ON i.pool_id = p.id WHERE i.hv_msg_id = ? ORDER BY p.id",
+        )
+        .bind(msg_id)
+        .fetch_all(&self.conn.sqlite_pool)
+        .await?;
+        Ok(names)
+    }
+
+    /// The messages of the pool, in order
+    pub async fn items(&self, pool_id: i64) -> Result<Vec<PoolItem>> {
+        let items = sqlx::query(
+            "SELECT i.hv_msg_id, i.position, m.msg_title FROM hv_msg_pool_item i JOIN hv_msg m ON i.hv_msg_id = m.id WHERE i.pool_id = ? ORDER BY i.position",
+        )
+        .bind(pool_id)
+        .map(|row: sqlx::sqlite::SqliteRow| PoolItem {
+            hv_msg_id: row.get("hv_msg_id"),
+            position: row.get("position"),
+            msg_title: row.get("msg_title"),
+        })
+        .fetch_all(&self.conn.sqlite_pool)
+        .await?;
+        Ok(items)
+    }
+
+    /// Add the message at the end of the pool, or remove it when it is in. Return is it in now.
+    pub async fn toggle_item(&self, pool_id: i64, msg_id: i64) -> Result<bool> {
+        let removed = sqlx::query("DELETE FROM hv_msg_pool_item WHERE pool_id = ? AND hv_msg_id = ?")
+            .bind(pool_id)
+            .bind(msg_id)
+            .execute(&self.conn.sqlite_pool)
+            .await?;
+        if removed.rows_affected() > 0 {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            "INSERT INTO hv_msg_pool_item (pool_id, hv_msg_id, position) SELECT ?, ?, COALESCE(MAX(position), -1) + 1 FROM hv_msg_pool_item WHERE pool_id = ?",
+        )
+        .bind(pool_id)
+        .bind(msg_id)
+        .bind(pool_id)
+        .execute(&self.conn.sqlite_pool)
+        .await?;
+        Ok(true)
+    }
+
+    /// Pick the message of the pool for the next send to the group and move the cursor,
+    /// `None` when the pool is empty.
+    pub async fn next_msg(&self, pool_id: i64, group_id: &str) -> Result<Option<i64>> {
+        let Some(pool) = self.get(pool_id).await? else {
+            return Ok(None);
+        };
+        let items = self.items(pool_id).await?;
+        if items.is_empty() {
+            return Ok(None);
+        }
+
+        let mut tx = self.conn.sqlite_pool.begin().await?;
+        let sent: Vec<(i64, i64)> = sqlx::query_as(
+            "SELECT hv_msg_id, position FROM hv_pool_cursor WHERE pool_id = ? AND group_id = ? ORDER BY id",
+        )
+        .bind(pool_id)
+        .bind(group_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        let last = sent.last().copied();
+        let random: i64 = sqlx::query_scalar("SELECT RANDOM()").fetch_one(&mut *tx).await?;
+
+        let pick = match pool.order_mode {
+            PoolOrder::Sequential => {
+                let last_position = last.map(|(_, position)| position).unwrap_or(-1);
+                items.iter().find(|item| item.position > last_position)
+            }
+            PoolOrder::Random => {
+                let unsent: Vec<&PoolItem> = items
+                    .iter()
+                    .filter(|item| !sent.iter().any(|(msg_id, _)| *msg_id == item.hv_msg_id))
+                    .collect();
+                random_item(&unsent, random)
+            }
+        };
+
+        let pick = match pick {
+            Some(item) => item,
+            // Round over, start again, not with the one just sent.
+            None => {
+                sqlx::query("DELETE FROM hv_pool_cursor WHERE pool_id = ? AND group_id = ?")
+                    .bind(pool_id)
+                    .bind(group_id)
+                    .execute(&mut *tx)
+                    .await?;
+                match pool.order_mode {
+                    PoolOrder::Sequential => &items[0],
+                    PoolOrder::Random => {
+                        let others: Vec<&PoolItem> = items
+                            .iter()
+                            .filter(|item| items.len() == 1 || Some(item.hv_msg_id) != last.map(|(msg_id, _)| msg_id))
+                            .collect();
+                        random_item(&others, random).unwrap_or(&items[0])
+                    }
+                }
+            }
+        };
+
+        sqlx::query("INSERT INTO hv_pool_cursor (pool_id, group_id, hv_msg_id, position) VALUES (?, ?, ?, ?)")
+            .bind(pool_id)
+            .bind(group_id)
+            .bind(pick.hv_msg_id)
+            .bind(pick.position)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(Some(pick.hv_msg_id))
+    }
+}
+
+/// The item at the random number (`RANDOM()` of SQLite)
+fn random_item<'a>(items: &[&'a PoolItem], random: i64) -> Option<&'a PoolItem> {
+    if items.is_empty() {
+        return None;
+    }
+    Some(items[(random.unsigned_abs() % items.len() as u64) as usize])
+}
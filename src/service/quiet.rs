@@ -0,0 +1,178 @@
+//! # Quiet hours
+//! No bot posts at night: the scheduled pushes of the group within its quiet hours are skipped,
+//! or deferred to the end of the window, and the welcome messages can wait until then too.
+//!
+//! The setting is the text in `hv_group.quiet_hours`, e.g. `22:00-07:00 defer welcome`,
+//! in the wall clock of the group time zone. The held welcomes are in `hv_welcome_held`,
+//! so a restart at night does not lose them.
+use crate::service::drip::Member;
+use crate::service::polling_msg::wall_clock;
+use crate::service::Db;
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Duration, NaiveTime, Timelike, Utc};
+use chrono_tz::Tz;
+use sqlx::Row;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuietMode {
+    Skip,  // the push is not sent
+    Defer, // the push is sent at the end of the quiet hours
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime, // may be the next day, e.g. 22:00-07:00
+    pub mode: QuietMode,
+    pub batch_welcome: bool, // the welcomes wait for the end, in one message
+}
+
+/// The welcome held until the quiet hours end
+#[derive(Debug)]
+pub struct HeldWelcome {
+    pub id: i64,
+    pub group_id: String, // Telegram chat id
+    pub user_id: i64,
+    pub first_name: String,
+    pub username: Option<String>,
+    pub send_at: DateTime<Utc>,
+}
+
+impl QuietHours {
+    /// `22:00-07:00 [skip|defer] [welcome]`, the default is skip. `off` (or empty) is `None`.
+    pub fn parse(input: &str) -> Result<Option<QuietHours>> {
+        let input = input.trim().to_lowercase();
+        let mut words = input.split_whitespace();
+        let window = match words.next() {
+            None | Some("off") => return Ok(None),
+            Some(window) => window,
+        };
+        let (start, end) = window
+            .split_once('-')
+            .ok_or_else(|| anyhow!("Bad window '{window}', e.g. 22:00-07:00"))?;
+        let time = |text: &str| {
+            NaiveTime::parse_from_str(text, "%H:%M").map_err(|_| anyhow!("Bad time '{text}', use HH:MM"))
+        };
+        let (start, end) = (time(start)?, time(end)?);
+        if start == end {
+            bail!("The quiet hours can not start and end at the same time");
+        }
+
+        let mut quiet = QuietHours { start, end, mode: QuietMode::Skip, batch_welcome: false };
+        for word in words {
+            match word {
+                "skip" => quiet.mode = QuietMode::Skip,
+                "defer" => quiet.mode = QuietMode::Defer,
+                "welcome" => quiet.batch_welcome = true,
+                _ => bail!("Bad option '{word}'"),
+            }
+        }
+        Ok(Some(quiet))
+    }
+
+    /// Is the wall clock time within the quiet hours? The end is not.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        let time = time.with_second(0).and_then(|t| t.with_nanosecond(0)).unwrap_or(time);
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// Is the instant within the quiet hours of the group?
+    pub fn is_quiet(&self, at: &DateTime<Utc>, tz: Option<&Tz>) -> bool {
+        self.contains(wall_clock(at, tz).time())
+    }
+
+    /// The first minute after the instant out of the quiet hours, the instant itself when not quiet.
+    /// Walked by the minute, so the daylight saving changes need no care.
+    pub fn end_after(&self, at: &DateTime<Utc>, tz: Option<&Tz>) -> DateTime<Utc> {
+        let mut end = at.with_second(0).and_then(|t| t.with_nanosecond(0)).unwrap_or(*at);
+        let limit = end + Duration::days(1);
+        while self.is_quiet(&end, tz) && end < limit {
+            end += Duration::minutes(1);
+        }
+        end
+    }
+}
+
+impl std::fmt::Display for QuietHours {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{} {}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M"),
+            match self.mode {
+                QuietMode::Skip => "skip",
+                QuietMode::Defer => "defer",
+            }
+        )?;
+        if self.batch_welcome {
+            write!(f, " welcome")?;
+        }
+        Ok(())
+    }
+}
+
+pub struct QuietDb {
+    conn: Db,
+}
+
+pub fn new(conn: Db) -> QuietDb {
+    QuietDb { conn }
+}
+
+impl QuietDb {
+    /// Hold the welcome of the member until `send_at`.
+    pub async fn hold_welcome(&self, group_id: &str, member: &Member<'_>, send_at: &DateTime<Utc>) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO hv_welcome_held (group_id, user_id, first_name, username, send_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(group_id)
+        .bind(member.user_id)
+        .bind(member.first_name)
+        .bind(member.username)
+        .bind(send_at)
+        .execute(&self.conn.sqlite_pool)
+        .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    /// The held welcomes due at `now`, by group in the order of the join.
+    pub async fn due_welcomes(&self, now: &DateTime<Utc>) -> Result<Vec<HeldWelcome>> {
+        let held = sqlx::query("SELECT * FROM hv_welcome_held WHERE send_at <= ? ORDER BY group_id, id")
+            .bind(now)
+            .map(|row: sqlx::sqlite::SqliteRow| HeldWelcome {
+                id: row.get("id"),
+                group_id: row.get("group_id"),
+                user_id: row.get("user_id"),
+                first_name: row.get("first_name"),
+                username: row.get("username"),
+                send_at: row.get("send_at"),
+            })
+            .fetch_all(&self.conn.sqlite_pool)
+            .await?;
+        Ok(held)
+    }
+
+    /// Claim the held welcome, only the first caller gets `true` and may send it.
+    pub async fn claim(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM hv_welcome_held WHERE id = ?")
+            .bind(id)
+            .execute(&self.conn.sqlite_pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The member left before the morning, no welcome.
+    pub async fn cancel(&self, group_id: &str, user_id: i64) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM hv_welcome_held WHERE group_id = ? AND user_id = ?")
+            .bind(group_id)
+            .bind(user_id)
+            .execute(&self.conn.sqlite_pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
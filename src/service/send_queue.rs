@@ -0,0 +1,262 @@
+//! # Send queue
+//! All the pushes go out through here, sent by a worker task within the Telegram rate limits.
+//!
+//! `RetryAfter` is waited out, the transient failures (network, Telegram server errors)
+//! are tried again with exponential backoff, the permanent ones (bot kicked, chat not found...)
+//! are returned at once for the delivery log.
+use crate::service::msg::media;
+use crate::service::msg::template::TemplateContext;
+use crate::service::msg::MsgContent;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use teloxide::prelude::*;
+use tokio::sync::{mpsc, oneshot};
+use teloxide::{ApiError, RequestError};
+
+/// Telegram sends about 30 messages per second in total
+pub const GLOBAL_PER_SECOND: usize = 30;
+/// and 20 messages per minute to the same group.
+pub const CHAT_PER_MINUTE: usize = 20;
+/// Attempts of one send, the first one included
+pub const MAX_ATTEMPTS: u32 = 5;
+/// The longest wait between two attempts
+pub const BACKOFF_CAP: Duration = Duration::from_secs(60);
+
+const BACKOFF_BASE: Duration = Duration::from_secs(2);
+/// A chat lane without jobs for this long exits, the next job of the chat starts a new one.
+const LANE_IDLE: Duration = Duration::from_secs(300);
+
+/// What to do with the failed send
+#[derive(Debug, PartialEq)]
+pub enum Failure {
+    RetryAfter(Duration), // flood control, try again after the wait
+    Transient,            // try again with backoff
+    Permanent,            // the same request fails again
+}
+
+pub fn classify(error: &RequestError) -> Failure {
+    match error {
+        RequestError::RetryAfter(seconds) => Failure::RetryAfter(seconds.duration()),
+        RequestError::Network(_) | RequestError::Io(_) | RequestError::InvalidJson { .. } => {
+            Failure::Transient
+        }
+        // Telegram server errors (5xx) have no variant of their own.
+        RequestError::Api(ApiError::Unknown(text))
+            if ["Internal Server Error", "Bad Gateway", "Service Unavailable", "Gateway Timeout"]
+                .iter()
+                .any(|server_error| text.contains(server_error)) =>
+        {
+            Failure::Transient
+        }
+        RequestError::Api(_) | RequestError::MigrateToChatId(_) => Failure::Permanent,
+    }
+}
+
+/// The wait before the attempt (2, 3, ...): 2s, 4s, 8s ... up to [`BACKOFF_CAP`].
+pub fn backoff(attempt: u32) -> Duration {
+    let exp = attempt.saturating_sub(2).min(16);
+    (BACKOFF_BASE * 2u32.pow(exp)).min(BACKOFF_CAP)
+}
+
+/// The send failed for good
+#[derive(Debug)]
+pub struct SendError {
+    pub error: RequestError,
+    pub permanent: bool, // false: gave up after the retries
+    pub attempts: u32,
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.attempts > 1 {
+            write!(f, "{} (after {} attempts)", self.error, self.attempts)
+        } else {
+            write!(f, "{}", self.error)
+        }
+    }
+}
+
+impl std::error::Error for SendError {}
+
+/// Sliding windows of the recent sends, in total and per chat.
+#[derive(Default)]
+pub struct RateLimiter {
+    global: VecDeque<Instant>,
+    chats: HashMap<i64, VecDeque<Instant>>,
+}
+
+impl RateLimiter {
+    /// Take the turn of the chat at `now`, `Duration::ZERO` when taken,
+    /// otherwise how long to wait before asking again.
+    pub fn reserve(&mut self, chat_id: i64, now: Instant) -> Duration {
+        let second = Duration::from_secs(1);
+        let minute = Duration::from_secs(60);
+        expire(&mut self.global, now, second);
+        let chat = self.chats.entry(chat_id).or_default();
+        expire(chat, now, minute);
+
+        let mut wait = Duration::ZERO;
+        if self.global.len() >= GLOBAL_PER_SECOND {
+            wait = wait.max(self.global[0] + second - now);
+        }
+        if chat.len() >= CHAT_PER_MINUTE {
+            wait = wait.max(chat[0] + minute - now);
+        }
+        if wait.is_zero() {
+            self.global.push_back(now);
+            chat.push_back(now);
+        }
+        // The idle chats are forgotten.
+        self.chats.retain(|_, sent| !sent.is_empty());
+        wait
+    }
+}
+
+fn expire(sent: &mut VecDeque<Instant>, now: Instant, window: Duration) {
+    while sent.front().is_some_and(|at| *at + window <= now) {
+        sent.pop_front();
+    }
+}
+
+/// The queued send, resolves once the worker has sent it or given up.
+pub struct Sending(oneshot::Receiver<Result<Message, SendError>>);
+
+impl Sending {
+    pub async fn result(self) -> Result<Message, SendError> {
+        self.0.await.unwrap_or_else(|_| {
+            Err(SendError {
+                error: RequestError::Io(std::io::Error::other("The send queue stopped")),
+                permanent: false,
+                attempts: 0,
+            })
+        })
+    }
+}
+
+struct Job {
+    chat_id: ChatId,
+    content: MsgContent,
+    ctx: TemplateContext,
+    reply: oneshot::Sender<Result<Message, SendError>>,
+}
+
+/// The handle of the send worker, callers enqueue and go on, the waits happen in the worker.
+///
+/// Every chat has a lane of its own, sending its messages in order, so a chat waiting out
+/// its per-minute limit or a retry does not hold up the other chats. The global limit is
+/// shared by the lanes.
+pub struct SendQueue {
+    jobs: mpsc::UnboundedSender<Job>,
+}
+
+impl SendQueue {
+    /// Start the worker, it runs as long as the runtime does.
+    pub fn start(bot: Bot) -> SendQueue {
+        let (jobs, rx) = mpsc::unbounded_channel();
+        tokio::spawn(dispatch(bot, rx));
+        SendQueue { jobs }
+    }
+
+    /// Queue the content for the chat and return at once, dropping the [`Sending`] does not cancel it.
+    pub fn enqueue(&self, chat_id: ChatId, content: MsgContent, ctx: TemplateContext) -> Sending {
+        let (reply, result) = oneshot::channel();
+        // A stopped worker drops the job, the `Sending` tells so.
+        let _ = self.jobs.send(Job { chat_id, content, ctx, reply });
+        Sending(result)
+    }
+}
+
+/// Route the jobs to the lanes of their chats, the idle lanes are forgotten.
+async fn dispatch(bot: Bot, mut jobs: mpsc::UnboundedReceiver<Job>) {
+    let limiter = Arc::new(Mutex::new(RateLimiter::default()));
+    let mut lanes: HashMap<i64, mpsc::UnboundedSender<Job>> = HashMap::new();
+    while let Some(job) = jobs.recv().await {
+        let chat_id = job.chat_id.0;
+        let job = match lanes.get(&chat_id) {
+            Some(lane) => match lane.send(job) {
+                Ok(()) => continue,
+                // The lane went idle and closed, a new one takes over.
+                Err(mpsc::error::SendError(job)) => job,
+            },
+            None => job,
+        };
+        let (lane, rx) = mpsc::unbounded_channel();
+        let _ = lane.send(job);
+        tokio::spawn(run_lane(bot.clone(), limiter.clone(), rx));
+        lanes.insert(chat_id, lane);
+        lanes.retain(|_, lane| !lane.is_closed());
+    }
+}
+
+/// Send the jobs of one chat in order, exit after [`LANE_IDLE`] without jobs.
+async fn run_lane(bot: Bot, limiter: Arc<Mutex<RateLimiter>>, mut jobs: mpsc::UnboundedReceiver<Job>) {
+    loop {
+        let job = match tokio::time::timeout(LANE_IDLE, jobs.recv()).await {
+            Ok(Some(job)) => job,
+            Ok(None) => return,
+            Err(_) => {
+                // No new jobs after the close, the ones already queued are still sent.
+                jobs.close();
+                while let Some(job) = jobs.recv().await {
+                    send_job(&bot, &limiter, job).await;
+                }
+                return;
+            }
+        };
+        send_job(&bot, &limiter, job).await;
+    }
+}
+
+async fn send_job(bot: &Bot, limiter: &Mutex<RateLimiter>, job: Job) {
+    let result = send_content(bot, limiter, job.chat_id, &job.content, &job.ctx).await;
+    // Nobody may wait for the result.
+    let _ = job.reply.send(result);
+}
+
+/// Wait until the chat may get the next message.
+async fn wait_turn(limiter: &Mutex<RateLimiter>, chat_id: ChatId) {
+    loop {
+        let wait = limiter.lock().unwrap().reserve(chat_id.0, Instant::now());
+        if wait.is_zero() {
+            return;
+        }
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Send the content within the rate limits, retrying `RetryAfter` and the transient failures.
+async fn send_content(
+    bot: &Bot,
+    limiter: &Mutex<RateLimiter>,
+    chat_id: ChatId,
+    content: &MsgContent,
+    ctx: &TemplateContext,
+) -> Result<Message, SendError> {
+    let mut attempts = 0;
+    loop {
+        wait_turn(limiter, chat_id).await;
+        attempts += 1;
+        let error = match media::send_content(bot, chat_id, content, ctx).await {
+            Ok(sent) => return Ok(sent),
+            Err(error) => error,
+        };
+
+        let wait = match classify(&error) {
+            Failure::Permanent => {
+                return Err(SendError { error, permanent: true, attempts });
+            }
+            _ if attempts >= MAX_ATTEMPTS => {
+                return Err(SendError { error, permanent: false, attempts });
+            }
+            Failure::RetryAfter(wait) => wait,
+            Failure::Transient => backoff(attempts + 1),
+        };
+        log::warn!(
+            "Send to chat {} failed (attempt {}): {}, retry in {:?}",
+            chat_id, attempts, error, wait
+        );
+        tokio::time::sleep(wait).await;
+    }
+}
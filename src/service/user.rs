@@ -12,6 +12,7 @@ pub struct Admin {
     pub user_id: String,
     pub user_name: String,
     pub is_admin: bool,
+    pub notify_failures: bool, // 推送失败时私信通知
     pub created_at: chrono::DateTime<Utc>, // 或其他时间类型
 }
 
@@ -81,19 +82,35 @@ impl User {
     }
 
     pub async fn all_admins(&self) -> Vec<Admin> {
-        sqlx::query("SELECT id, user_id, user_name, is_admin, created_at FROM hv_user")
-            .map(|row: sqlx::sqlite::SqliteRow| Admin {
-                id: row.get("id"),
-                user_id: row.get("user_id"),
-                user_name: row.get("user_name"),
-                is_admin: row.get("is_admin"),
-                created_at: row.get("created_at"),
-            })
+        sqlx::query("SELECT id, user_id, user_name, is_admin, notify_failures, created_at FROM hv_user")
+            .map(admin_from_row)
             .fetch_all(&self.conn.sqlite_pool)
             .await
             .unwrap()
     }
 
+    /// The admins to notify when a push fails
+    pub async fn failure_recipients(&self) -> Vec<Admin> {
+        sqlx::query("SELECT id, user_id, user_name, is_admin, notify_failures, created_at FROM hv_user WHERE is_admin = 1 AND notify_failures = 1")
+            .map(admin_from_row)
+            .fetch_all(&self.conn.sqlite_pool)
+            .await
+            .unwrap()
+    }
+
+    /// Turn the failure notifications of the admin on or off, return the new value.
+    /// `fetch_all` steps the statement to the end, so the update is committed before it returns.
+    pub async fn toggle_notify_failures(&self, user_id: &str) -> Option<bool> {
+        let values: Vec<bool> = sqlx::query_scalar(
+            "UPDATE hv_user SET notify_failures = NOT notify_failures WHERE user_id = ? RETURNING notify_failures",
+        )
+        .bind(user_id)
+        .fetch_all(&self.conn.sqlite_pool)
+        .await
+        .unwrap();
+        values.into_iter().next()
+    }
+
     pub async fn cancel_admin(&self, user_id: &str) -> bool {
         let result = sqlx::query(
             "UPDATE hv_user set is_admin = false WHERE user_id = ? and is_admin = true",
@@ -130,9 +147,20 @@ impl User {
                 r.rows_affected() > 0
             },
             Err(e) => {
-                eprintln!("set_admin_name error: {}", e.to_string());
+                eprintln!("set_admin_name error: {}", e);
                 false
             }
         }
     }
 }
+
+fn admin_from_row(row: sqlx::sqlite::SqliteRow) -> Admin {
+    Admin {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        user_name: row.get("user_name"),
+        is_admin: row.get("is_admin"),
+        notify_failures: row.get("notify_failures"),
+        created_at: row.get("created_at"),
+    }
+}
@@ -0,0 +1,36 @@
+use hivin_bot::service::msg::buttons;
+
+#[test]
+fn parse_test() {
+    let rows = buttons::parse(
+        "Register - https://example.com/register | Rules - https://t.me/example/2\n\nSign - up - https://example.com",
+    )
+    .unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].len(), 2);
+    assert_eq!(rows[0][1].label, "Rules");
+    assert_eq!(rows[0][1].url.as_str(), "https://t.me/example/2");
+    // The label may have " - "
+    assert_eq!(rows[1][0].label, "Sign - up");
+
+    assert!(buttons::parse("").unwrap().is_empty());
+    assert!(buttons::parse("Register").is_err());
+    assert!(buttons::parse(" - https://example.com").is_err());
+    assert!(buttons::parse("Register - example.com").is_err());
+    assert!(buttons::parse("Register - ftp://example.com").is_err());
+}
+
+#[test]
+fn keyboard_test() {
+    assert_eq!(
+        buttons::normalize("  A -  https://a.com |B - https://b.com/x  ").unwrap(),
+        "A - https://a.com/ | B - https://b.com/x"
+    );
+
+    let keyboard = buttons::keyboard("A - https://a.com | B - https://b.com\nC - tg://resolve?domain=example").unwrap();
+    assert_eq!(keyboard.inline_keyboard.len(), 2);
+    assert_eq!(keyboard.inline_keyboard[0].len(), 2);
+    assert_eq!(keyboard.inline_keyboard[1][0].text, "C");
+
+    assert!(buttons::keyboard("").is_none());
+}
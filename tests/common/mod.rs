@@ -1,6 +1,18 @@
 use hivin_bot::service;
 use hivin_bot::service::Db;
 
+#[allow(dead_code)]
 pub async fn get_db() -> Db {
     service::new("test.sqlite").await
-}
\ No newline at end of file
+}
+
+/// A fresh database of its own, for the tests that must not race with the fixed ids used above.
+/// The file of the previous run is removed, so every run starts empty.
+#[allow(dead_code)]
+pub async fn get_own_db(name: &str) -> Db {
+    let path = format!("test_{name}.sqlite");
+    for file in [path.clone(), format!("{path}-wal"), format!("{path}-shm")] {
+        let _ = std::fs::remove_file(file);
+    }
+    service::new(&path).await
+}
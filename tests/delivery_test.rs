@@ -0,0 +1,135 @@
+use chrono::{Duration, Utc};
+use hivin_bot::service::delivery::{self, DeliveryDb, DeliveryStatus};
+
+mod common;
+
+const PUSH_ID: i64 = 3;
+const GROUP_ID: &str = "-1003";
+
+async fn get_sev() -> DeliveryDb {
+    let db = common::get_db().await;
+    delivery::new(db)
+}
+
+#[tokio::test]
+async fn claim_once_test() {
+    let sev = get_sev().await;
+    let scheduled_at = Utc::now();
+
+    assert!(sev.claim(PUSH_ID, GROUP_ID, &scheduled_at).await.unwrap());
+    assert!(!sev.claim(PUSH_ID, GROUP_ID, &scheduled_at).await.unwrap(), "slot should be claimed once");
+
+    assert!(sev.mark_sent(PUSH_ID, GROUP_ID, &scheduled_at, 42).await.unwrap());
+    let delivery = sev.get(PUSH_ID, GROUP_ID, &scheduled_at).await.unwrap().unwrap();
+    assert_eq!(delivery.status, DeliveryStatus::Sent);
+    assert_eq!(delivery.message_id, Some(42));
+}
+
+#[tokio::test]
+async fn claim_per_group_test() {
+    let sev = get_sev().await;
+    let scheduled_at = Utc::now();
+
+    // A tag push: the same slot for every group of the tag
+    assert!(sev.claim(PUSH_ID, "-1004", &scheduled_at).await.unwrap());
+    assert!(sev.claim(PUSH_ID, "-1005", &scheduled_at).await.unwrap());
+    assert!(!sev.claim(PUSH_ID, "-1005", &scheduled_at).await.unwrap());
+
+    assert!(sev.mark_failed(PUSH_ID, "-1004", &scheduled_at, "Forbidden: bot was kicked").await.unwrap());
+    let failed = sev.get(PUSH_ID, "-1004", &scheduled_at).await.unwrap().unwrap();
+    let pending = sev.get(PUSH_ID, "-1005", &scheduled_at).await.unwrap().unwrap();
+    assert_eq!(failed.status, DeliveryStatus::Failed);
+    assert_eq!(failed.error, "Forbidden: bot was kicked");
+    assert!(failed.attempted_at.is_some());
+    assert_eq!(pending.status, DeliveryStatus::Pending);
+    assert!(pending.attempted_at.is_none());
+}
+
+#[tokio::test]
+async fn recent_by_group_test() {
+    let sev = delivery::new(common::get_own_db("delivery_report").await);
+    let group_id = "-1006";
+    let first = Utc::now() - Duration::minutes(10);
+    let second = Utc::now();
+
+    sev.claim(PUSH_ID, group_id, &first).await.unwrap();
+    sev.mark_failed(PUSH_ID, group_id, &first, "Bad Request: chat not found").await.unwrap();
+    sev.claim(PUSH_ID, group_id, &second).await.unwrap();
+    sev.mark_sent(PUSH_ID, group_id, &second, 7).await.unwrap();
+    sev.claim(PUSH_ID, "-1007", &second).await.unwrap();
+
+    let recent = sev.recent_by_group(group_id, 20).await.unwrap();
+    assert!(recent.len() >= 2);
+    assert!(recent.iter().all(|d| d.group_id == group_id), "only the group");
+    // Newest first
+    assert_eq!(recent[0].message_id, Some(7));
+    assert_eq!(recent[1].status, DeliveryStatus::Failed);
+    assert_eq!(recent[1].error, "Bad Request: chat not found");
+
+    assert_eq!(sev.recent_by_group(group_id, 1).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn auto_delete_test() {
+    let sev = delivery::new(common::get_own_db("auto_delete").await);
+    let push_id = 7;
+    let group_id = "-1007";
+    let first = Utc::now() - Duration::hours(2);
+    let second = first + Duration::hours(1);
+    let third = second + Duration::hours(1);
+    for (at, message_id) in [(first, 1), (second, 2)] {
+        sev.claim(push_id, group_id, &at).await.unwrap();
+        sev.mark_sent(push_id, group_id, &at, message_id).await.unwrap();
+    }
+    sev.claim(push_id, group_id, &third).await.unwrap();
+    sev.mark_failed(push_id, group_id, &third, "Bad Request").await.unwrap();
+
+    // The sent ones before the slot, not the failed one
+    let previous = sev.previous_sent(push_id, group_id, &third).await.unwrap();
+    assert_eq!(previous.iter().map(|d| d.message_id).collect::<Vec<_>>(), vec![Some(1), Some(2)]);
+    assert!(sev.mark_deleted(previous[0].id).await.unwrap());
+    let previous = sev.previous_sent(push_id, group_id, &third).await.unwrap();
+    assert_eq!(previous.len(), 1);
+
+    // TTL
+    let delete_at = Utc::now() + Duration::minutes(30);
+    assert!(sev.schedule_delete(push_id, group_id, &second, &delete_at).await.unwrap());
+    let is_due = |deliveries: Vec<delivery::Delivery>| deliveries.iter().any(|d| d.message_id == Some(2));
+    assert!(!is_due(sev.due_deletes(&Utc::now()).await.unwrap()));
+    assert!(is_due(sev.due_deletes(&(delete_at + Duration::minutes(1))).await.unwrap()));
+
+    sev.mark_deleted(previous[0].id).await.unwrap();
+    assert!(!is_due(sev.due_deletes(&(delete_at + Duration::minutes(1))).await.unwrap()));
+    assert!(sev.get(push_id, group_id, &second).await.unwrap().unwrap().deleted_at.is_some());
+}
+
+#[tokio::test]
+async fn auto_unpin_test() {
+    let sev = delivery::new(common::get_own_db("auto_unpin").await);
+    let push_id = 8;
+    let group_id = "-1008";
+    let first = Utc::now() - Duration::hours(1);
+    let second = first + Duration::hours(1);
+    for (at, message_id) in [(first, 1), (second, 2)] {
+        sev.claim(push_id, group_id, &at).await.unwrap();
+        sev.mark_sent(push_id, group_id, &at, message_id).await.unwrap();
+    }
+    let unpin_at = Utc::now() + Duration::hours(12);
+    assert!(sev.mark_pinned(push_id, group_id, &first, None).await.unwrap());
+    assert!(sev.mark_pinned(push_id, group_id, &second, Some(&unpin_at)).await.unwrap());
+
+    // Unpin when the next is sent
+    let previous = sev.previous_pinned(push_id, group_id, &second).await.unwrap();
+    assert_eq!(previous.iter().map(|d| d.message_id).collect::<Vec<_>>(), vec![Some(1)]);
+    assert!(sev.mark_unpinned(previous[0].id).await.unwrap());
+    assert!(sev.previous_pinned(push_id, group_id, &second).await.unwrap().is_empty());
+
+    // Unpin after the time, a deleted message is not pinned
+    let is_due = |deliveries: Vec<delivery::Delivery>| deliveries.iter().any(|d| d.message_id == Some(2));
+    assert!(!is_due(sev.due_unpins(&Utc::now()).await.unwrap()));
+    assert!(is_due(sev.due_unpins(&(unpin_at + Duration::minutes(1))).await.unwrap()));
+    let pinned = sev.get(push_id, group_id, &second).await.unwrap().unwrap();
+    assert!(pinned.pinned);
+    sev.mark_deleted(pinned.id).await.unwrap();
+    assert!(!is_due(sev.due_unpins(&(unpin_at + Duration::minutes(1))).await.unwrap()));
+}
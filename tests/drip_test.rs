@@ -0,0 +1,69 @@
+use chrono::{Duration, Utc};
+use hivin_bot::service::drip::{delay_str, parse_delay, Member};
+use hivin_bot::service::msg::MsgType;
+use hivin_bot::service::{drip, group, msg};
+
+mod common;
+
+#[tokio::test]
+async fn drip_sequence_test() {
+    let db = common::get_own_db("drip_sequence").await;
+    let sev = drip::new(db.clone());
+    let group_ser = group::new(db.clone());
+    let msg_ser = msg::new(db.clone());
+    let group_db_id = group_ser.add_group("-5001", "Drip").await.unwrap();
+    let rules = msg_ser.add_msg(MsgType::Polling, "Rules", "rules").await;
+    let faq = msg_ser.add_msg(MsgType::Polling, "FAQ", "faq").await;
+
+    sev.add_step(group_db_id, 24 * 60, faq).await.unwrap();
+    let rules_step = sev.add_step(group_db_id, 60, rules).await.unwrap();
+    assert!(sev.add_step(group_db_id, -1, rules).await.is_err());
+    let steps = sev.steps(group_db_id).await.unwrap();
+    assert_eq!(steps.iter().map(|s| s.hv_msg_id).collect::<Vec<_>>(), vec![rules, faq]);
+    assert_eq!(sev.count_by_msg_id(rules).await.unwrap(), 1);
+
+    // Queued from the join, the joining again starts over
+    let joined_at = Utc::now() - Duration::minutes(90);
+    let alice = Member { user_id: 11, first_name: "Alice", username: Some("alice") };
+    assert_eq!(sev.enqueue(group_db_id, "-5001", &alice, &joined_at).await.unwrap(), 2);
+    assert_eq!(sev.enqueue(group_db_id, "-5001", &alice, &joined_at).await.unwrap(), 2);
+    let bob = Member { user_id: 12, first_name: "Bob", username: None };
+    sev.enqueue(group_db_id, "-5001", &bob, &Utc::now()).await.unwrap();
+    assert_eq!(sev.pending_count("-5001").await.unwrap(), 4);
+
+    // Only the rules of Alice are due, sent once
+    let due = sev.due(&Utc::now()).await.unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!((due[0].user_id, due[0].hv_msg_id, due[0].group_name.as_str()), (11, rules, "Drip"));
+    assert_eq!(due[0].username.as_deref(), Some("alice"));
+    assert!(sev.claim(due[0].id).await.unwrap());
+    assert!(!sev.claim(due[0].id).await.unwrap());
+
+    // Bob left, Alice still waits for the FAQ
+    assert_eq!(sev.cancel("-5001", 12).await.unwrap(), 2);
+    let tomorrow = Utc::now() + Duration::days(1);
+    let due = sev.due(&tomorrow).await.unwrap();
+    assert_eq!(due.iter().map(|d| (d.user_id, d.hv_msg_id)).collect::<Vec<_>>(), vec![(11, faq)]);
+
+    // The deleted step is not sent, the deleted group has no steps
+    sev.enqueue(group_db_id, "-5001", &bob, &Utc::now()).await.unwrap();
+    assert!(sev.delete_step(rules_step).await.unwrap());
+    assert_eq!(sev.pending_count("-5001").await.unwrap(), 2);
+    assert_eq!(sev.count_by_msg_id(rules).await.unwrap(), 0);
+    assert!(group_ser.delete_group("-5001").await.unwrap());
+    assert_eq!(sev.pending_count("-5001").await.unwrap(), 0);
+    assert!(sev.steps(group_db_id).await.unwrap().is_empty());
+}
+
+#[test]
+fn drip_delay_test() {
+    assert_eq!(parse_delay("now").unwrap(), 0);
+    assert_eq!(parse_delay(" 1H ").unwrap(), 60);
+    assert_eq!(parse_delay("1d").unwrap(), 24 * 60);
+    assert!(parse_delay("").is_err());
+    assert!(parse_delay("-5m").is_err());
+    assert!(parse_delay("31d").is_err());
+    assert!(parse_delay("soon").is_err());
+    assert_eq!(delay_str(0), "now");
+    assert_eq!(delay_str(90), "after 90m");
+}
@@ -0,0 +1,44 @@
+use hivin_bot::service::msg::entities::to_html;
+use teloxide::types::MessageEntity;
+
+#[test]
+fn to_html_test() {
+    // Plain text is escaped
+    assert_eq!(to_html("a < b & c", &[]), "a &lt; b &amp; c");
+
+    // Nested: bold "Hello world", italic "world"
+    assert_eq!(
+        to_html("Hello world!", &[MessageEntity::bold(0, 11), MessageEntity::italic(6, 5)]),
+        "<b>Hello <i>world</i></b>!"
+    );
+
+    // UTF-16 offsets: the emoji is 2 code units
+    assert_eq!(
+        to_html("👋 {group_name}", &[MessageEntity::bold(3, 12)]),
+        "👋 <b>{group_name}</b>"
+    );
+
+    let url = "https://example.com/?a=1&b=2".parse().unwrap();
+    assert_eq!(
+        to_html("Rules here", &[MessageEntity::text_link(url, 0, 5)]),
+        "<a href=\"https://example.com/?a=1&amp;b=2\">Rules</a> here"
+    );
+
+    assert_eq!(
+        to_html("fn main", &[MessageEntity::pre(Some("rust".to_string()), 0, 7)]),
+        "<pre><code class=\"language-rust\">fn main</code></pre>"
+    );
+    assert_eq!(
+        to_html("🎉", &[MessageEntity::custom_emoji("5368324170671202286".to_string(), 0, 2)]),
+        "<tg-emoji emoji-id=\"5368324170671202286\">🎉</tg-emoji>"
+    );
+}
+
+#[test]
+fn overlap_test() {
+    // Bold "abc", italic "bcd": the italic is closed and opened again
+    assert_eq!(
+        to_html("abcde", &[MessageEntity::bold(0, 3), MessageEntity::italic(1, 3)]),
+        "<b>a<i>bc</i></b><i>d</i>e"
+    );
+}
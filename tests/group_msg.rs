@@ -1,5 +1,7 @@
-use hivin_bot::service::polling_msg;
-use hivin_bot::service::polling_msg::PollingMsgDb;
+use hivin_bot::service::{delivery, group, msg, polling_msg};
+use hivin_bot::service::msg::MsgType;
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use hivin_bot::service::polling_msg::{Cleanup, Pin, PinMode, PollingMsgDb, Schedule};
 mod common;
 
 const MSG_ID: u32 = 3;
@@ -24,4 +26,261 @@ async fn get_group_msg_test() {
     let data = sev.get_polling_msg(GROUP_ID, "08:30").await.unwrap();
     assert!(data.is_some());
     println!("group id is {:?}", data.unwrap());
-}
\ No newline at end of file
+}
+#[test]
+fn schedule_parse_test() {
+    assert!(Schedule::parse("08:30").is_ok());
+    assert!(Schedule::parse("30 9 * * 1-5").is_ok());
+    assert!(Schedule::parse("0 8 * * MON").is_ok());
+    assert!(Schedule::parse("0 */3 * * *").is_ok());
+
+    assert!(Schedule::parse("").is_err());
+    assert!(Schedule::parse("25:00").is_err());
+    assert!(Schedule::parse("60 * * * *").is_err());
+    assert!(Schedule::parse("0 8 * *").is_err());
+    assert!(Schedule::parse("0 8 * * FUN").is_err());
+    assert!(Schedule::parse("*/0 * * * *").is_err());
+}
+
+#[test]
+fn schedule_matches_test() {
+    let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+
+    let daily = Schedule::parse("08:30").unwrap();
+    assert!(daily.matches(&at("2026-11-01 08:30")));
+    assert!(!daily.matches(&at("2026-11-01 08:31")));
+
+    // 2026-11-02 is a Monday
+    let weekdays = Schedule::parse("30 9 * * 1-5").unwrap();
+    assert!(weekdays.matches(&at("2026-11-02 09:30")));
+    assert!(!weekdays.matches(&at("2026-11-01 09:30")));
+
+    let sunday = Schedule::parse("0 8 * * 7").unwrap();
+    assert!(sunday.matches(&at("2026-11-01 08:00")));
+
+    let first_of_month = Schedule::parse("0 10 1 * *").unwrap();
+    assert!(first_of_month.matches(&at("2026-11-01 10:00")));
+    assert!(!first_of_month.matches(&at("2026-11-02 10:00")));
+
+    let every_3_hours = Schedule::parse("0 */3 * * *").unwrap();
+    assert!(every_3_hours.matches(&at("2026-11-02 15:00")));
+    assert!(!every_3_hours.matches(&at("2026-11-02 16:00")));
+
+    // Day of month and weekday both set, either one matches.
+    let either = Schedule::parse("0 8 15 * MON").unwrap();
+    assert!(either.matches(&at("2026-11-02 08:00")));
+    assert!(either.matches(&at("2026-11-15 08:00")));
+    assert!(!either.matches(&at("2026-11-03 08:00")));
+}
+
+#[test]
+fn schedule_time_zone_test() {
+    let utc = |s: &str| {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M")
+            .unwrap()
+            .and_utc()
+    };
+    let berlin = chrono_tz::Europe::Berlin;
+    let shanghai = chrono_tz::Asia::Shanghai;
+
+    let daily = Schedule::parse("08:30").unwrap();
+    assert!(daily.is_due(&utc("2026-11-02 00:30"), &shanghai));
+    assert!(!daily.is_due(&utc("2026-11-02 08:30"), &shanghai));
+
+    // Clocks forward 02:00 -> 03:00, the skipped 02:30 fires at 03:00.
+    let skipped = Schedule::parse("02:30").unwrap();
+    assert!(skipped.is_due(&utc("2026-03-29 01:00"), &berlin));
+    assert!(!skipped.is_due(&utc("2026-03-29 01:30"), &berlin));
+
+    // Clocks back 03:00 -> 02:00, the repeated 02:30 fires once.
+    assert!(skipped.is_due(&utc("2026-10-25 00:30"), &berlin));
+    assert!(!skipped.is_due(&utc("2026-10-25 01:30"), &berlin));
+}
+
+#[test]
+fn schedule_one_shot_test() {
+    let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+
+    let once = Schedule::parse("2026-11-01 09:00").unwrap();
+    assert!(once.is_one_shot());
+    assert_eq!(once.one_shot_at(), Some(at("2026-11-01 09:00")));
+    assert!(once.matches(&at("2026-11-01 09:00")));
+    assert!(!once.matches(&at("2026-11-02 09:00")));
+    assert!(!once.matches(&at("2027-11-01 09:00")));
+
+    assert!(!Schedule::parse("09:00").unwrap().is_one_shot());
+    assert!(Schedule::parse("2026-02-30 09:00").is_err());
+}
+
+#[test]
+fn parse_date_window_test() {
+    let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+
+    assert_eq!(
+        polling_msg::parse_date_window("2026-11-01 ~ 2026-11-30").unwrap(),
+        (Some(date("2026-11-01")), Some(date("2026-11-30")))
+    );
+    assert_eq!(
+        polling_msg::parse_date_window("2026-11-01 ~").unwrap(),
+        (Some(date("2026-11-01")), None)
+    );
+    assert_eq!(
+        polling_msg::parse_date_window("~ 2026-11-30").unwrap(),
+        (None, Some(date("2026-11-30")))
+    );
+    assert!(polling_msg::parse_date_window("2026-11-30 ~ 2026-11-01").is_err());
+    assert!(polling_msg::parse_date_window("tomorrow").is_err());
+}
+
+#[tokio::test]
+async fn tag_push_slots_test() {
+    let db = common::get_own_db("tag_push").await;
+    let sev = polling_msg::new(db.clone());
+    let group_ser = group::new(db.clone());
+    let first = group_ser.add_group("-2001", "First").await.unwrap();
+    let second = group_ser.add_group("-2002", "Second").await.unwrap();
+    group_ser.set_tags(first, &["vip".to_string()]).await.unwrap();
+    group_ser.set_tags(second, &[]).await.unwrap();
+
+    let msg_id = msg::new(db.clone()).add_msg(MsgType::Polling, "Hi vip", "vip").await;
+    let push_id = sev.add_tag_polling_msg(msg_id, "vip", "* * * * *").await.unwrap();
+    let next = Utc::now() + Duration::minutes(1);
+
+    let groups_of = |slots: Vec<(chrono::DateTime<Utc>, polling_msg::PollingMsg)>| {
+        slots
+            .into_iter()
+            .filter(|(_, push)| push.id == push_id)
+            .map(|(_, push)| push.group_id)
+            .collect::<Vec<String>>()
+    };
+    assert_eq!(groups_of(sev.get_polling_slots(&next, &next).await.unwrap()), vec!["-2001"]);
+
+    // Tagged later, the future sends include it.
+    group_ser.set_tags(second, &["vip".to_string()]).await.unwrap();
+    assert_eq!(
+        groups_of(sev.get_polling_slots(&next, &next).await.unwrap()),
+        vec!["-2001", "-2002"]
+    );
+
+    let tag_msgs = sev.get_tag_msgs("vip").await.unwrap();
+    assert!(tag_msgs.iter().any(|push| push.id == push_id && push.tag == "vip"));
+    // Not a push of the group itself
+    assert!(sev.get_group_msgs(first).await.unwrap().iter().all(|push| push.id != push_id));
+
+    sev.delete_polling_msg_by_id(push_id).await.unwrap();
+}
+
+#[tokio::test]
+async fn disabled_push_test() {
+    let db = common::get_own_db("disabled_push").await;
+    let sev = polling_msg::new(db.clone());
+    let group_id = group::new(db.clone()).add_group("-3001", "Disabled").await.unwrap();
+    let msg_id = msg::new(db.clone()).add_msg(MsgType::Polling, "Hi", "disabled").await;
+    let push_id = sev.add_polling_msg(msg_id, group_id, "* * * * *").await.unwrap();
+    let next = Utc::now() + Duration::minutes(1);
+    let is_due = |slots: Vec<(chrono::DateTime<Utc>, polling_msg::PollingMsg)>| {
+        slots.iter().any(|(_, push)| push.id == push_id)
+    };
+    assert!(is_due(sev.get_polling_slots(&next, &next).await.unwrap()));
+
+    assert!(sev.set_disabled(push_id, true).await.unwrap());
+    assert!(!is_due(sev.get_polling_slots(&next, &next).await.unwrap()));
+    // Kept in the group list
+    let push = sev.get_by_id(push_id).await.unwrap().unwrap();
+    assert!(push.disabled);
+    assert_eq!(push.group_id, "-3001");
+
+    assert!(sev.set_disabled(push_id, false).await.unwrap());
+    assert!(is_due(sev.get_polling_slots(&next, &next).await.unwrap()));
+    sev.delete_polling_msg_by_id(push_id).await.unwrap();
+    assert!(!sev.set_disabled(push_id, true).await.unwrap());
+}
+
+#[test]
+fn cleanup_parse_test() {
+    let previous = Cleanup::parse("previous").unwrap();
+    assert!(previous.delete_previous);
+    assert_eq!(previous.delete_after, None);
+    assert_eq!(Cleanup::parse("2h").unwrap().delete_after, Some(120));
+    let both = Cleanup::parse("Previous 1d").unwrap();
+    assert_eq!((both.delete_previous, both.delete_after), (true, Some(1440)));
+    assert_eq!(both.to_string(), "previous 1d");
+    assert_eq!(Cleanup::parse("90m").unwrap().to_string(), "90m");
+    assert!(Cleanup::parse("off").unwrap().is_off());
+    assert_eq!(Cleanup::default().to_string(), "off");
+
+    assert!(Cleanup::parse("3d").is_err(), "Telegram can not delete after 48h");
+    assert!(Cleanup::parse("0m").is_err());
+    assert!(Cleanup::parse("soon").is_err());
+    assert!(Cleanup::parse("").is_err());
+}
+
+#[test]
+fn pin_parse_test() {
+    let pin = Pin::parse("silent next").unwrap();
+    assert_eq!((pin.mode, pin.unpin_previous, pin.unpin_after), (PinMode::Silent, true, None));
+    assert_eq!(pin.to_string(), "silent next");
+    let pin = Pin::parse("Notify 12h").unwrap();
+    assert_eq!((pin.mode, pin.unpin_previous, pin.unpin_after), (PinMode::Notify, false, Some(720)));
+    assert_eq!(pin.to_string(), "notify 12h");
+    assert!(Pin::parse("off").unwrap().is_off());
+    assert_eq!(Pin::default().to_string(), "off");
+
+    assert!(Pin::parse("next 1d").is_err(), "pin mode is required");
+    assert!(Pin::parse("silent 0h").is_err());
+    assert!(Pin::parse("loud").is_err());
+}
+
+#[tokio::test]
+async fn one_shot_settled_test() {
+    let db = common::get_own_db("one_shot_settled").await;
+    let sev = polling_msg::new(db.clone());
+    let ledger = delivery::new(db.clone());
+    let group_ser = group::new(db.clone());
+    for (group_id, name) in [("-4001", "First"), ("-4002", "Second"), ("-4003", "Muted")] {
+        let id = group_ser.add_group(group_id, name).await.unwrap();
+        group_ser.set_tags(id, &["news".to_string()]).await.unwrap();
+    }
+    group_ser.set_mute_polling("-4003", true).await.unwrap();
+    let msg_id = msg::new(db.clone()).add_msg(MsgType::Polling, "Hi", "once").await;
+    let push_id = sev.add_tag_polling_msg(msg_id, "news", "2026-11-01 09:00").await.unwrap();
+
+    // The muted group gets nothing, it does not hold the push
+    let targets = sev.target_groups(push_id).await.unwrap();
+    assert_eq!(targets, vec!["-4001", "-4002"]);
+
+    // The second group failed before the claim, or is still being sent
+    let at = Utc::now();
+    ledger.claim(push_id, "-4001", &at).await.unwrap();
+    ledger.mark_sent(push_id, "-4001", &at, 1).await.unwrap();
+    assert!(!ledger.is_settled(push_id, &targets).await.unwrap());
+    ledger.claim(push_id, "-4002", &at).await.unwrap();
+    assert!(!ledger.is_settled(push_id, &targets).await.unwrap());
+
+    // Deferred is not final, skipped is
+    ledger.defer(push_id, "-4002", &at, &(at + Duration::hours(1))).await.unwrap();
+    assert!(!ledger.is_settled(push_id, &targets).await.unwrap());
+    let deferred = ledger.get(push_id, "-4002", &at).await.unwrap().unwrap();
+    ledger.take_deferred(deferred.id).await.unwrap();
+    ledger.mark_skipped(push_id, "-4002", &at, "Paused").await.unwrap();
+    assert!(ledger.is_settled(push_id, &targets).await.unwrap());
+}
+
+#[tokio::test]
+async fn one_shot_pending_kept_test() {
+    let db = common::get_own_db("one_shot_pending").await;
+    let sev = polling_msg::new(db.clone());
+    let ledger = delivery::new(db.clone());
+    let group_db_id = group::new(db.clone()).add_group("-4011", "Queued").await.unwrap();
+    let msg_id = msg::new(db.clone()).add_msg(MsgType::Polling, "Hi", "queued").await;
+    let push_id = sev.add_polling_msg(msg_id, group_db_id, "2020-01-01 09:00").await.unwrap();
+
+    // The slot waits in the send queue, the expired push keeps its cleanup and pin settings
+    let at = Utc::now() - Duration::minutes(5);
+    ledger.claim(push_id, "-4011", &at).await.unwrap();
+    assert_eq!(sev.delete_expired(&Utc::now()).await.unwrap(), 0);
+    assert!(sev.get_by_id(push_id).await.unwrap().is_some());
+
+    ledger.mark_sent(push_id, "-4011", &at, 1).await.unwrap();
+    assert_eq!(sev.delete_expired(&Utc::now()).await.unwrap(), 1);
+}
@@ -1,5 +1,6 @@
-use hivin_bot::service::group;
 use hivin_bot::service::group::Group;
+use hivin_bot::service::msg::{MsgContent, MsgType};
+use hivin_bot::service::{group, msg, polling_msg};
 
 mod common;
 
@@ -22,7 +23,7 @@ async fn add_test() {
 async fn all_test() {
     let sev = get_sev().await;
     let list = sev.all().await;
-    assert!(list.len() > 0, "list should is empty");
+    assert!(!list.is_empty(), "list should is empty");
     for item in list {
         println!("item {:?}", item);
     }
@@ -31,11 +32,90 @@ async fn all_test() {
 #[tokio::test]
 async fn set_mute_test() {
     let sev = get_sev().await;
-    let _ = sev.set_mute_polling(GROUP_ID, false).await.unwrap();
-    let _ = sev.set_mute_welcome(GROUP_ID, false).await.unwrap();
-    
-    let is_mute_polling = sev.should_do_polling(GROUP_ID).await.unwrap();
-    let is_mute_welcome = sev.should_do_welcome(GROUP_ID).await.unwrap();
-    assert_eq!(is_mute_polling, false, "set_mute_polling should not be false, got {:?}", is_mute_polling);
-    assert_eq!(is_mute_welcome, false,  "set_mute_welcome should not be false, got {:?}", is_mute_welcome);
-}
\ No newline at end of file
+    sev.add_group(GROUP_ID, GROUP_NAME).await.unwrap();
+
+    sev.set_mute_polling(GROUP_ID, true).await.unwrap();
+    sev.set_mute_welcome(GROUP_ID, true).await.unwrap();
+    assert!(!sev.should_do_polling(GROUP_ID).await.unwrap(), "muted group should not do polling");
+    assert!(!sev.should_do_welcome(GROUP_ID).await.unwrap(), "muted group should not do welcome");
+
+    sev.set_mute_polling(GROUP_ID, false).await.unwrap();
+    sev.set_mute_welcome(GROUP_ID, false).await.unwrap();
+    assert!(sev.should_do_polling(GROUP_ID).await.unwrap(), "unmuted group should do polling");
+    assert!(sev.should_do_welcome(GROUP_ID).await.unwrap(), "unmuted group should do welcome");
+
+    assert!(!sev.should_do_polling("unknown").await.unwrap(), "unknown group should not do polling");
+}
+
+#[test]
+fn parse_time_zone_test() {
+    assert!(group::parse_time_zone("").unwrap().is_none());
+    assert_eq!(
+        group::parse_time_zone("America/Sao_Paulo").unwrap(),
+        Some(chrono_tz::America::Sao_Paulo)
+    );
+    assert!(group::parse_time_zone("Mars/Olympus").is_err());
+}
+
+#[test]
+fn parse_tags_test() {
+    assert_eq!(group::parse_tags("VIP, #cn  en,vip").unwrap(), vec!["vip", "cn", "en"]);
+    assert_eq!(group::parse_tags("中文 early-bird").unwrap(), vec!["中文", "early-bird"]);
+    assert!(group::parse_tags("").unwrap().is_empty());
+    assert!(group::parse_tags("vip_cn").is_err(), "'_' splits the callback data");
+    assert!(group::parse_tags(&"x".repeat(33)).is_err());
+}
+
+#[tokio::test]
+async fn tags_test() {
+    let sev = group::new(common::get_own_db("tags").await);
+    let vip = sev.add_group("-1001", "Vip group").await.unwrap();
+    let other = sev.add_group("-1002", "Other group").await.unwrap();
+
+    sev.set_tags(vip, &["vip".to_string(), "cn".to_string()]).await.unwrap();
+    sev.set_tags(other, &["cn".to_string()]).await.unwrap();
+    assert_eq!(sev.tags(vip).await.unwrap(), vec!["cn", "vip"]);
+    assert_eq!(
+        sev.all_tags().await.unwrap(),
+        vec![("cn".to_string(), 2), ("vip".to_string(), 1)]
+    );
+    let cn: Vec<i64> = sev.groups_by_tag("cn").await.unwrap().iter().map(|g| g.id).collect();
+    assert_eq!(cn, vec![vip, other]);
+
+    // Replaced, not added
+    sev.set_tags(vip, &["en".to_string()]).await.unwrap();
+    assert_eq!(sev.tags(vip).await.unwrap(), vec!["en"]);
+    assert_eq!(sev.groups_by_tag("vip").await.unwrap().len(), 0);
+
+    // The tags go with the group
+    sev.delete_group("-1002").await.unwrap();
+    assert!(sev.groups_by_tag("cn").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn delete_group_test() {
+    let db = common::get_own_db("delete_group").await;
+    let sev = group::new(db.clone());
+    let polling_ser = polling_msg::new(db.clone());
+    let msg_ser = msg::new(db.clone());
+    let id = sev.add_group("-7001", "Gone").await.unwrap();
+    let other = sev.add_group("-7002", "Stays").await.unwrap();
+    sev.set_tags(id, &["news".to_string()]).await.unwrap();
+    sev.set_tags(other, &["news".to_string()]).await.unwrap();
+    let msg_id = msg_ser.add_msg(MsgType::Polling, "Hi", "hi").await;
+    let push = polling_ser.add_polling_msg(msg_id, id, "08:00").await.unwrap();
+    let tag_push = polling_ser.add_tag_polling_msg(msg_id, "news", "09:00").await.unwrap();
+    assert!(msg_ser.set_group_welcome_msg(id, &MsgContent::text("Welcome to Gone")).await);
+    let welcome_id = msg_id + 1; // the next row of the empty database
+    assert!(msg_ser.edit_msg(welcome_id, "Welcome to Gone!", "1").await);
+    assert_eq!(msg_ser.revisions(welcome_id, 10).await.unwrap().len(), 1);
+
+    assert!(sev.delete_group("-7001").await.unwrap());
+    assert!(sev.tags(id).await.unwrap().is_empty());
+    assert!(polling_ser.get_by_id(push).await.unwrap().is_none());
+    assert!(msg_ser.group_welcome_msg(id).await.is_none());
+    assert!(msg_ser.revisions(welcome_id, 10).await.unwrap().is_empty());
+    // The tag push is still there for the other group
+    assert_eq!(polling_ser.target_groups(tag_push).await.unwrap(), vec!["-7002"]);
+    assert!(!sev.delete_group("-7001").await.unwrap());
+}
@@ -0,0 +1,21 @@
+use hivin_bot::service::msg::markup;
+
+#[test]
+fn validate_test() {
+    assert!(markup::validate("Hello <b>{first_name}</b>, <a href=\"https://t.me/x\">rules</a>").is_ok());
+    assert!(markup::validate("<pre><code class=\"language-rust\">x</code></pre>").is_ok());
+    assert!(markup::validate("1 &lt; 2 &amp;&amp; 3 &gt; 2 &#169; &#x1F600; &quot;").is_ok());
+    assert!(markup::validate("plain text").is_ok());
+
+    // Unsupported, unclosed and mismatched tags
+    assert!(markup::validate("<div>x</div>").is_err());
+    assert!(markup::validate("<b>x").is_err());
+    assert!(markup::validate("<b><i>x</b></i>").is_err());
+    assert!(markup::validate("x</b>").is_err());
+    assert!(markup::validate("<a>x</a>").is_err());
+
+    // Bare characters
+    assert!(markup::validate("1 < 2").is_err());
+    assert!(markup::validate("Tom & Jerry").is_err());
+    assert!(markup::validate("&nbsp;").is_err());
+}
@@ -1,5 +1,5 @@
-use hivin_bot::service::{msg::{self, Msg}};
-use hivin_bot::service::msg::MsgType;
+use hivin_bot::service::{msg::{self, Msg}, polling_msg};
+use hivin_bot::service::msg::{media, MediaKind, MsgContent, MsgType};
 
 mod common;
 
@@ -12,7 +12,7 @@ async fn get_sev() -> Msg {
 pub async fn all_msg() {
     let ser = get_sev().await;
     let msgs = ser.all().await;
-    assert!(msgs.len() > 0, "no found msg data.");
+    assert!(!msgs.is_empty(), "no found msg data.");
     for msg in msgs {
         println!("{:?}", msg);
     }
@@ -34,7 +34,7 @@ pub async fn add_msg() {
 pub async fn remove_msg() {
     let ser = get_sev().await;
     let _ = ser.remove_msg(2).await;
-    let is_ok = ser.all().await.len() == 0;
+    let is_ok = ser.all().await.is_empty();
     assert!(is_ok, "Remove message success");
 }
 
@@ -43,4 +43,111 @@ pub async fn msg_welcome() {
     let ser = get_sev().await;
     let welcome = ser.welcome_msg().await;
     println!("{:?}", welcome);
-}
\ No newline at end of file
+}
+#[tokio::test]
+pub async fn group_welcome_msg() {
+    let ser = msg::new(common::get_own_db("welcome").await);
+    let group_db_id = 22346;
+
+    assert!(ser.set_group_welcome_msg(group_db_id, &MsgContent::text("Hi group")).await);
+    assert_eq!(ser.welcome_msg_for(Some(group_db_id)).await.text, "Hi group");
+
+    assert!(ser.remove_group_welcome_msg(group_db_id).await.unwrap());
+    assert_eq!(ser.welcome_msg_for(Some(group_db_id)).await.text, ser.welcome_msg().await);
+}
+
+#[tokio::test]
+pub async fn media_msg() {
+    let ser = msg::new(common::get_own_db("media").await);
+    let poster = MsgContent {
+        media_kind: MediaKind::Photo,
+        file_id: "AgACAgUAAxkBAAIB".to_string(),
+        text: "Poster of {group_name}".to_string(),
+        album: Vec::new(),
+        buttons: "Register - https://example.com/register".to_string(),
+    };
+
+    let id = ser.add_content_msg(MsgType::Polling, &poster, "poster").await;
+    let saved = ser.all().await.into_iter().find(|m| m.id == id).unwrap();
+    assert_eq!(saved.content(), poster);
+
+    // The welcome message keeps the media too
+    let group_db_id = 22347;
+    assert!(ser.set_group_welcome_msg(group_db_id, &poster).await);
+    assert_eq!(ser.group_welcome_msg(group_db_id).await, Some(poster));
+}
+
+#[tokio::test]
+pub async fn album_msg() {
+    let ser = msg::new(common::get_own_db("album").await);
+    let mut album = MsgContent::default();
+    for (i, kind) in [MediaKind::Photo, MediaKind::Video, MediaKind::Photo].into_iter().enumerate() {
+        let item = MsgContent {
+            media_kind: kind,
+            file_id: format!("file-{i}"),
+            text: if i == 1 { "Gallery".to_string() } else { String::new() },
+            ..Default::default()
+        };
+        media::add_album_item(&mut album, item).unwrap();
+    }
+    assert_eq!(album.media_kind, MediaKind::Album);
+    assert_eq!(album.text, "Gallery");
+
+    // Animations can not be in an album
+    let gif = MsgContent { media_kind: MediaKind::Animation, ..Default::default() };
+    assert!(media::add_album_item(&mut album, gif).is_err());
+
+    let id = ser.add_content_msg(MsgType::Polling, &album, "gallery").await;
+    let items = ser.album(id).await.unwrap();
+    assert_eq!(items, album.album);
+
+    assert!(ser.remove_msg(id).await.is_ok_and(|ok| ok));
+    assert!(ser.album(id).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+pub async fn edit_and_remove_msg() {
+    let db = common::get_own_db("edit_msg").await;
+    let ser = msg::new(db.clone());
+    let pushes = polling_msg::new(db);
+
+    let id = ser.add_msg(MsgType::Polling, "Daily tip", "tip").await;
+    pushes.add_polling_msg(id, 1, "08:30").await.unwrap();
+    pushes.add_polling_msg(id, 2, "0 9 * * MON").await.unwrap();
+    assert_eq!(pushes.count_by_msg_id(id).await.unwrap(), 2);
+
+    assert!(ser.edit_msg(id, "Tip of the day", "1001").await);
+    assert!(ser.edit_title(id, "tips", "1001").await);
+    let saved = ser.get_msg(id).await.unwrap().unwrap();
+    assert_eq!(saved.msg_text, "Tip of the day");
+    assert_eq!(saved.msg_title, "tips");
+
+    // The group pushes go with the message
+    assert!(ser.remove_msg(id).await.unwrap());
+    assert!(ser.get_msg(id).await.unwrap().is_none());
+    assert_eq!(pushes.count_by_msg_id(id).await.unwrap(), 0);
+}
+
+#[tokio::test]
+pub async fn msg_revisions() {
+    let ser = msg::new(common::get_own_db("revision").await);
+    let id = ser.add_msg(MsgType::Polling, "v1", "notice").await;
+    assert!(ser.edit_msg(id, "v2", "1001").await);
+    assert!(ser.edit_msg(id, "v3", "1002").await);
+    // No history for the missing message
+    assert!(!ser.edit_msg(id + 1000, "v1", "1001").await);
+
+    let revisions = ser.revisions(id, 10).await.unwrap();
+    let texts: Vec<&str> = revisions.iter().map(|r| r.msg_text.as_str()).collect();
+    assert_eq!(texts, ["v2", "v1"]);
+    assert_eq!(revisions[0].edited_by, "1002");
+
+    // Restore v1, v3 goes to the history
+    let v1 = revisions[1].id;
+    assert!(ser.restore_revision(v1, "1001").await.unwrap());
+    assert_eq!(ser.get_msg(id).await.unwrap().unwrap().msg_text, "v1");
+    assert_eq!(ser.revisions(id, 1).await.unwrap()[0].msg_text, "v3");
+
+    assert!(ser.remove_msg(id).await.unwrap());
+    assert!(ser.revisions(id, 10).await.unwrap().is_empty());
+}
@@ -0,0 +1,96 @@
+use chrono::{Duration, Utc};
+use hivin_bot::service::msg::MsgType;
+use hivin_bot::service::pool::PoolOrder;
+use hivin_bot::service::{group, msg, polling_msg, pool};
+
+mod common;
+
+#[tokio::test]
+async fn sequential_pool_test() {
+    let db = common::get_own_db("pool_sequential").await;
+    let sev = pool::new(db.clone());
+    let msg_ser = msg::new(db.clone());
+    let pool_id = sev.add_pool("Tips").await.unwrap();
+    let mut tips = Vec::new();
+    for i in 0..3 {
+        let msg_id = msg_ser.add_msg(MsgType::Polling, &format!("Tip {i}"), &format!("tip{i}")).await;
+        assert!(sev.toggle_item(pool_id, msg_id).await.unwrap());
+        tips.push(msg_id);
+    }
+    assert_eq!(sev.items(pool_id).await.unwrap().len(), 3);
+
+    // In order, then a new round, the cursor is per group
+    let mut sent = Vec::new();
+    for _ in 0..4 {
+        sent.push(sev.next_msg(pool_id, "-4001").await.unwrap().unwrap());
+    }
+    assert_eq!(sent, vec![tips[0], tips[1], tips[2], tips[0]]);
+    assert_eq!(sev.next_msg(pool_id, "-4002").await.unwrap(), Some(tips[0]));
+
+    // Removed from the pool, skipped
+    assert!(!sev.toggle_item(pool_id, tips[1]).await.unwrap());
+    assert_eq!(sev.next_msg(pool_id, "-4001").await.unwrap(), Some(tips[2]));
+    assert_eq!(sev.pools_of_msg(tips[0]).await.unwrap(), vec!["Tips"]);
+    assert!(sev.pools_of_msg(tips[1]).await.unwrap().is_empty());
+
+    assert!(sev.add_pool(" ").await.is_err());
+    let empty = sev.add_pool("Empty").await.unwrap();
+    assert_eq!(sev.next_msg(empty, "-4001").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn random_pool_test() {
+    let db = common::get_own_db("pool_random").await;
+    let sev = pool::new(db.clone());
+    let msg_ser = msg::new(db.clone());
+    let pool_id = sev.add_pool("Random").await.unwrap();
+    assert!(sev.set_order(pool_id, PoolOrder::Random).await.unwrap());
+    let mut tips = Vec::new();
+    for i in 0..4 {
+        let msg_id = msg_ser.add_msg(MsgType::Polling, &format!("Tip {i}"), &format!("random{i}")).await;
+        sev.toggle_item(pool_id, msg_id).await.unwrap();
+        tips.push(msg_id);
+    }
+
+    // No repeat within a round, nor at the start of the next one
+    for _ in 0..3 {
+        let mut round = Vec::new();
+        for _ in 0..4 {
+            round.push(sev.next_msg(pool_id, "-4003").await.unwrap().unwrap());
+        }
+        let next = sev.next_msg(pool_id, "-4003").await.unwrap().unwrap();
+        assert_ne!(Some(&next), round.last());
+        round.sort();
+        assert_eq!(round, tips);
+        // The next round started with `next`, finish it.
+        for _ in 0..3 {
+            sev.next_msg(pool_id, "-4003").await.unwrap();
+        }
+    }
+}
+
+#[tokio::test]
+async fn pool_push_test() {
+    let db = common::get_own_db("pool_push").await;
+    let sev = pool::new(db.clone());
+    let polling_ser = polling_msg::new(db.clone());
+    let group_id = group::new(db.clone()).add_group("-4004", "Pool").await.unwrap();
+    let pool_id = sev.add_pool("Daily tip").await.unwrap();
+    let msg_id = msg::new(db.clone()).add_msg(MsgType::Polling, "Tip", "daily").await;
+    sev.toggle_item(pool_id, msg_id).await.unwrap();
+
+    let push_id = polling_ser.add_polling_msg(0, group_id, "* * * * *").await.unwrap();
+    // No message nor pool yet
+    assert!(polling_ser.get_by_id(push_id).await.unwrap().is_none());
+    assert!(polling_ser.set_pool(push_id, pool_id).await.unwrap());
+    let next = Utc::now() + Duration::minutes(1);
+    let slots = polling_ser.get_polling_slots(&next, &next).await.unwrap();
+    let (_, push) = slots.iter().find(|(_, push)| push.id == push_id).unwrap();
+    assert_eq!((push.pool_id, push.msg_title.as_str()), (pool_id, "Daily tip"));
+    assert_eq!(sev.push_count(pool_id).await.unwrap(), 1);
+
+    // The pushes go with the pool
+    assert!(sev.delete_pool(pool_id).await.unwrap());
+    assert!(polling_ser.get_by_id(push_id).await.unwrap().is_none());
+    assert_eq!(sev.push_count(pool_id).await.unwrap(), 0);
+}
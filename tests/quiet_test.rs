@@ -0,0 +1,135 @@
+use chrono::{Duration, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use hivin_bot::service::delivery::DeliveryStatus;
+use hivin_bot::service::drip::Member;
+use hivin_bot::service::msg::MsgType;
+use hivin_bot::service::quiet::{QuietHours, QuietMode};
+use hivin_bot::service::{delivery, group, msg, polling_msg, quiet};
+
+mod common;
+
+fn time(text: &str) -> NaiveTime {
+    NaiveTime::parse_from_str(text, "%H:%M").unwrap()
+}
+
+#[test]
+fn quiet_hours_parse_test() {
+    let quiet = QuietHours::parse(" 22:00-07:00 DEFER welcome ").unwrap().unwrap();
+    assert_eq!((quiet.start, quiet.end), (time("22:00"), time("07:00")));
+    assert_eq!(quiet.mode, QuietMode::Defer);
+    assert!(quiet.batch_welcome);
+    assert_eq!(quiet.to_string(), "22:00-07:00 defer welcome");
+
+    let quiet = QuietHours::parse("12:00-14:00").unwrap().unwrap();
+    assert_eq!((quiet.mode, quiet.batch_welcome), (QuietMode::Skip, false));
+    assert_eq!(QuietHours::parse(&quiet.to_string()).unwrap(), Some(quiet));
+
+    assert_eq!(QuietHours::parse("off").unwrap(), None);
+    assert_eq!(QuietHours::parse("").unwrap(), None);
+    assert!(QuietHours::parse("22:00").is_err());
+    assert!(QuietHours::parse("22:00-25:00").is_err());
+    assert!(QuietHours::parse("07:00-07:00").is_err());
+    assert!(QuietHours::parse("22:00-07:00 later").is_err());
+}
+
+#[test]
+fn quiet_hours_window_test() {
+    // Over midnight, the end is not quiet
+    let night = QuietHours::parse("22:00-07:00").unwrap().unwrap();
+    assert!(night.contains(time("22:00")));
+    assert!(night.contains(time("03:30")));
+    assert!(!night.contains(time("07:00")));
+    assert!(!night.contains(time("12:00")));
+    let noon = QuietHours::parse("12:00-14:00").unwrap().unwrap();
+    assert!(noon.contains(time("13:59")));
+    assert!(!noon.contains(time("23:00")));
+
+    // In the group time zone
+    let tz: Tz = "Asia/Shanghai".parse().unwrap();
+    let at = Utc.with_ymd_and_hms(2026, 11, 1, 15, 30, 0).unwrap(); // 23:30 in Shanghai
+    assert!(night.is_quiet(&at, Some(&tz)));
+    assert_eq!(
+        night.end_after(&at, Some(&tz)),
+        Utc.with_ymd_and_hms(2026, 11, 1, 23, 0, 0).unwrap()
+    );
+    let day = Utc.with_ymd_and_hms(2026, 11, 1, 4, 0, 0).unwrap(); // 12:00 in Shanghai
+    assert!(!night.is_quiet(&day, Some(&tz)));
+    assert_eq!(night.end_after(&day, Some(&tz)), day);
+}
+
+#[tokio::test]
+async fn quiet_hours_group_test() {
+    let db = common::get_own_db("quiet_group").await;
+    let group_ser = group::new(db.clone());
+    let group_db_id = group_ser.add_group("-6001", "Quiet").await.unwrap();
+
+    assert!(group_ser.get_by_id(group_db_id).await.unwrap().quiet().is_none());
+    assert!(group_ser.set_quiet_hours(group_db_id, "bad").await.is_err());
+    assert!(group_ser.set_quiet_hours(group_db_id, "23:00-08:00 Defer").await.unwrap());
+    let info = group_ser.get_by_id(group_db_id).await.unwrap();
+    assert_eq!(info.quiet_hours, "23:00-08:00 defer");
+    assert_eq!(info.quiet().unwrap().mode, QuietMode::Defer);
+    assert!(group_ser.set_quiet_hours(group_db_id, "off").await.unwrap());
+    assert!(group_ser.get_by_id(group_db_id).await.unwrap().quiet().is_none());
+}
+
+#[tokio::test]
+async fn deferred_delivery_test() {
+    let db = common::get_own_db("quiet_delivery").await;
+    let ledger = delivery::new(db.clone());
+    let polling_ser = polling_msg::new(db.clone());
+    let group_db_id = group::new(db.clone()).add_group("-6002", "Night").await.unwrap();
+    let msg_id = msg::new(db.clone()).add_msg(MsgType::Polling, "Hi", "night").await;
+    let push_id = polling_ser.add_polling_msg(msg_id, group_db_id, "2020-01-01 23:00").await.unwrap();
+
+    // Skipped
+    let skipped_at = Utc::now() - Duration::hours(2);
+    ledger.claim(push_id, "-6002", &skipped_at).await.unwrap();
+    assert!(ledger.mark_skipped(push_id, "-6002", &skipped_at, "Quiet hours").await.unwrap());
+    let skipped = ledger.get(push_id, "-6002", &skipped_at).await.unwrap().unwrap();
+    assert_eq!((skipped.status, skipped.error.as_str()), (DeliveryStatus::Skipped, "Quiet hours"));
+
+    // Deferred: pending until the end, taken once
+    let scheduled_at = Utc::now() - Duration::hours(1);
+    let until = Utc::now() + Duration::hours(6);
+    ledger.claim(push_id, "-6002", &scheduled_at).await.unwrap();
+    assert!(ledger.defer(push_id, "-6002", &scheduled_at, &until).await.unwrap());
+    assert!(ledger.due_deferred(&Utc::now()).await.unwrap().is_empty());
+
+    // The expired one-shot push is kept for its deferred slot
+    assert_eq!(polling_ser.delete_expired(&Utc::now()).await.unwrap(), 0);
+
+    let due = ledger.due_deferred(&(until + Duration::minutes(1))).await.unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!((due[0].status, due[0].defer_until), (DeliveryStatus::Pending, Some(until)));
+    assert!(ledger.take_deferred(due[0].id).await.unwrap());
+    assert!(!ledger.take_deferred(due[0].id).await.unwrap());
+    // Still pending while it is being sent
+    assert_eq!(polling_ser.delete_expired(&Utc::now()).await.unwrap(), 0);
+    ledger.mark_sent(push_id, "-6002", &scheduled_at, 1).await.unwrap();
+    assert_eq!(polling_ser.delete_expired(&Utc::now()).await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn held_welcome_test() {
+    let db = common::get_own_db("quiet_welcome").await;
+    let sev = quiet::new(db.clone());
+    let morning = Utc::now() + Duration::hours(5);
+    let alice = Member { user_id: 21, first_name: "Alice", username: None };
+    let bob = Member { user_id: 22, first_name: "Bob", username: Some("bob") };
+    sev.hold_welcome("-6003", &alice, &morning).await.unwrap();
+    sev.hold_welcome("-6003", &bob, &morning).await.unwrap();
+    sev.hold_welcome("-6004", &alice, &morning).await.unwrap();
+
+    assert!(sev.due_welcomes(&Utc::now()).await.unwrap().is_empty());
+    // Bob left before the morning
+    assert_eq!(sev.cancel("-6003", 22).await.unwrap(), 1);
+
+    let due = sev.due_welcomes(&morning).await.unwrap();
+    assert_eq!(
+        due.iter().map(|held| (held.group_id.as_str(), held.user_id)).collect::<Vec<_>>(),
+        vec![("-6003", 21), ("-6004", 21)]
+    );
+    assert!(sev.claim(due[0].id).await.unwrap());
+    assert!(!sev.claim(due[0].id).await.unwrap());
+}
@@ -0,0 +1,67 @@
+use hivin_bot::service::send_queue::{
+    self, backoff, Failure, RateLimiter, SendError, BACKOFF_CAP, CHAT_PER_MINUTE, GLOBAL_PER_SECOND,
+};
+use std::time::{Duration, Instant};
+use teloxide::types::Seconds;
+use teloxide::{ApiError, RequestError};
+
+#[test]
+fn classify_test() {
+    assert_eq!(
+        send_queue::classify(&RequestError::RetryAfter(Seconds::from_seconds(7))),
+        Failure::RetryAfter(Duration::from_secs(7))
+    );
+    assert_eq!(
+        send_queue::classify(&RequestError::Io(std::io::ErrorKind::ConnectionReset.into())),
+        Failure::Transient
+    );
+    assert_eq!(
+        send_queue::classify(&RequestError::Api(ApiError::Unknown("Bad Gateway".to_string()))),
+        Failure::Transient
+    );
+    assert_eq!(send_queue::classify(&RequestError::Api(ApiError::BotKicked)), Failure::Permanent);
+    assert_eq!(send_queue::classify(&RequestError::Api(ApiError::ChatNotFound)), Failure::Permanent);
+}
+
+#[test]
+fn backoff_test() {
+    assert_eq!(backoff(2), Duration::from_secs(2));
+    assert_eq!(backoff(3), Duration::from_secs(4));
+    assert_eq!(backoff(4), Duration::from_secs(8));
+    assert_eq!(backoff(100), BACKOFF_CAP);
+}
+
+#[test]
+fn send_error_test() {
+    let once = SendError { error: RequestError::Api(ApiError::BotKicked), permanent: true, attempts: 1 };
+    assert!(!once.to_string().contains("attempts"));
+    let retried = SendError { error: RequestError::Api(ApiError::BotKicked), permanent: false, attempts: 5 };
+    assert!(retried.to_string().ends_with("(after 5 attempts)"));
+}
+
+#[test]
+fn rate_limit_chat_test() {
+    let mut limiter = RateLimiter::default();
+    let start = Instant::now();
+    // One per second, within the global limit
+    for i in 0..CHAT_PER_MINUTE as u64 {
+        assert!(limiter.reserve(-1, start + Duration::from_secs(i)).is_zero());
+    }
+    let now = start + Duration::from_secs(CHAT_PER_MINUTE as u64);
+    // The chat waits until the first send leaves the minute
+    assert_eq!(limiter.reserve(-1, now), Duration::from_secs(60 - CHAT_PER_MINUTE as u64));
+    // Other chats do not
+    assert!(limiter.reserve(-2, now).is_zero());
+    assert!(limiter.reserve(-1, start + Duration::from_secs(60)).is_zero());
+}
+
+#[test]
+fn rate_limit_global_test() {
+    let mut limiter = RateLimiter::default();
+    let now = Instant::now();
+    for chat_id in 0..GLOBAL_PER_SECOND as i64 {
+        assert!(limiter.reserve(chat_id, now).is_zero());
+    }
+    assert_eq!(limiter.reserve(-100, now), Duration::from_secs(1));
+    assert!(limiter.reserve(-100, now + Duration::from_secs(1)).is_zero());
+}
@@ -0,0 +1,19 @@
+use hivin_bot::service::msg::MsgContent;
+use hivin_bot::State;
+use teloxide::dispatching::dialogue::serializer::{Json, Serializer};
+
+#[test]
+fn stored_state_test() {
+    // Stored before the media messages
+    let state: State = Serializer::<State>::deserialize(&Json, br#"{"AddPollingTitle":"Hello"}"#).unwrap();
+    assert!(matches!(state, State::AddPollingTitle(text) if text == "Hello"));
+    let state: State =
+        Serializer::<State>::deserialize(&Json, br#"{"GroupPushMsg":{"group_db_id":1,"group_name":"G","msg_db_id":2}}"#)
+            .unwrap();
+    assert!(matches!(state, State::GroupPushMsg { pool_id: 0, .. }));
+
+    let state = State::AddPollingContentTitle(MsgContent::text("Hello"));
+    let stored = Serializer::<State>::serialize(&Json, &state).unwrap();
+    let state: State = Serializer::<State>::deserialize(&Json, &stored).unwrap();
+    assert!(matches!(state, State::AddPollingContentTitle(content) if content.text == "Hello"));
+}
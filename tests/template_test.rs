@@ -0,0 +1,37 @@
+use chrono::NaiveDateTime;
+use hivin_bot::service::msg::template::{self, TemplateContext};
+use hivin_bot::service::msg::MsgType;
+
+#[test]
+fn validate_test() {
+    assert!(template::validate("Hi {mention}, welcome to {group_name}", &MsgType::Welcome).is_ok());
+    assert!(template::validate("Today is {weekday} {date}", &MsgType::Polling).is_ok());
+    assert!(template::validate("No placeholders, {not one} or {}", &MsgType::Polling).is_ok());
+
+    assert!(template::validate("Hi {nickname}", &MsgType::Welcome).is_err());
+    // No member for the scheduled message
+    assert!(template::validate("Hi {first_name}", &MsgType::Polling).is_err());
+}
+
+#[test]
+fn render_test() {
+    let ctx = TemplateContext {
+        user_id: Some(42),
+        first_name: "<Tom>".to_string(),
+        username: None,
+        group_name: "Rust & Go".to_string(),
+        member_count: Some(128),
+        now: NaiveDateTime::parse_from_str("2026-11-02 09:00", "%Y-%m-%d %H:%M").ok(),
+    };
+
+    assert_eq!(
+        template::render("Hi {mention}, {username}!", &ctx),
+        "Hi <a href=\"tg://user?id=42\">&lt;Tom&gt;</a>, &lt;Tom&gt;!"
+    );
+    assert_eq!(
+        template::render("<b>{group_name}</b> {member_count} {date} {weekday}", &ctx),
+        "<b>Rust &amp; Go</b> 128 2026-11-02 Monday"
+    );
+    assert_eq!(template::render("{a b} {unknown}", &ctx), "{a b} {unknown}");
+    assert!(template::uses("{member_count} members", "member_count"));
+}
@@ -36,4 +36,21 @@ async fn all_admin() {
     for admin in admins {
         println!("{:?}", admin);
     }
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn notify_failures_test() {
+    let user = user::new(common::get_own_db("notify_failures").await);
+    user.add_admin("30001", "alice").await;
+    user.add_admin("30002", "bob").await;
+    let recipients = |admins: Vec<user::Admin>| {
+        admins.into_iter().map(|admin| admin.user_id).collect::<Vec<String>>()
+    };
+    assert_eq!(recipients(user.failure_recipients().await), vec!["30001", "30002"]);
+
+    // Opted out
+    assert_eq!(user.toggle_notify_failures("30002").await, Some(false));
+    assert_eq!(recipients(user.failure_recipients().await), vec!["30001"]);
+    assert_eq!(user.toggle_notify_failures("30002").await, Some(true));
+    assert_eq!(user.toggle_notify_failures("404").await, None);
+}